@@ -1,16 +1,22 @@
-use crate::config::Config;
+use crate::config::{Config, MatrixListenerConfig, MatrixResponderConfig, WebhookListenerConfig};
 use crate::database::insert_or_update;
-use crate::database::models::{AccessToken, CorrectionTimeCooldown, LastSync};
+use crate::database::models::{
+    AccessToken, CorrectionTimeCooldown, GithubRateLimit, GithubSearchCache, GroupPing, LastSync,
+    ModerationRecord, MutedUser, PolicyRule, ResponseRecord, RoomPowerLevels, ScheduledExpiry,
+    TextExpansion,
+};
 use crate::services::matrix::listener::MatrixListener;
 use crate::services::matrix::responder::MatrixResponder;
+use crate::services::scheduler::Scheduler;
 use crate::services::webhook::listener::WebhookListener;
 use anyhow::Context;
 use native_db::{Database, DatabaseBuilder};
 use std::env;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::{mpsc, watch};
-use tracing::{info, trace};
+use tracing::{error, info, trace, warn};
 
 pub async fn init() -> anyhow::Result<()> {
     // Load config data
@@ -34,6 +40,36 @@ pub async fn init() -> anyhow::Result<()> {
     builder
         .define::<CorrectionTimeCooldown>()
         .context("Unable to load correction time cooldown database model")?;
+    builder
+        .define::<GithubRateLimit>()
+        .context("Unable to load github rate limit database model")?;
+    builder
+        .define::<GithubSearchCache>()
+        .context("Unable to load github search cache database model")?;
+    builder
+        .define::<TextExpansion>()
+        .context("Unable to load text expansion database model")?;
+    builder
+        .define::<GroupPing>()
+        .context("Unable to load group ping database model")?;
+    builder
+        .define::<RoomPowerLevels>()
+        .context("Unable to load room power levels database model")?;
+    builder
+        .define::<ModerationRecord>()
+        .context("Unable to load moderation record database model")?;
+    builder
+        .define::<ScheduledExpiry>()
+        .context("Unable to load scheduled expiry database model")?;
+    builder
+        .define::<PolicyRule>()
+        .context("Unable to load policy rule database model")?;
+    builder
+        .define::<ResponseRecord>()
+        .context("Unable to load response record database model")?;
+    builder
+        .define::<MutedUser>()
+        .context("Unable to load muted user database model")?;
     let static_builder: &'static DatabaseBuilder = Box::leak(builder);
     //open db
     let db = Box::new(
@@ -95,16 +131,27 @@ pub async fn init() -> anyhow::Result<()> {
     let matrix_responder_client = matrix_listener_client.clone();
     let (matrix_tx, matrix_rx) = mpsc::channel(8);
     let webhook_tx = matrix_tx.clone();
+    let scheduler_tx = matrix_tx.clone();
+
+    // Create config channels so a reloaded config can be swapped into running threads
+    let (matrix_config_tx, matrix_config_rx) =
+        watch::channel(Arc::new(MatrixListenerConfig::new(&config)));
+    let (responder_config_tx, responder_config_rx) =
+        watch::channel(Arc::new(MatrixResponderConfig::new(&config)));
+    let (webhook_config_tx, webhook_config_rx) =
+        watch::channel(Arc::new(WebhookListenerConfig::new(&config)));
 
     // Create thread structures
-    let mut matrix_listener = MatrixListener::new(&config, matrix_tx, &static_db)?;
-    let mut matrix_responder = MatrixResponder::new(matrix_rx)?;
-    let webhook_listener = WebhookListener::new(&config, webhook_tx);
+    let mut matrix_listener = MatrixListener::new(matrix_config_rx, matrix_tx, static_db)?;
+    let mut matrix_responder = MatrixResponder::new(matrix_rx, responder_config_rx, static_db)?;
+    let webhook_listener = WebhookListener::new(webhook_config_rx, webhook_tx, static_db);
+    let scheduler = Scheduler::new(scheduler_tx, static_db);
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let matrix_listener_shutdown_rx = shutdown_rx.clone();
     let matrix_responder_shutdown_rx = shutdown_rx.clone();
     let webhook_listener_shutdown_rx = shutdown_rx.clone();
+    let scheduler_shutdown_rx = shutdown_rx.clone();
 
     // Spawn threads from thread structures, save their cached data when they exit
     let matrix_listener_task = tokio::spawn(async move {
@@ -115,6 +162,9 @@ pub async fn init() -> anyhow::Result<()> {
     let webhook_listener_task = tokio::spawn(async move {
         webhook_listener.start(webhook_listener_shutdown_rx).await;
     });
+    let scheduler_task = tokio::spawn(async move {
+        scheduler.start(scheduler_shutdown_rx).await;
+    });
     let matrix_responder_task = tokio::spawn(async move {
         matrix_responder
             .start(matrix_responder_client, matrix_responder_shutdown_rx)
@@ -133,6 +183,7 @@ pub async fn init() -> anyhow::Result<()> {
             },
             _ = hangup.recv() => {
                 trace!("Received SIGHUP on main thread");
+                reload_config(&matrix_config_tx, &responder_config_tx, &webhook_config_tx);
             }
         };
     }
@@ -141,7 +192,46 @@ pub async fn init() -> anyhow::Result<()> {
     // Join threads to main thread
     matrix_listener_task.await?;
     webhook_listener_task.await?;
+    scheduler_task.await?;
     matrix_responder_task.await?;
 
     Ok(())
 }
+
+/// Loads config.toml again and swaps the new config into the running threads
+///
+/// Keeps the current config if the new one is invalid. Matrix login settings are only used at
+/// startup, so changes to them are ignored until the bot is restarted
+fn reload_config(
+    matrix_config_tx: &watch::Sender<Arc<MatrixListenerConfig>>,
+    responder_config_tx: &watch::Sender<Arc<MatrixResponderConfig>>,
+    webhook_config_tx: &watch::Sender<Arc<WebhookListenerConfig>>,
+) {
+    let config = match Config::load_config() {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Unable to reload config, keeping current config. Error is {:#}",
+                e
+            );
+            return;
+        }
+    };
+    let mut matrix_config = MatrixListenerConfig::new(&config);
+    {
+        let current = matrix_config_tx.borrow();
+        if current.mx_url != matrix_config.mx_url
+            || current.mx_uname != matrix_config.mx_uname
+            || current.mx_pass != matrix_config.mx_pass
+        {
+            warn!("Matrix authentication settings changed. Restart the bot to apply them");
+            matrix_config.mx_url = current.mx_url.clone();
+            matrix_config.mx_uname = current.mx_uname.clone();
+            matrix_config.mx_pass = current.mx_pass.clone();
+        }
+    }
+    matrix_config_tx.send_replace(Arc::new(matrix_config));
+    responder_config_tx.send_replace(Arc::new(MatrixResponderConfig::new(&config)));
+    webhook_config_tx.send_replace(Arc::new(WebhookListenerConfig::new(&config)));
+    info!("Reloaded config");
+}
@@ -1,4 +1,11 @@
 //! Structs and functions for loading and saving configuration and storage data.
+//!
+//! Relevant tests are in a test submodule
+//!
+//! Tests cover loading settings that need more than a lookup, such as group alias cycles and roles
+
+#[cfg(test)]
+mod tests;
 
 // TODO: Implement Option type enum that will encapsulate the logic and potential states of config options that
 // TODO: are disable-able. This would be to prevent improper use down the line, whereas right now I pass around
@@ -6,9 +13,11 @@
 // TODO: This problem has gotten worse recently, as now not all empty items mean disabled
 // TODO: and as such, the type system needs to come to the rescue
 
-use anyhow::{anyhow, Context};
+use crate::events::{Event, NOTIFIABLE_EVENTS};
+use anyhow::{anyhow, bail, Context};
 use axum::http::Uri;
 use reqwest::header::HeaderValue;
+use reqwest::Url;
 use ruma::{OwnedRoomId, OwnedUserId, UserId};
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
@@ -17,12 +26,20 @@ use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 
 /// Constant representing the crate name.
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 /// Constant representing the crate version.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Default time found issues and pulls are cached for.
+const DEFAULT_SEARCH_CACHE_TTL: Duration = Duration::from_secs(600);
+const DEFAULT_COMMIT_SEARCH_SIGIL: char = '@';
+/// Default shortest time between welcome messages in a room.
+const DEFAULT_WELCOME_COOLDOWN: Duration = Duration::from_secs(300);
+/// Name of the role granting every capability to the users in authorized_users.
+const AUTHORIZED_USERS_ROLE: &str = "authorized_users";
 
 #[derive(Debug)]
 /// Configuration struct used at runtime. Loaded from RawConfig and its constituent parts.
@@ -37,6 +54,10 @@ pub struct MatrixListenerConfig {
     pub mx_pass: Box<str>,
     /// Github access token as string.
     pub gh_access_token: Box<str>,
+    /// How long found issues and pulls are cached before being searched again. Zero disables caching.
+    pub gh_search_cache_ttl: Duration,
+    /// Character between a repo and a commit sha that requests a commit search, such as '@' in "jf@abc1234".
+    pub commit_search_sigil: char,
     /// Bool used to determine if unit conversions will be supported from plain text messages.
     pub enable_unit_conversions: bool,
     /// Bool used to determine if the corrections feature is enabled or not.
@@ -51,14 +72,20 @@ pub struct MatrixListenerConfig {
     pub correction_exclusion: HashSet<OwnedRoomId>,
     /// List of all words that can be used to link URLs.
     pub linkers: HashSet<Box<str>>,
-    /// List of matrix users that can invite the bot to rooms.
-    pub admins: HashSet<OwnedUserId>,
+    /// Hashmap containing role name as key and the users and capabilities of the role as the value.
+    pub roles: HashMap<Box<str>, Role>,
     /// List of rooms in which help function can be used.
     pub help_rooms: HashSet<OwnedRoomId>,
     /// List of rooms in which ban function will apply.
     pub ban_rooms: HashSet<OwnedRoomId>,
-    /// Hashmap containing short name for a repo as a key and the org/repo as a value.
-    pub repos: HashMap<Box<str>, Box<str>>,
+    /// List of rooms whose ban rules are applied to the ban rooms.
+    pub policy_rooms: HashSet<OwnedRoomId>,
+    /// Hashmap containing short name for a repo as a key and the repo and forge it is hosted on as a value.
+    pub repos: SearchableRepos,
+    /// List of rooms in which pasted issue, pull, commit, and release URLs will not be unfurled.
+    pub url_unfurl_exclusion: HashSet<OwnedRoomId>,
+    /// List of rooms in which every response is sent in a thread.
+    pub thread_rooms: HashSet<OwnedRoomId>,
     /// Hashmap containing searched key and matching URL for linking.
     pub links: HashMap<Box<str>, Uri>,
     /// List of all text expansions.
@@ -67,12 +94,203 @@ pub struct MatrixListenerConfig {
     pub user_agent: HeaderValue,
     /// Hashmap containing group ping name as key and list of user IDs as the value.
     pub group_pings: HashMap<Box<str>, HashSet<OwnedUserId>>,
-    /// Hashset containing list of users that can initiate group pings
+    /// Hashmap containing group ping name as key and the groups it aliases as the value.
+    pub group_aliases: GroupAliases,
+    /// Hashset containing list of users in any group, who can initiate group pings without a role
     pub group_ping_users: HashSet<OwnedUserId>,
+    /// Anti-spam rules, disabled if not supplied.
+    pub anti_spam: Option<AntiSpam>,
+    /// Hashmap containing room as key and the message welcoming users who join it as the value.
+    pub welcome_messages: HashMap<OwnedRoomId, Box<str>>,
+    /// Shortest time between welcome messages in a room. Users joining in between are welcomed together.
+    pub welcome_cooldown: Duration,
+}
+
+/// Configuration struct used at runtime by the matrix responder.
+pub struct MatrixResponderConfig {
+    /// Room every moderation action is posted to.
+    pub audit_room: Option<OwnedRoomId>,
+    /// Room permanent bans are published to as policy rules.
+    pub policy_publish_room: Option<OwnedRoomId>,
 }
 
+/// Configuration struct used at runtime by the webhook listener.
 pub struct WebhookListenerConfig {
+    /// Token required in the `X-Webhook-Token` header of message requests.
     pub token: Box<str>,
+    /// Hashmap containing lowercase owner/repo as key and its webhook settings as the value.
+    pub github_webhooks: HashMap<Box<str>, GithubWebhook>,
+}
+
+#[derive(Clone, Debug)]
+/// A named set of capabilities granted to a list of users and users with a high enough power level.
+pub struct Role {
+    /// Capabilities granted to users with the role.
+    pub capabilities: HashSet<Capability>,
+    /// List of matrix users that have the role.
+    pub users: HashSet<OwnedUserId>,
+    /// Power level at or above which users have the role in a room.
+    pub power_level: Option<i64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// An action that requires permission, granted to users by roles.
+pub enum Capability {
+    /// Invite the bot to rooms.
+    Invite,
+    /// Ban and unban users with the !ban and !unban commands.
+    Ban,
+    /// Kick users with the !kick command.
+    Kick,
+    /// Mute and unmute users with the !mute and !unmute commands.
+    Mute,
+    /// Redact messages with the !redact and !purge commands.
+    Redact,
+    /// View the moderation history of users with the !modlog command.
+    ViewModlog,
+    /// Ping groups with %group.
+    GroupPing,
+    /// Create and delete groups and add or remove other users with the !group command.
+    ManageGroups,
+    /// Create, close, label, and comment on github issues with the !issue command.
+    ManageIssues,
+    /// Add, edit, and remove text expansions with the !expand command.
+    EditExpansions,
+}
+
+impl Capability {
+    /// Every capability, granted to authorized users.
+    pub const ALL: [Capability; 10] = [
+        Capability::Invite,
+        Capability::Ban,
+        Capability::Kick,
+        Capability::Mute,
+        Capability::Redact,
+        Capability::ViewModlog,
+        Capability::GroupPing,
+        Capability::ManageGroups,
+        Capability::ManageIssues,
+        Capability::EditExpansions,
+    ];
+}
+
+#[derive(Clone, Debug)]
+/// Rules that flag spam in chat and what is done to the users who send it.
+pub struct AntiSpam {
+    /// List of rooms the rules apply in. Applies in every room if empty.
+    pub rooms: HashSet<OwnedRoomId>,
+    /// How long users flagged with the mute action are muted for, forever if not supplied.
+    pub mute_duration: Option<Duration>,
+    /// Limit on messages sent by a user in a single room within the period.
+    pub flood: Option<SpamLimit>,
+    /// Limit on users mentioned in a single message. The period is unused.
+    pub mentions: Option<SpamLimit>,
+    /// Limit on identical messages sent by a user across all rooms within the period.
+    pub repeats: Option<SpamLimit>,
+    /// Domains that can not be linked, including their subdomains.
+    pub blocked_domains: Option<BlockedDomains>,
+}
+
+#[derive(Clone, Debug)]
+/// A limit that flags a message once it is reached.
+pub struct SpamLimit {
+    /// Count at which a message is flagged.
+    pub limit: usize,
+    /// Period in which the count is taken.
+    pub period: Duration,
+    /// Action taken against the sender of a flagged message.
+    pub action: SpamAction,
+}
+
+#[derive(Clone, Debug)]
+/// A list of domains that flag any message linking to them.
+pub struct BlockedDomains {
+    /// List of lowercase domains.
+    pub domains: HashSet<Box<str>>,
+    /// Action taken against the sender of a flagged message.
+    pub action: SpamAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Action taken against the sender of a message flagged as spam.
+pub enum SpamAction {
+    /// Post a warning mentioning the sender.
+    Warn,
+    /// Redact the flagged message.
+    Redact,
+    /// Mute the sender in the ban rooms, or the current room if there are none.
+    Mute,
+    /// Ban the sender from the ban rooms, or the current room if there are none.
+    Ban,
+}
+
+/// Searchable repos keyed by the name they are referred to by in chat.
+pub type SearchableRepos = HashMap<Box<str>, SearchableRepo>;
+
+/// Group names mapped to the names of the groups they alias
+pub type GroupAliases = HashMap<Box<str>, HashSet<Box<str>>>;
+
+#[derive(Clone, Debug)]
+/// A repo that can be searched for issues and pulls.
+pub struct SearchableRepo {
+    /// Path of the repo on its forge, such as owner/repo or group/subgroup/repo.
+    pub repo: Box<str>,
+    /// Forge the repo is hosted on.
+    pub forge: Forge,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A forge that hosts searchable repos.
+pub enum Forge {
+    /// github.com, searched with the configured github access token.
+    Github,
+    /// A Gitea or Forgejo instance.
+    Gitea {
+        /// Base URL of the instance.
+        url: Url,
+        /// Optional access token for private repos.
+        token: Option<Box<str>>,
+    },
+    /// A GitLab instance.
+    Gitlab {
+        /// Base URL of the instance.
+        url: Url,
+        /// Optional access token for private repos.
+        token: Option<Box<str>>,
+    },
+}
+
+#[derive(Clone, Debug)]
+/// Settings for a single repo that sends GitHub webhooks to the bot.
+pub struct GithubWebhook {
+    /// Secret used to sign payloads sent by GitHub.
+    pub secret: Box<str>,
+    /// List of rooms that all notifications for this repo will be posted to.
+    pub rooms: HashSet<OwnedRoomId>,
+    /// List of routes that post a subset of notifications for this repo to specific rooms.
+    pub routes: Vec<GithubWebhookRoute>,
+}
+
+#[derive(Clone, Debug)]
+/// A route that posts matching GitHub notifications to a list of rooms.
+pub struct GithubWebhookRoute {
+    /// List of event filters, any of which can match for the route to apply.
+    pub events: Vec<GithubEventFilter>,
+    /// List of rooms that matching notifications will be posted to.
+    pub rooms: HashSet<OwnedRoomId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Filter for GitHub events in the form of `event` or `event.qualifier`.
+///
+/// The qualifier is the action (`issues.opened`), branch (`push.master`), or ref type (`create.tag`) of the event.
+pub struct GithubEventFilter {
+    /// Name of the event as sent by GitHub.
+    pub event: Box<str>,
+    /// Optional qualifier that must also match.
+    pub qualifier: Option<Box<str>>,
 }
 
 #[derive(Debug)]
@@ -88,6 +306,10 @@ pub struct Config {
     pub mx_pass: Box<str>,
     /// Github access token as string.
     gh_access_token: Box<str>,
+    /// How long found issues and pulls are cached before being searched again. Zero disables caching.
+    gh_search_cache_ttl: Duration,
+    /// Character between a repo and a commit sha that requests a commit search, such as '@' in "jf@abc1234".
+    commit_search_sigil: char,
     /// Bool used to determine if unit conversions will be supported from plain text messages.
     enable_unit_conversions: bool,
     /// Bool used to determine if the corrections feature is enabled or not.
@@ -102,14 +324,24 @@ pub struct Config {
     correction_exclusion: HashSet<OwnedRoomId>,
     /// List of all words that can be used to link URLs.
     linkers: HashSet<Box<str>>,
-    /// List of matrix users that can invite the bot to rooms.
-    admins: HashSet<OwnedUserId>,
+    /// Hashmap containing role name as key and the users and capabilities of the role as the value.
+    roles: HashMap<Box<str>, Role>,
     /// List of matrix rooms that the help function can be used in
     help_rooms: HashSet<OwnedRoomId>,
     /// List of matrix rooms in which bans will be applied
     ban_rooms: HashSet<OwnedRoomId>,
-    /// Hashmap containing short name for a repo as a key and the org/repo as a value.
-    repos: HashMap<Box<str>, Box<str>>,
+    /// Room every moderation action is posted to.
+    audit_room: Option<OwnedRoomId>,
+    /// List of rooms whose ban rules are applied to the ban rooms.
+    policy_rooms: HashSet<OwnedRoomId>,
+    /// Room permanent bans are published to as policy rules.
+    policy_publish_room: Option<OwnedRoomId>,
+    /// Hashmap containing short name for a repo as a key and the repo and forge it is hosted on as a value.
+    repos: SearchableRepos,
+    /// List of rooms in which pasted issue, pull, commit, and release URLs will not be unfurled.
+    url_unfurl_exclusion: HashSet<OwnedRoomId>,
+    /// List of rooms in which every response is sent in a thread.
+    thread_rooms: HashSet<OwnedRoomId>,
     /// Hashmap containing searched key and matching URL for linking.
     links: HashMap<Box<str>, Uri>,
     /// List of all text expansions.
@@ -118,9 +350,20 @@ pub struct Config {
     user_agent: HeaderValue,
     /// Hashmap containing group ping name as key and list of user IDs as the value.
     group_pings: HashMap<Box<str>, HashSet<OwnedUserId>>,
-    /// Hashset containing list of users that can initiate group pings
+    /// Hashmap containing group ping name as key and the groups it aliases as the value.
+    group_aliases: GroupAliases,
+    /// Hashset containing list of users in any group, who can initiate group pings without a role
     group_ping_users: HashSet<OwnedUserId>,
-    pub webhook_token: Box<str>,
+    /// Anti-spam rules, disabled if not supplied.
+    anti_spam: Option<AntiSpam>,
+    /// Hashmap containing room as key and the message welcoming users who join it as the value.
+    welcome_messages: HashMap<OwnedRoomId, Box<str>>,
+    /// Shortest time between welcome messages in a room. Users joining in between are welcomed together.
+    welcome_cooldown: Duration,
+    /// Token required in the `X-Webhook-Token` header of message requests.
+    webhook_token: Box<str>,
+    /// Hashmap containing lowercase owner/repo as key and its webhook settings as the value.
+    github_webhooks: HashMap<Box<str>, GithubWebhook>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -134,25 +377,39 @@ pub struct RawConfig {
     matrix_authentication: RawMatrixAuthentication,
     /// Contains struct for all github authentication data.
     github_authentication: Option<RawGithubAuthentication>,
-    /// Hashmap containing short name for a repo as a key and the org/repo as a value.
-    searchable_repos: Option<HashMap<String, String>>,
+    /// Hashmap containing short name for a repo as a key and the repo settings as a value.
+    searchable_repos: Option<HashMap<String, RawSearchableRepo>>,
     /// Hashmap containing searched key and matching URL for linking.
     linkable_urls: Option<HashMap<String, String>>,
     /// List of all text expansions.
     text_expansion: Option<HashMap<String, String>>,
     /// Hashmap containing group ping name as key and list of user IDs as the value.
     group_pings: Option<HashMap<String, Vec<String>>>,
+    /// Hashmap containing role name as key and the role settings as the value.
+    roles: Option<HashMap<String, RawRole>>,
+    /// Hashmap containing owner/repo as key and its webhook settings as the value.
+    github_webhooks: Option<HashMap<String, RawGithubWebhook>>,
+    /// Contains struct for all anti-spam rules.
+    anti_spam: Option<RawAntiSpam>,
+    /// Contains struct for all welcome message settings.
+    welcome: Option<RawWelcome>,
 }
 
 #[derive(Debug, Deserialize)]
 /// Struct that contains raw general configuration data.
 struct RawGeneral {
-    /// List of matrix users that can invite the bot to rooms.
+    /// List of matrix users that are granted every capability.
     authorized_users: Option<HashSet<OwnedUserId>>,
     /// List of rooms the help function can be used in.
     help_rooms: Option<HashSet<OwnedRoomId>>,
     /// List of rooms the ban function will apply to
     ban_rooms: Option<HashSet<OwnedRoomId>>,
+    /// Room every moderation action is posted to.
+    audit_room: Option<OwnedRoomId>,
+    /// List of policy rooms whose ban rules are applied to the ban rooms.
+    policy_rooms: Option<HashSet<OwnedRoomId>>,
+    /// Room permanent bans are published to as policy rules.
+    policy_publish_room: Option<OwnedRoomId>,
     /// Bool used to determine if unit conversions will be supported from plain text messages.
     enable_unit_conversions: bool,
     /// Bool used to determine if the corrections feature is enabled or not.
@@ -169,10 +426,71 @@ struct RawGeneral {
     correction_exclusion: Option<HashSet<OwnedRoomId>>,
     /// List of all words that can be used to link URLs.
     link_matchers: Option<HashSet<String>>,
+    /// List of all rooms to be excluded from unfurling pasted issue, pull, commit, and release URLs.
+    url_unfurl_exclusion: Option<HashSet<OwnedRoomId>>,
+    /// List of rooms in which every response is sent in a thread.
+    thread_rooms: Option<HashSet<OwnedRoomId>>,
 
     webhook_token: String,
 }
 
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw settings for a single role.
+struct RawRole {
+    /// Capabilities granted to users with the role.
+    capabilities: HashSet<Capability>,
+    /// List of matrix users that have the role.
+    users: Option<HashSet<OwnedUserId>>,
+    /// Power level at or above which users have the role in a room.
+    power_level: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw welcome message config data.
+struct RawWelcome {
+    /// Shortest time between welcome messages in a room, such as "5m". Defaults to 5 minutes.
+    cooldown: Option<String>,
+    /// Hashmap containing room as key and the message welcoming users who join it as the value.
+    messages: HashMap<OwnedRoomId, String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw anti-spam config data.
+struct RawAntiSpam {
+    /// List of rooms the rules apply in.
+    rooms: Option<HashSet<OwnedRoomId>>,
+    /// How long users flagged with the mute action are muted for, such as "1h".
+    mute_duration: Option<String>,
+    /// Limit on messages sent by a user in a single room within the period.
+    flood: Option<RawSpamLimit>,
+    /// Limit on users mentioned in a single message.
+    mentions: Option<RawSpamLimit>,
+    /// Limit on identical messages sent by a user across all rooms within the period.
+    repeats: Option<RawSpamLimit>,
+    /// Domains that can not be linked.
+    blocked_domains: Option<RawBlockedDomains>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw settings for a single anti-spam limit.
+struct RawSpamLimit {
+    /// Count at which a message is flagged.
+    limit: usize,
+    /// Period in which the count is taken, such as "10s".
+    period: Option<String>,
+    /// Action taken against the sender of a flagged message.
+    action: SpamAction,
+}
+
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw settings for blocked link domains.
+struct RawBlockedDomains {
+    /// List of domains.
+    domains: HashSet<String>,
+    /// Action taken against the sender of a flagged message.
+    action: SpamAction,
+}
+
 #[derive(Debug, Deserialize)]
 /// Struct that contains raw matrix authentication config data.
 struct RawMatrixAuthentication {
@@ -189,6 +507,62 @@ struct RawMatrixAuthentication {
 struct RawGithubAuthentication {
     /// Access token as string.
     access_token: String,
+    /// How long found issues and pulls are cached, such as "10m". Defaults to 10 minutes.
+    search_cache_ttl: Option<String>,
+    /// Character between a repo and a commit sha that requests a commit search. Defaults to '@'.
+    commit_search_sigil: Option<char>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+/// Enum that contains raw searchable repo config data.
+enum RawSearchableRepo {
+    /// A github repo in the form of "owner/repo".
+    Github(String),
+    /// A repo with explicit forge settings.
+    Forge {
+        /// Path of the repo on its forge.
+        repo: String,
+        /// Kind of forge hosting the repo.
+        forge: RawForgeKind,
+        /// Base URL of the forge. Required for all forges but github.
+        url: Option<String>,
+        /// Optional access token for the forge.
+        token: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+/// Enum of supported forge kinds in config data.
+enum RawForgeKind {
+    /// github.com
+    Github,
+    /// Gitea or Forgejo.
+    #[serde(alias = "forgejo")]
+    Gitea,
+    /// GitLab.
+    Gitlab,
+}
+
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw github webhook config data for a single repo.
+struct RawGithubWebhook {
+    /// Secret used to sign payloads sent by GitHub.
+    secret: String,
+    /// List of rooms that all notifications for this repo will be posted to.
+    rooms: Option<HashSet<OwnedRoomId>>,
+    /// List of routes that post a subset of notifications for this repo to specific rooms.
+    routes: Option<Vec<RawGithubWebhookRoute>>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Struct that contains raw github webhook routing config data.
+struct RawGithubWebhookRoute {
+    /// List of event filters in the form of "event" or "event.qualifier".
+    events: Vec<String>,
+    /// List of rooms that matching notifications will be posted to.
+    rooms: HashSet<OwnedRoomId>,
 }
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
@@ -221,6 +595,8 @@ impl MatrixListenerConfig {
             mx_uname: config.mx_uname.clone(),
             mx_pass: config.mx_pass.clone(),
             gh_access_token: config.gh_access_token.clone(),
+            gh_search_cache_ttl: config.gh_search_cache_ttl,
+            commit_search_sigil: config.commit_search_sigil,
             enable_unit_conversions: config.enable_unit_conversions,
             enable_corrections: config.enable_corrections,
             unit_conversion_exclusion: config.unit_conversion_exclusion.clone(),
@@ -228,15 +604,91 @@ impl MatrixListenerConfig {
             correction_text: config.correction_text.clone(),
             correction_exclusion: config.correction_exclusion.clone(),
             linkers: config.linkers.clone(),
-            admins: config.admins.clone(),
+            roles: config.roles.clone(),
             help_rooms: config.help_rooms.clone(),
             ban_rooms: config.ban_rooms.clone(),
+            policy_rooms: config.policy_rooms.clone(),
             repos: config.repos.clone(),
+            url_unfurl_exclusion: config.url_unfurl_exclusion.clone(),
+            thread_rooms: config.thread_rooms.clone(),
             links: config.links.clone(),
             text_expansions: config.text_expansions.clone(),
             user_agent: config.user_agent.clone(),
             group_pings: config.group_pings.clone(),
+            group_aliases: config.group_aliases.clone(),
             group_ping_users: config.group_ping_users.clone(),
+            anti_spam: config.anti_spam.clone(),
+            welcome_messages: config.welcome_messages.clone(),
+            welcome_cooldown: config.welcome_cooldown,
+        }
+    }
+}
+
+impl GithubWebhook {
+    /// Returns all rooms a notification for the supplied event should be posted to
+    pub fn rooms_for(&self, event: &Event) -> HashSet<OwnedRoomId> {
+        let mut rooms = self.rooms.clone();
+        for route in &self.routes {
+            if route.events.iter().any(|f| f.matches(event)) {
+                rooms.extend(route.rooms.iter().cloned());
+            }
+        }
+        rooms
+    }
+}
+
+impl GithubEventFilter {
+    /// Returns `true` if the supplied event matches this filter
+    pub fn matches(&self, event: &Event) -> bool {
+        if *self.event != *event.name() {
+            return false;
+        }
+        match &self.qualifier {
+            Some(q) => event.qualifier() == Some(&**q),
+            None => true,
+        }
+    }
+}
+
+impl std::str::FromStr for GithubEventFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (event, qualifier) = match s.split_once('.') {
+            Some((e, q)) => (e, Some(q)),
+            None => (s, None),
+        };
+        if !NOTIFIABLE_EVENTS.contains(&event) {
+            return Err(anyhow!(
+                "Unsupported github event {}. Supported events are {}",
+                event,
+                NOTIFIABLE_EVENTS.join(", ")
+            ));
+        }
+        if qualifier == Some("") {
+            return Err(anyhow!("Github event filter {} has an empty qualifier", s));
+        }
+        Ok(GithubEventFilter {
+            event: event.to_string().into_boxed_str(),
+            qualifier: qualifier.map(|q| q.to_string().into_boxed_str()),
+        })
+    }
+}
+
+impl MatrixResponderConfig {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            audit_room: config.audit_room.clone(),
+            policy_publish_room: config.policy_publish_room.clone(),
+        }
+    }
+}
+
+impl WebhookListenerConfig {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            token: config.webhook_token.clone(),
+            github_webhooks: config.github_webhooks.clone(),
         }
     }
 }
@@ -263,17 +715,27 @@ impl Config {
         file.read_to_string(&mut contents)
             .with_context(|| format!("Unable to read file contents at {:?}", path))?;
         let toml: RawConfig = toml::from_str(&contents).context("Invalid toml")?;
+        Self::from_raw(toml)
+    }
 
+    /// Loads bot config from the on disk configuration data
+    pub fn from_raw(toml: RawConfig) -> anyhow::Result<Self> {
         // Set variables and exit/error if set improperly
         let (repos, gh_access_token) = load_github_settings(&toml)?;
+        let gh_search_cache_ttl = load_github_search_cache_settings(&toml)?;
+        let commit_search_sigil = load_commit_search_settings(&toml)?;
         let (linkers, links) = load_linker_settings(&toml)?;
         let text_expansions = load_text_expansions(&toml);
         let unit_conversion_exclusion = load_unit_conversion_settings(&toml);
         let (incorrect_spellings, correction_text, correction_exclusion) =
             load_spell_correct_settings(&toml)?;
-        let admins = load_admin_settings(&toml)?;
+        let roles = load_role_settings(&toml)?;
         let help_rooms = load_help_settings(&toml);
+        let url_unfurl_exclusion = load_url_unfurl_settings(&toml);
+        let thread_rooms = load_thread_room_settings(&toml);
         let ban_rooms = load_ban_room_settings(&toml);
+        let audit_room = load_audit_room_settings(&toml);
+        let (policy_rooms, policy_publish_room) = load_policy_room_settings(&toml);
         let (mx_url, mx_uname, mx_pass, enable_corrections, enable_unit_conversions) = (
             toml.matrix_authentication
                 .url
@@ -296,7 +758,10 @@ impl Config {
                 )
             })?;
 
-        let (group_pings, group_ping_users) = load_group_ping_settings(&toml)?;
+        let (group_pings, group_aliases, group_ping_users) = load_group_ping_settings(&toml)?;
+        let github_webhooks = load_github_webhook_settings(&toml)?;
+        let anti_spam = load_anti_spam_settings(&toml)?;
+        let (welcome_messages, welcome_cooldown) = load_welcome_settings(&toml)?;
         let webhook_token = toml.general.webhook_token.into_boxed_str();
 
         // Return value
@@ -305,6 +770,8 @@ impl Config {
             mx_uname,
             mx_pass,
             gh_access_token,
+            gh_search_cache_ttl,
+            commit_search_sigil,
             enable_unit_conversions,
             enable_corrections,
             unit_conversion_exclusion,
@@ -313,15 +780,25 @@ impl Config {
             correction_exclusion,
             linkers,
             text_expansions,
-            admins,
+            roles,
             help_rooms,
             ban_rooms,
+            audit_room,
+            policy_rooms,
+            policy_publish_room,
             repos,
+            url_unfurl_exclusion,
+            thread_rooms,
             links,
             user_agent,
             group_pings,
+            group_aliases,
             group_ping_users,
+            anti_spam,
+            welcome_messages,
+            welcome_cooldown,
             webhook_token,
+            github_webhooks,
         })
     }
 }
@@ -363,22 +840,163 @@ impl Display for SensitiveSpelling {
     }
 }
 
-fn load_github_settings(
-    toml: &RawConfig,
-) -> anyhow::Result<(HashMap<Box<str>, Box<str>>, Box<str>)> {
-    match &toml.searchable_repos {
-        Some(r) => match &toml.github_authentication {
-            Some(g) => {
-                let r = r.iter().map(|(k,v)| (k.clone().into_boxed_str(), v.clone().into_boxed_str())).collect();
-                Ok((r, g.access_token.to_owned().into_boxed_str()))
-            },
-            None => {
-                Err(anyhow!(format!("Searchable repos configured, but no github access token found. Unable to continue...")))
+fn load_github_settings(toml: &RawConfig) -> anyhow::Result<(SearchableRepos, Box<str>)> {
+    let repos = match &toml.searchable_repos {
+        Some(r) => {
+            let mut repos = HashMap::new();
+            for (name, repo) in r {
+                repos.insert(
+                    name.clone().into_boxed_str(),
+                    load_searchable_repo(name, repo)?,
+                );
             }
-        },
+            repos
+        }
         None => {
             info!("No searchable repos found. Disabling feature...");
-            Ok((HashMap::new(), String::new().into_boxed_str()))
+            return Ok((HashMap::new(), String::new().into_boxed_str()));
+        }
+    };
+    match &toml.github_authentication {
+        Some(g) => Ok((repos, g.access_token.to_owned().into_boxed_str())),
+        None if repos.values().any(|r| r.forge == Forge::Github) => Err(anyhow!(
+            "Searchable github repos configured, but no github access token found. Unable to continue..."
+        )),
+        None => Ok((repos, String::new().into_boxed_str())),
+    }
+}
+
+fn load_searchable_repo(name: &str, repo: &RawSearchableRepo) -> anyhow::Result<SearchableRepo> {
+    let (path, forge) = match repo {
+        RawSearchableRepo::Github(path) => (path, Forge::Github),
+        RawSearchableRepo::Forge {
+            repo,
+            forge,
+            url,
+            token,
+        } => {
+            let url = match (forge, url) {
+                (RawForgeKind::Github, _) => None,
+                (_, Some(url)) => Some(Url::parse(url).with_context(|| {
+                    format!("Invalid url {} for searchable repo {}", url, name)
+                })?),
+                (_, None) => {
+                    return Err(anyhow!(
+                        "Searchable repo {} requires a url for its forge",
+                        name
+                    ))
+                }
+            };
+            let token = token.as_ref().map(|t| t.clone().into_boxed_str());
+            let forge = match (forge, url) {
+                (RawForgeKind::Gitea, Some(url)) => Forge::Gitea { url, token },
+                (RawForgeKind::Gitlab, Some(url)) => Forge::Gitlab { url, token },
+                _ => Forge::Github,
+            };
+            (repo, forge)
+        }
+    };
+    if !path.contains('/') {
+        return Err(anyhow!(
+            "Searchable repo {} has path {} which is not in owner/repo format",
+            name,
+            path
+        ));
+    }
+    Ok(SearchableRepo {
+        repo: path.clone().into_boxed_str(),
+        forge,
+    })
+}
+
+fn load_github_search_cache_settings(toml: &RawConfig) -> anyhow::Result<Duration> {
+    match toml
+        .github_authentication
+        .as_ref()
+        .and_then(|g| g.search_cache_ttl.as_ref())
+    {
+        Some(v) => humantime::parse_duration(v)
+            .with_context(|| format!("Invalid github search cache ttl {}", v)),
+        None => Ok(DEFAULT_SEARCH_CACHE_TTL),
+    }
+}
+
+fn load_commit_search_settings(toml: &RawConfig) -> anyhow::Result<char> {
+    match toml
+        .github_authentication
+        .as_ref()
+        .and_then(|g| g.commit_search_sigil)
+    {
+        Some(v) if v.is_alphanumeric() || v.is_whitespace() || matches!(v, '#' | '/' | '-') => {
+            Err(anyhow!(
+                "Invalid commit search sigil {:?}. It cannot be a letter, number, whitespace, '#', '/', or '-'",
+                v
+            ))
+        }
+        Some(v) => Ok(v),
+        None => Ok(DEFAULT_COMMIT_SEARCH_SIGIL),
+    }
+}
+
+fn load_github_webhook_settings(
+    toml: &RawConfig,
+) -> anyhow::Result<HashMap<Box<str>, GithubWebhook>> {
+    match &toml.github_webhooks {
+        Some(v) => {
+            let mut webhooks = HashMap::new();
+            for (repo, webhook) in v {
+                if !repo.contains('/') {
+                    return Err(anyhow!(format!(
+                        "Github webhook repo {} is not in owner/repo format",
+                        repo
+                    )));
+                }
+                if webhook.secret.is_empty() {
+                    return Err(anyhow!(format!(
+                        "Github webhook for repo {} has an empty secret",
+                        repo
+                    )));
+                }
+                let rooms = webhook.rooms.clone().unwrap_or_default();
+                let mut routes = Vec::new();
+                for route in webhook.routes.iter().flatten() {
+                    let mut events = Vec::new();
+                    for event in &route.events {
+                        events.push(event.parse().with_context(|| {
+                            format!("Invalid event in github webhook routes for repo {}", repo)
+                        })?);
+                    }
+                    if events.is_empty() || route.rooms.is_empty() {
+                        return Err(anyhow!(format!(
+                            "Github webhook route for repo {} must have at least 1 event and 1 room",
+                            repo
+                        )));
+                    }
+                    routes.push(GithubWebhookRoute {
+                        events,
+                        rooms: route.rooms.clone(),
+                    });
+                }
+                if rooms.is_empty() && routes.is_empty() {
+                    info!(
+                        "No rooms or routes specified for github webhook {}. Notifications will not be posted.",
+                        repo
+                    );
+                }
+                webhooks.insert(
+                    repo.to_lowercase().into_boxed_str(),
+                    GithubWebhook {
+                        secret: webhook.secret.clone().into_boxed_str(),
+                        rooms,
+                        routes,
+                    },
+                );
+            }
+            Ok(webhooks)
+        }
+        None => {
+            info!("No github webhooks found. Disabling feature...");
+            Ok(HashMap::new())
         }
     }
 }
@@ -521,13 +1139,150 @@ fn load_spell_correct_settings(
     }
 }
 
-fn load_admin_settings(toml: &RawConfig) -> anyhow::Result<HashSet<OwnedUserId>> {
-    match &toml.general.authorized_users {
-        Some(v) => Ok(v.clone()),
-        None => Err(anyhow!(format!(
-            "You must provide at least 1 authorized user"
-        ))),
+fn load_role_settings(toml: &RawConfig) -> anyhow::Result<HashMap<Box<str>, Role>> {
+    let mut roles = HashMap::new();
+    if let Some(v) = &toml.roles {
+        for (name, role) in v {
+            if name == AUTHORIZED_USERS_ROLE {
+                return Err(anyhow!(
+                    "{} is a reserved role name, add the users to authorized_users instead",
+                    AUTHORIZED_USERS_ROLE
+                ));
+            }
+            if role.capabilities.is_empty() {
+                return Err(anyhow!("Role {} must grant at least 1 capability", name));
+            }
+            roles.insert(
+                name.clone().into_boxed_str(),
+                Role {
+                    capabilities: role.capabilities.clone(),
+                    users: role.users.clone().unwrap_or_default(),
+                    power_level: role.power_level,
+                },
+            );
+        }
+    }
+    if let Some(v) = &toml.general.authorized_users {
+        roles.insert(
+            AUTHORIZED_USERS_ROLE.into(),
+            Role {
+                capabilities: Capability::ALL.iter().copied().collect(),
+                users: v.clone(),
+                power_level: None,
+            },
+        );
+    }
+    if !roles.values().any(|r| {
+        r.capabilities.contains(&Capability::Invite)
+            && (!r.users.is_empty() || r.power_level.is_some())
+    }) {
+        return Err(anyhow!(
+            "You must provide at least 1 authorized user or a role with the invite capability and at least 1 user or a power level"
+        ));
     }
+    Ok(roles)
+}
+
+fn load_audit_room_settings(toml: &RawConfig) -> Option<OwnedRoomId> {
+    if toml.general.audit_room.is_none() {
+        info!("No audit room specified. Moderation actions will only be logged and saved.");
+    }
+    toml.general.audit_room.clone()
+}
+
+fn load_policy_room_settings(toml: &RawConfig) -> (HashSet<OwnedRoomId>, Option<OwnedRoomId>) {
+    let policy_rooms = match &toml.general.policy_rooms {
+        Some(v) => {
+            if toml.general.ban_rooms.is_none() {
+                warn!("Policy rooms specified without ban rooms. Policy bans will not be applied.");
+            }
+            v.clone()
+        }
+        None => {
+            info!("No policy rooms specified. Disabling feature.");
+            HashSet::new()
+        }
+    };
+    (policy_rooms, toml.general.policy_publish_room.clone())
+}
+
+fn load_welcome_settings(
+    toml: &RawConfig,
+) -> anyhow::Result<(HashMap<OwnedRoomId, Box<str>>, Duration)> {
+    let raw = match &toml.welcome {
+        Some(v) => v,
+        None => {
+            info!("No welcome messages specified. Disabling feature.");
+            return Ok((HashMap::new(), DEFAULT_WELCOME_COOLDOWN));
+        }
+    };
+    let cooldown = match &raw.cooldown {
+        Some(v) => humantime::parse_duration(v)
+            .with_context(|| format!("Invalid welcome cooldown {}", v))?,
+        None => DEFAULT_WELCOME_COOLDOWN,
+    };
+    let messages = raw
+        .messages
+        .iter()
+        .map(|(room, message)| (room.clone(), message.clone().into_boxed_str()))
+        .collect();
+    Ok((messages, cooldown))
+}
+
+fn load_anti_spam_settings(toml: &RawConfig) -> anyhow::Result<Option<AntiSpam>> {
+    let raw = match &toml.anti_spam {
+        Some(v) => v,
+        None => {
+            info!("No anti-spam rules specified. Disabling feature.");
+            return Ok(None);
+        }
+    };
+    let limit = |name: &str,
+                 raw: &Option<RawSpamLimit>,
+                 needs_period: bool|
+     -> anyhow::Result<Option<SpamLimit>> {
+        let raw = match raw {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        if raw.limit == 0 {
+            bail!("Anti-spam {} limit must be at least 1", name);
+        }
+        let period = match (&raw.period, needs_period) {
+            (Some(v), true) => humantime::parse_duration(v)
+                .with_context(|| format!("Invalid anti-spam {} period {}", name, v))?,
+            (None, true) => bail!("Anti-spam {} requires a period", name),
+            (_, false) => Duration::ZERO,
+        };
+        Ok(Some(SpamLimit {
+            limit: raw.limit,
+            period,
+            action: raw.action,
+        }))
+    };
+    let mute_duration = match &raw.mute_duration {
+        Some(v) => Some(
+            humantime::parse_duration(v)
+                .with_context(|| format!("Invalid anti-spam mute duration {}", v))?,
+        ),
+        None => None,
+    };
+    let blocked_domains = raw.blocked_domains.as_ref().map(|b| BlockedDomains {
+        domains: b
+            .domains
+            .iter()
+            .map(|d| d.trim_start_matches("*.").to_lowercase().into_boxed_str())
+            .collect(),
+        action: b.action,
+    });
+    Ok(Some(AntiSpam {
+        rooms: raw.rooms.clone().unwrap_or_default(),
+        mute_duration,
+        flood: limit("flood", &raw.flood, true)?,
+        mentions: limit("mentions", &raw.mentions, false)?,
+        repeats: limit("repeats", &raw.repeats, true)?,
+        blocked_domains,
+    }))
 }
 
 fn load_help_settings(toml: &RawConfig) -> HashSet<OwnedRoomId> {
@@ -540,6 +1295,26 @@ fn load_help_settings(toml: &RawConfig) -> HashSet<OwnedRoomId> {
     }
 }
 
+fn load_url_unfurl_settings(toml: &RawConfig) -> HashSet<OwnedRoomId> {
+    match &toml.general.url_unfurl_exclusion {
+        Some(v) => v.clone(),
+        None => {
+            info!("No url unfurl exclusion rooms specified. Allowing all rooms.");
+            HashSet::new()
+        }
+    }
+}
+
+fn load_thread_room_settings(toml: &RawConfig) -> HashSet<OwnedRoomId> {
+    match &toml.general.thread_rooms {
+        Some(v) => v.clone(),
+        None => {
+            info!("No thread rooms specified. Responding in threads only to messages in threads.");
+            HashSet::new()
+        }
+    }
+}
+
 fn load_ban_room_settings(toml: &RawConfig) -> HashSet<OwnedRoomId> {
     match &toml.general.ban_rooms {
         Some(v) => v.clone(),
@@ -550,80 +1325,92 @@ fn load_ban_room_settings(toml: &RawConfig) -> HashSet<OwnedRoomId> {
     }
 }
 
+/// Reads the groups aliased by each group
+///
+/// Aliases are kept rather than expanded so members added to an aliased group from chat are pinged too.
+/// Returns an error naming the aliases involved if groups alias each other in a cycle
+fn load_group_aliases(groups: &HashMap<String, Vec<String>>) -> anyhow::Result<GroupAliases> {
+    let mut aliases: GroupAliases = HashMap::new();
+    for (group, members) in groups {
+        let group_aliases = aliases.entry(group.as_str().into()).or_default();
+        for alias in members.iter().filter_map(|m| m.strip_prefix('%')) {
+            if !groups.contains_key(alias) {
+                return Err(anyhow!("Group alias %{} has no corresponding group", alias));
+            }
+            group_aliases.insert(alias.into());
+        }
+    }
+    let mut checked = HashSet::new();
+    for group in aliases.keys() {
+        check_group_aliases(group, &aliases, &mut Vec::new(), &mut checked)?;
+    }
+    Ok(aliases)
+}
+
+/// Checks a single group for alias cycles, using `path` to track the aliases currently being followed
+fn check_group_aliases<'a>(
+    group: &'a str,
+    aliases: &'a GroupAliases,
+    path: &mut Vec<&'a str>,
+    checked: &mut HashSet<&'a str>,
+) -> anyhow::Result<()> {
+    if checked.contains(group) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|g| *g == group) {
+        let cycle = path[start..]
+            .iter()
+            .chain(std::iter::once(&group))
+            .map(|g| format!("%{}", g))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(anyhow!("Group aliases form a cycle: {}", cycle));
+    }
+    path.push(group);
+    for alias in aliases.get(group).into_iter().flatten() {
+        check_group_aliases(alias, aliases, path, checked)?;
+    }
+    path.pop();
+    checked.insert(group);
+    Ok(())
+}
+
 fn load_group_ping_settings(
     toml: &RawConfig,
 ) -> anyhow::Result<(
     HashMap<Box<str>, HashSet<OwnedUserId>>,
+    GroupAliases,
     HashSet<OwnedUserId>,
 )> {
     match &toml.group_pings {
         Some(v) => {
+            let mut group_pings = HashMap::new();
             let mut group_ping_users = HashSet::new();
-            let groups = v.clone();
-            for group in groups {
-                for user in group.1 {
+            for (group, members) in v {
+                let mut users = HashSet::new();
+                for user in members {
                     if user.eq("%all") {
-                        return Err(anyhow!(format!(
+                        return Err(anyhow!(
                             "%all is a reserved group_ping name, do not configure it manually"
-                        )));
+                        ));
                     }
-                    if user.starts_with('@') {
-                        let user_id = UserId::parse(user.clone()).context(
-                            "Somehow got an alias in a part of code meant to handle UserIds",
-                        )?;
-                        group_ping_users.insert(user_id);
+                    if !user.starts_with('%') {
+                        let user_id = UserId::parse(user.clone())
+                            .with_context(|| format!("Invalid user {} in group {}", user, group))?;
+                        users.insert(user_id);
                     }
                 }
+                group_ping_users.extend(users.iter().cloned());
+                group_pings.insert(group.as_str().into(), users);
             }
 
-            let mut expanded_groups: HashMap<Box<str>, HashSet<OwnedUserId>> = HashMap::new();
-            for (group, users) in v {
-                let mut expanded_users: HashSet<OwnedUserId> = HashSet::new();
-
-                for user in users {
-                    if user.eq("%all") {
-                        return Err(anyhow!(format!(
-                            "%all is a reserved group_ping name, do not configure it manually"
-                        )));
-                    }
-                    if user.starts_with('%') {
-                        // If user is an alias, expand it to list of users and insert them
-                        let alias = user.replace('%', "");
-                        match v.get(&alias) {
-                            // If list of users found, insert them
-                            Some(g) => {
-                                for u in g {
-                                    if u.starts_with('@') {
-                                        let user_id = UserId::parse(u.clone()).context("Somehow got an alias in a part of code meant to handle UserIds")?;
-                                        expanded_users.insert(user_id);
-                                    }
-                                }
-                            }
-                            // If list of users are not found, print error to console and move on
-                            None => {
-                                return Err(anyhow!(format!(
-                                    "Group alias %{} has no corresponding group. Ignoring...",
-                                    alias
-                                )))
-                            }
-                        }
-                    } else {
-                        // If user is not alias, just insert it
-                        let user_id = UserId::parse(user.clone()).context(
-                            "Somehow got an alias in a part of code meant to handle UserIds",
-                        )?;
-                        expanded_users.insert(user_id);
-                    }
-                }
-
-                expanded_groups.insert(group.clone().into_boxed_str(), expanded_users);
-            }
+            let group_aliases = load_group_aliases(v)?;
 
-            Ok((expanded_groups, group_ping_users))
+            Ok((group_pings, group_aliases, group_ping_users))
         }
         None => {
             info!("No group pings defined. Disabling feature...");
-            Ok((HashMap::new(), HashSet::new()))
+            Ok((HashMap::new(), HashMap::new(), HashSet::new()))
         }
     }
 }
@@ -0,0 +1,84 @@
+use super::super::{load_anti_spam_settings, RawConfig, SpamAction};
+use crate::test_helpers::raw_config;
+use std::time::Duration;
+
+#[test]
+fn disabled_without_section() {
+    assert!(load_anti_spam_settings(&raw_config("", ""))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn configured_rules() {
+    let anti_spam = load_anti_spam_settings(&raw_config(
+        "",
+        "[anti_spam]
+mute_duration = '1h'
+
+[anti_spam.flood]
+limit = 10
+period = '10s'
+action = 'mute'
+
+[anti_spam.mentions]
+limit = 5
+action = 'redact'
+
+[anti_spam.blocked_domains]
+domains = ['*.Spam.example']
+action = 'ban'",
+    ))
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(Some(Duration::from_secs(3600)), anti_spam.mute_duration);
+    let flood = anti_spam.flood.unwrap();
+    assert_eq!(Duration::from_secs(10), flood.period);
+    assert_eq!(SpamAction::Mute, flood.action);
+    assert_eq!(5, anti_spam.mentions.unwrap().limit);
+    assert!(anti_spam.repeats.is_none());
+    assert!(anti_spam
+        .blocked_domains
+        .unwrap()
+        .domains
+        .contains("spam.example"));
+}
+
+#[test]
+fn flood_without_period() {
+    let error = load_anti_spam_settings(&raw_config(
+        "",
+        "[anti_spam.flood]
+limit = 10
+action = 'mute'",
+    ))
+    .unwrap_err();
+
+    assert!(error.to_string().contains("requires a period"));
+}
+
+#[test]
+fn unknown_action() {
+    let error = toml::from_str::<RawConfig>(
+        r#"
+[general]
+enable_unit_conversions = false
+enable_corrections = false
+webhook_token = "token"
+
+[matrix_authentication]
+url = "https://matrix.org"
+username = "@bot:matrix.org"
+password = "password"
+
+[anti_spam.repeats]
+limit = 3
+period = '1m'
+action = 'explode'
+"#,
+    )
+    .unwrap_err();
+
+    assert!(error.to_string().contains("explode"));
+}
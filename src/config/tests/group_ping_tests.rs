@@ -0,0 +1,71 @@
+use super::super::load_group_aliases;
+use std::collections::{HashMap, HashSet};
+
+fn groups(groups: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+    groups
+        .iter()
+        .map(|(name, members)| {
+            (
+                name.to_string(),
+                members.iter().map(|m| m.to_string()).collect(),
+            )
+        })
+        .collect()
+}
+
+fn names(names: &[&str]) -> HashSet<Box<str>> {
+    names.iter().map(|n| (*n).into()).collect()
+}
+
+#[test]
+fn nested_aliases() {
+    let aliases = load_group_aliases(&groups(&[
+        ("backend", &["@user1:matrix.org", "@user2:matrix.org"]),
+        ("server", &["%backend"]),
+        ("web", &["@user3:matrix.org"]),
+        ("api", &["%server", "%web", "@user5:matrix.org"]),
+    ]))
+    .unwrap();
+
+    assert_eq!(names(&[]), aliases["backend"]);
+    assert_eq!(names(&["backend"]), aliases["server"]);
+    assert_eq!(names(&["server", "web"]), aliases["api"]);
+}
+
+#[test]
+fn cycle() {
+    let error = load_group_aliases(&groups(&[
+        ("a", &["%b"]),
+        ("b", &["%c", "@user1:matrix.org"]),
+        ("c", &["%a"]),
+    ]))
+    .unwrap_err()
+    .to_string();
+
+    assert!(error.contains("cycle"));
+    assert!(
+        [
+            "%a -> %b -> %c -> %a",
+            "%b -> %c -> %a -> %b",
+            "%c -> %a -> %b -> %c"
+        ]
+        .iter()
+        .any(|c| error.contains(c)),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn self_alias() {
+    let error = load_group_aliases(&groups(&[("a", &["%a"])]))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("%a -> %a"), "{}", error);
+}
+
+#[test]
+fn missing_alias() {
+    assert!(load_group_aliases(&groups(&[("a", &["%b"])])).is_err());
+}
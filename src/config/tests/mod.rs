@@ -0,0 +1,3 @@
+mod anti_spam_tests;
+mod group_ping_tests;
+mod roles_tests;
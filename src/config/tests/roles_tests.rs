@@ -0,0 +1,93 @@
+use super::super::{load_role_settings, Capability, RawConfig};
+use crate::test_helpers::raw_config;
+use ruma::UserId;
+
+#[test]
+fn authorized_users_have_every_capability() {
+    let roles =
+        load_role_settings(&raw_config("authorized_users = ['@admin:matrix.org']", "")).unwrap();
+    let role = &roles["authorized_users"];
+
+    assert!(role
+        .users
+        .contains(&UserId::parse("@admin:matrix.org").unwrap()));
+    assert_eq!(Capability::ALL.len(), role.capabilities.len());
+}
+
+#[test]
+fn configured_roles() {
+    let roles = load_role_settings(&raw_config(
+        "",
+        "[roles.moderator]
+capabilities = ['invite', 'ban']
+users = ['@mod:matrix.org']
+
+[roles.triager]
+capabilities = ['manage_issues']",
+    ))
+    .unwrap();
+
+    assert!(roles["moderator"].capabilities.contains(&Capability::Ban));
+    assert!(!roles["moderator"]
+        .capabilities
+        .contains(&Capability::ManageIssues));
+    assert!(roles["triager"].users.is_empty());
+}
+
+#[test]
+fn reserved_role_name() {
+    let error = load_role_settings(&raw_config(
+        "authorized_users = ['@admin:matrix.org']",
+        "[roles.authorized_users]
+capabilities = ['ban']",
+    ))
+    .unwrap_err();
+
+    assert!(error.to_string().contains("reserved"));
+}
+
+#[test]
+fn no_user_can_invite() {
+    let error = load_role_settings(&raw_config(
+        "",
+        "[roles.moderator]
+capabilities = ['ban']
+users = ['@mod:matrix.org']",
+    ))
+    .unwrap_err();
+
+    assert!(error.to_string().contains("invite"));
+}
+
+#[test]
+fn unknown_capability() {
+    let result = toml::from_str::<RawConfig>(
+        "[general]
+enable_unit_conversions = false
+enable_corrections = false
+webhook_token = 'token'
+
+[matrix_authentication]
+url = 'https://matrix.org'
+username = '@bot:matrix.org'
+password = 'password'
+
+[roles.moderator]
+capabilities = ['kick_everyone']",
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn power_level_role_can_invite() {
+    let roles = load_role_settings(&raw_config(
+        "",
+        "[roles.moderator]
+capabilities = ['invite', 'ban']
+power_level = 50",
+    ))
+    .unwrap();
+
+    assert_eq!(Some(50), roles["moderator"].power_level);
+}
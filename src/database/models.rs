@@ -1,8 +1,12 @@
+use crate::helpers::{SearchResultKind, SearchResultState};
 use native_db::{native_db, InnerKeyValue};
 use native_model::{native_model, Model};
+use ruma::TransactionId;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
 #[native_model(id = 1, version = 1)]
@@ -39,3 +43,321 @@ pub struct CorrectionTimeCooldown {
     #[secondary_key]
     pub(crate) last_correction_time: u64,
 }
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 4, version = 1)]
+#[native_db]
+pub struct GithubRateLimit {
+    #[primary_key]
+    pub(crate) id: u8,
+    pub(crate) cost: i64,
+    pub(crate) remaining: i64,
+    pub(crate) reset_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 5, version = 1)]
+#[native_db]
+pub struct GithubSearchCache {
+    #[primary_key]
+    pub(crate) key: String,
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) kind: SearchResultKind,
+    pub(crate) title: String,
+    pub(crate) state: SearchResultState,
+    pub(crate) author: Option<String>,
+    pub(crate) labels: Vec<String>,
+    pub(crate) url: String,
+    #[secondary_key]
+    pub(crate) fetched_at: u64,
+}
+
+impl GithubSearchCache {
+    /// Builds the cache key for an issue or pull in the form of `owner/repo#number`
+    pub fn key(repo: &str, number: i64) -> String {
+        format!("{}#{}", repo.to_lowercase(), number)
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 6, version = 1)]
+#[native_db]
+pub struct TextExpansion {
+    #[primary_key]
+    pub(crate) keyword: String,
+    pub(crate) text: String,
+    pub(crate) edited_by: String,
+    pub(crate) edited_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 7, version = 1)]
+#[native_db]
+pub struct GroupPing {
+    #[primary_key]
+    pub(crate) name: String,
+    pub(crate) members: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 8, version = 1)]
+#[native_db]
+pub struct RoomPowerLevels {
+    #[primary_key]
+    pub(crate) room_id: String,
+    pub(crate) users: HashMap<String, i64>,
+    pub(crate) users_default: i64,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 9, version = 1)]
+#[native_db]
+pub struct ModerationRecord {
+    #[primary_key]
+    pub(crate) id: String,
+    pub(crate) timestamp: u64,
+    pub(crate) action: ModerationAction,
+    pub(crate) actor: String,
+    pub(crate) target: String,
+    pub(crate) reason: Option<String>,
+    pub(crate) outcomes: Vec<RoomOutcome>,
+}
+
+impl ModerationRecord {
+    /// Creates a record of an action taken now, keyed so records sort by the time they were taken
+    pub fn new(
+        action: ModerationAction,
+        actor: String,
+        target: String,
+        reason: Option<String>,
+        outcomes: Vec<RoomOutcome>,
+    ) -> Self {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            id: format!("{:020}-{}", now.as_millis(), TransactionId::new()),
+            timestamp: now.as_secs(),
+            action,
+            actor,
+            target,
+            reason,
+            outcomes,
+        }
+    }
+}
+
+impl Display for ModerationRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.actor, self.action, self.target)?;
+        if let Some(reason) = &self.reason {
+            write!(f, ": {}", reason)?;
+        }
+        for outcome in &self.outcomes {
+            match &outcome.error {
+                Some(e) => write!(f, "\n\tfailed in {}: {}", outcome.room_id, e)?,
+                None => write!(f, "\n\tapplied in {}", outcome.room_id)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum ModerationAction {
+    Ban,
+    Unban,
+    Kick,
+    Mute,
+    Unmute,
+    Redact,
+    Purge,
+    AcceptInvite,
+    RejectInvite,
+    Warn,
+}
+
+impl Display for ModerationAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let action = match self {
+            ModerationAction::Ban => "banned",
+            ModerationAction::Unban => "unbanned",
+            ModerationAction::Kick => "kicked",
+            ModerationAction::Mute => "muted",
+            ModerationAction::Unmute => "unmuted",
+            ModerationAction::Redact => "redacted",
+            ModerationAction::Purge => "purged",
+            ModerationAction::AcceptInvite => "invited the bot to",
+            ModerationAction::RejectInvite => "was refused an invite of the bot to",
+            ModerationAction::Warn => "warned",
+        };
+        write!(f, "{}", action)
+    }
+}
+
+/// Result of a moderation action in a single room
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct RoomOutcome {
+    pub(crate) room_id: String,
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 10, version = 1)]
+#[native_db]
+pub struct ScheduledExpiry {
+    #[primary_key]
+    pub(crate) id: String,
+    pub(crate) expires_at: u64,
+    pub(crate) action: ModerationAction,
+    pub(crate) actor: String,
+    pub(crate) user: String,
+    pub(crate) rooms: Vec<String>,
+}
+
+impl ScheduledExpiry {
+    /// Creates an expiry that runs once the duration has passed, keyed so expiries sort by when they run
+    pub fn new(
+        action: ModerationAction,
+        actor: String,
+        user: String,
+        rooms: Vec<String>,
+        duration: Duration,
+    ) -> Self {
+        // Durations that overflow never expire rather than panicking
+        let expires_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .checked_add(duration)
+            .unwrap_or(Duration::MAX);
+        Self {
+            id: format!("{:020}-{}", expires_at.as_millis(), TransactionId::new()),
+            expires_at: expires_at.as_secs(),
+            action,
+            actor,
+            user,
+            rooms,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 11, version = 1)]
+#[native_db]
+pub struct PolicyRule {
+    #[primary_key]
+    pub(crate) id: String,
+    pub(crate) kind: PolicyRuleKind,
+    pub(crate) entity: String,
+    pub(crate) reason: String,
+}
+
+impl PolicyRule {
+    /// Creates a ban rule keyed by the policy room and state of the event it was read from
+    pub fn new(
+        policy_room: &str,
+        kind: PolicyRuleKind,
+        state_key: &str,
+        entity: String,
+        reason: String,
+    ) -> Self {
+        Self {
+            id: PolicyRule::id(policy_room, kind, state_key),
+            kind,
+            entity,
+            reason,
+        }
+    }
+
+    /// Returns the id of the rule set by the state event in the policy room
+    pub fn id(policy_room: &str, kind: PolicyRuleKind, state_key: &str) -> String {
+        format!("{}|{}|{}", policy_room, kind, state_key)
+    }
+
+    /// Returns the policy room the rule was read from
+    pub fn policy_room(&self) -> &str {
+        self.id.split('|').next().unwrap_or_default()
+    }
+}
+
+/// What the entity of a policy rule is matched against
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum PolicyRuleKind {
+    User,
+    Server,
+}
+
+impl Display for PolicyRuleKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let event_type = match self {
+            PolicyRuleKind::User => "m.policy.rule.user",
+            PolicyRuleKind::Server => "m.policy.rule.server",
+        };
+        write!(f, "{}", event_type)
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 12, version = 1)]
+#[native_db]
+pub struct ResponseRecord {
+    #[primary_key]
+    pub(crate) trigger_event_id: String,
+    pub(crate) sender: String,
+    pub(crate) thread_root: Option<String>,
+    pub(crate) responses: Vec<SentResponse>,
+    pub(crate) sent_at: u64,
+}
+
+impl ResponseRecord {
+    /// Creates a record of the responses to a message, timestamped with the current time
+    pub fn new(
+        trigger_event_id: String,
+        sender: String,
+        thread_root: Option<String>,
+        responses: Vec<SentResponse>,
+    ) -> Self {
+        Self {
+            trigger_event_id,
+            sender,
+            thread_root,
+            responses,
+            sent_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+/// A response sent to a message, with its content serialized so unchanged responses are not edited
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct SentResponse {
+    pub(crate) event_id: String,
+    pub(crate) content: String,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+#[native_model(id = 13, version = 1)]
+#[native_db]
+pub struct MutedUser {
+    #[primary_key]
+    pub(crate) id: String,
+    pub(crate) previous_level: Option<i64>,
+}
+
+impl MutedUser {
+    /// Creates a mute of the user in the room, remembering the power level set for them before it
+    pub fn new(room_id: &str, user: &str, previous_level: Option<i64>) -> Self {
+        Self {
+            id: MutedUser::id(room_id, user),
+            previous_level,
+        }
+    }
+
+    /// Returns the id of the mute of the user in the room
+    pub fn id(room_id: &str, user: &str) -> String {
+        format!("{}|{}", room_id, user)
+    }
+}
@@ -1,5 +1,21 @@
+//! Models of the payloads GitHub sends to webhooks
+//!
+//! Fields mirror the GitHub payload schema and are not all read by the bot
+#![allow(dead_code)]
+
 use serde::Deserialize;
 
+/// Names of the events the bot is able to post notifications for
+pub const NOTIFIABLE_EVENTS: [&str; 7] = [
+    "create",
+    "delete",
+    "issue_comment",
+    "issues",
+    "pull_request",
+    "push",
+    "release",
+];
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Value {
     pub json: serde_json::Value,
@@ -15,6 +31,7 @@ impl Default for Value {
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum Event {
     CommitComment {
         action: String,
@@ -23,7 +40,7 @@ pub enum Event {
         sender: User,
     },
     Create {
-        description: String,
+        description: Option<String>,
         master_branch: String,
         pusher_type: String,
         #[serde(rename = "ref")]
@@ -124,7 +141,7 @@ pub enum Event {
         created: bool,
         deleted: bool,
         forced: bool,
-        head_commit: CommitStats,
+        head_commit: Option<CommitStats>,
         pusher: UserRef,
         #[serde(rename = "ref")]
         _ref: String,
@@ -175,6 +192,63 @@ pub enum Event {
     },
 }
 
+impl Event {
+    /// Returns the name GitHub uses for this event in the `X-GitHub-Event` header
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::CommitComment { .. } => "commit_comment",
+            Event::Create { .. } => "create",
+            Event::Delete { .. } => "delete",
+            Event::Deployment { .. } => "deployment",
+            Event::DeploymentStatus { .. } => "deployment_status",
+            Event::Fork { .. } => "fork",
+            Event::Gollum { .. } => "gollum",
+            Event::IssueComment { .. } => "issue_comment",
+            Event::Issues { .. } => "issues",
+            Event::Member { .. } => "member",
+            Event::Membership { .. } => "membership",
+            Event::PageBuild { .. } => "page_build",
+            Event::Ping { .. } => "ping",
+            Event::PullRequest { .. } => "pull_request",
+            Event::PullRequestReviewComment { .. } => "pull_request_review_comment",
+            Event::Push { .. } => "push",
+            Event::Release { .. } => "release",
+            Event::Repository { .. } => "repository",
+            Event::Status { .. } => "status",
+            Event::TeamAdd { .. } => "team_add",
+            Event::Watch { .. } => "watch",
+            Event::Public { .. } => "public",
+        }
+    }
+
+    /// Returns the qualifier used to narrow down routing of this event
+    ///
+    /// This is the action for events that have one, the branch for pushes, and the ref type for creates and deletes.
+    /// Merged pull requests are qualified as `merged` rather than `closed`
+    pub fn qualifier(&self) -> Option<&str> {
+        match self {
+            Event::PullRequest {
+                action,
+                pull_request,
+                ..
+            } if action == "closed" && pull_request.merged => Some("merged"),
+            Event::CommitComment { action, .. }
+            | Event::IssueComment { action, .. }
+            | Event::Issues { action, .. }
+            | Event::Member { action, .. }
+            | Event::Membership { action, .. }
+            | Event::PullRequest { action, .. }
+            | Event::PullRequestReviewComment { action, .. }
+            | Event::Release { action, .. }
+            | Event::Repository { action, .. }
+            | Event::Watch { action, .. } => Some(action),
+            Event::Push { _ref, .. } => Some(_ref.trim_start_matches("refs/heads/")),
+            Event::Create { ref_type, .. } | Event::Delete { ref_type, .. } => Some(ref_type),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Default, Debug, Deserialize)]
 #[allow(dead_code)]
 pub struct Commit {
@@ -296,7 +370,7 @@ pub struct Hook {
 
 #[derive(Clone, Default, Debug, Deserialize)]
 pub struct Issue {
-    pub assignee: Option<String>,
+    pub assignee: Option<User>,
     pub body: Option<String>,
     pub closed_at: Option<String>,
     pub comments: u64,
@@ -308,7 +382,7 @@ pub struct Issue {
     pub labels: Vec<Label>,
     pub labels_url: String,
     pub locked: bool,
-    pub milestone: Option<String>,
+    pub milestone: Option<Milestone>,
     pub number: u64,
     pub state: String,
     pub title: String,
@@ -355,7 +429,7 @@ pub struct Pages {
 #[derive(Clone, Default, Debug, Deserialize)]
 pub struct PullRequestDetails {
     pub _links: PullRequestLinks,
-    pub assignee: Option<String>,
+    pub assignee: Option<User>,
     pub base: PullSource,
     pub body: Option<String>,
     pub closed_at: Option<String>,
@@ -367,10 +441,12 @@ pub struct PullRequestDetails {
     pub html_url: String,
     pub id: u64,
     pub issue_url: String,
+    #[serde(default)]
+    pub labels: Vec<Label>,
     pub locked: bool,
-    pub merge_commit_sha: String,
+    pub merge_commit_sha: Option<String>,
     pub merged_at: Option<String>,
-    pub milestone: Option<String>,
+    pub milestone: Option<Milestone>,
     pub number: u64,
     pub patch_url: String,
     pub review_comment_url: String,
@@ -382,9 +458,9 @@ pub struct PullRequestDetails {
     pub url: String,
     pub user: User,
     pub merged: bool,
-    pub mergeable: Option<String>,
+    pub mergeable: Option<bool>,
     pub mergeable_state: String,
-    pub merged_by: Option<String>,
+    pub merged_by: Option<User>,
     pub comments: u64,
     pub review_comments: u64,
     pub commits: u64,
@@ -396,7 +472,7 @@ pub struct PullRequestDetails {
 #[derive(Clone, Default, Debug, Deserialize)]
 pub struct PullRequest {
     pub _links: PullRequestLinks,
-    pub assignee: Option<String>,
+    pub assignee: Option<User>,
     pub base: PullSource,
     pub body: Option<String>,
     pub closed_at: Option<String>,
@@ -409,9 +485,9 @@ pub struct PullRequest {
     pub id: u64,
     pub issue_url: String,
     pub locked: bool,
-    pub merge_commit_sha: String,
+    pub merge_commit_sha: Option<String>,
     pub merged_at: Option<String>,
-    pub milestone: Option<String>,
+    pub milestone: Option<Milestone>,
     pub number: u64,
     pub patch_url: String,
     pub review_comment_url: String,
@@ -446,7 +522,7 @@ pub struct PullRequestReviewComment {
 
 #[derive(Clone, Default, Debug, Deserialize)]
 pub struct Release {
-    pub assets: Vec<String>,
+    pub assets: Vec<ReleaseAsset>,
     pub assets_url: String,
     pub author: User,
     pub body: Option<String>,
@@ -456,13 +532,13 @@ pub struct Release {
     pub id: u64,
     pub name: Option<String>,
     pub prerelease: bool,
-    pub published_at: String,
+    pub published_at: Option<String>,
     pub tag_name: String,
-    pub tarball_url: String,
+    pub tarball_url: Option<String>,
     pub target_commitish: String,
     pub upload_url: String,
     pub url: String,
-    pub zipball_url: String,
+    pub zipball_url: Option<String>,
 }
 
 #[derive(Clone, Default, Debug, Deserialize)]
@@ -487,7 +563,7 @@ pub struct PushRepository {
     pub contributors_url: String,
     pub created_at: u64,
     pub default_branch: String,
-    pub description: String,
+    pub description: Option<String>,
     pub downloads_url: String,
     pub events_url: String,
     pub fork: bool,
@@ -557,7 +633,7 @@ pub struct Repository {
     pub contributors_url: String,
     pub created_at: String,
     pub default_branch: String,
-    pub description: String,
+    pub description: Option<String>,
     pub downloads_url: String,
     pub events_url: String,
     pub forks: u64,
@@ -593,7 +669,7 @@ pub struct Repository {
     pub owner: User,
     pub private: bool,
     pub pulls_url: String,
-    pub pushed_at: String,
+    pub pushed_at: Option<String>,
     pub releases_url: String,
     pub size: u64,
     pub ssh_url: String,
@@ -649,7 +725,7 @@ pub struct PullSource {
     pub label: String,
     #[serde(rename = "ref")]
     pub _ref: String,
-    pub repo: Repository,
+    pub repo: Option<Repository>,
     pub sha: String,
     pub user: User,
 }
@@ -661,6 +737,21 @@ pub struct Label {
     pub url: String,
 }
 
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct Milestone {
+    pub html_url: String,
+    pub number: u64,
+    pub state: String,
+    pub title: String,
+}
+
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub browser_download_url: String,
+    pub name: String,
+    pub size: u64,
+}
+
 #[derive(Clone, Default, Debug, Deserialize)]
 pub struct LastResponse {
     pub code: Option<String>,
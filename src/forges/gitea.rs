@@ -0,0 +1,128 @@
+//! Search backend for repos hosted on Gitea or Forgejo instances
+//!
+//! Both share the same REST API, which returns issues and pulls from the issues endpoint
+
+use super::{Search, SearchBackend, SearchOutcome};
+use crate::helpers::{SearchResult, SearchResultKind, SearchResultState};
+use axum::async_trait;
+use reqwest::header::{self, HeaderValue};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+/// Searches a Gitea or Forgejo instance with its REST API
+pub struct GiteaBackend<'a> {
+    /// Reqwest client used for requests
+    pub api_client: &'a reqwest::Client,
+    /// Base URL of the instance
+    pub url: &'a Url,
+    /// Optional access token for private repos
+    pub token: Option<&'a str>,
+    /// UserAgent used by reqwest
+    pub user_agent: &'a HeaderValue,
+}
+
+#[derive(Debug, Deserialize)]
+/// Issue as returned by the Gitea API
+struct GiteaIssue {
+    number: i64,
+    title: String,
+    state: String,
+    html_url: String,
+    user: Option<GiteaUser>,
+    #[serde(default)]
+    labels: Vec<GiteaLabel>,
+    pull_request: Option<GiteaPullMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaUser {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaLabel {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaPullMeta {
+    #[serde(default)]
+    merged: bool,
+}
+
+#[async_trait]
+impl SearchBackend for GiteaBackend<'_> {
+    async fn search(&self, searches: &[Search]) -> Result<Vec<SearchOutcome>, String> {
+        let mut outcomes = Vec::new();
+        for search in searches {
+            outcomes.push(self.search_one(search).await);
+        }
+        Ok(outcomes)
+    }
+}
+
+impl GiteaBackend<'_> {
+    async fn search_one(&self, search: &Search) -> SearchOutcome {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| format!("Invalid forge url {}", self.url))?
+            .pop_if_empty()
+            .extend(&["api", "v1", "repos"])
+            .extend(search.repo.split('/'))
+            .extend(&["issues", &search.number.to_string()]);
+        let mut request = self
+            .api_client
+            .get(url)
+            .header(header::USER_AGENT, self.user_agent.clone());
+        if let Some(token) = self.token {
+            request = request.header(header::AUTHORIZATION, format!("token {}", token));
+        }
+        let response = match request.send().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Gitea query failed. Error is {:?}", e);
+                return Err("Unable to search forge".to_string());
+            }
+        };
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => return Err("Not found".to_string()),
+            s => {
+                debug!("Gitea search for {:?} returned status {}", search, s);
+                return Err(format!("Forge returned {}", s));
+            }
+        }
+        let issue: GiteaIssue = match response.json().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Unable to parse gitea response. Error is {:?}", e);
+                return Err("Unable to read result".to_string());
+            }
+        };
+        let url = Url::parse(&issue.html_url).map_err(|e| {
+            error!(
+                "Unable to parse result {:?} to Url. Error is {:?}",
+                issue.html_url, e
+            );
+            "Unable to read result".to_string()
+        })?;
+        let (kind, state) = match (&issue.pull_request, issue.state.as_str()) {
+            (None, "open") => (SearchResultKind::Issue, SearchResultState::Open),
+            (None, _) => (SearchResultKind::Issue, SearchResultState::Closed),
+            (Some(_), "open") => (SearchResultKind::PullRequest, SearchResultState::Open),
+            (Some(p), _) if p.merged => (SearchResultKind::PullRequest, SearchResultState::Merged),
+            (Some(_), _) => (SearchResultKind::PullRequest, SearchResultState::Closed),
+        };
+        Ok(SearchResult {
+            repo: search.repo.clone(),
+            number: issue.number,
+            kind,
+            title: issue.title,
+            state,
+            author: issue.user.map(|u| u.login),
+            labels: issue.labels.into_iter().map(|l| l.name).collect(),
+            url,
+        })
+    }
+}
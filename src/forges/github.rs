@@ -0,0 +1,468 @@
+//! Search backend for repos hosted on github.com
+//!
+//! Uses a single aliased GraphQL query for all searches to be API cost effective
+
+use super::{RefOutcome, RefSearch, Search, SearchBackend, SearchOutcome};
+use crate::database::insert_or_update;
+use crate::database::models::GithubRateLimit;
+use crate::helpers::{
+    cache_search_result, cached_search_result, RefResult, RefResultKind, SearchResult,
+    SearchResultKind, SearchResultState,
+};
+use crate::queries::commit_lookup::CommitDetails;
+use crate::queries::issue_or_pull::IssueOrPullDetails::{self, Issue, PullRequest};
+use crate::queries::issue_or_pull::{IssueState, PullRequestState};
+use crate::queries::lookup_batch::{self, GitObject, Lookup};
+use crate::queries::release_lookup::ReleaseDetails;
+use crate::queries::LookupBatch;
+use axum::async_trait;
+use graphql_client::PathFragment;
+use native_db::Database;
+use reqwest::header::{self, HeaderValue};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, error, trace, warn};
+
+/// Endpoint of the github GraphQL API
+pub const GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+/// Error message used when github could not be queried
+const UNAVAILABLE: &str = "Unable to search github";
+
+/// Results of a batch query keyed by alias and any errors returned by the query
+type BatchResponse = (
+    HashMap<String, Option<lookup_batch::Repository>>,
+    Vec<graphql_client::Error>,
+);
+
+/// Position of a lookup in the searches supplied to `GithubBackend::lookup`
+enum Slot {
+    /// Index of an issue or pull search
+    Search(usize),
+    /// Index of a ref search
+    Ref(usize),
+}
+
+/// Searches github with the configured access token, caching found results
+pub struct GithubBackend<'a> {
+    /// Reqwest client used for the query
+    pub api_client: &'a reqwest::Client,
+    /// GraphQL endpoint queried, github.com unless testing
+    pub graphql_url: &'a str,
+    /// Github access token
+    pub token: &'a str,
+    /// UserAgent used by reqwest
+    pub user_agent: &'a HeaderValue,
+    /// Storage used for the rate limit and search cache
+    pub storage: &'a Database<'a>,
+    /// How long found results are cached for
+    pub cache_ttl: Duration,
+}
+
+#[async_trait]
+impl SearchBackend for GithubBackend<'_> {
+    async fn search(&self, searches: &[Search]) -> Result<Vec<SearchOutcome>, String> {
+        self.lookup(searches, &[]).await.map(|(v, _)| v)
+    }
+}
+
+impl GithubBackend<'_> {
+    /// Looks up all supplied issue and pull searches and ref searches in a single query
+    ///
+    /// Returns an outcome for each search in the same order, or an error message if github
+    /// could not be searched at all
+    pub async fn lookup(
+        &self,
+        searches: &[Search],
+        refs: &[RefSearch],
+    ) -> Result<(Vec<SearchOutcome>, Vec<RefOutcome>), String> {
+        let mut outcomes = searches
+            .iter()
+            .map(|s| cached_search_result(self.storage, &s.repo, s.number, self.cache_ttl).map(Ok))
+            .collect::<Vec<_>>();
+        let mut ref_outcomes = vec![None; refs.len()];
+        let mut slots = Vec::new();
+        let mut lookups = Vec::new();
+        for (slot, search) in searches.iter().enumerate() {
+            if outcomes[slot].is_some() {
+                continue;
+            }
+            match search.repo.split_once('/') {
+                Some((owner, name)) => {
+                    slots.push(Slot::Search(slot));
+                    lookups.push(Lookup::IssueOrPull {
+                        owner: owner.to_string(),
+                        name: name.to_string(),
+                        number: search.number,
+                    });
+                }
+                None => outcomes[slot] = Some(Err("Invalid repo".to_string())),
+            }
+        }
+        debug!("{} searches found in cache", searches.len() - slots.len());
+        for (slot, search) in refs.iter().enumerate() {
+            let lookup = match search {
+                RefSearch::Commit { repo, sha } => {
+                    repo.split_once('/').map(|(owner, name)| Lookup::Commit {
+                        owner: owner.to_string(),
+                        name: name.to_string(),
+                        expression: sha.clone(),
+                    })
+                }
+                RefSearch::Release { repo, tag } => {
+                    repo.split_once('/').map(|(owner, name)| Lookup::Release {
+                        owner: owner.to_string(),
+                        name: name.to_string(),
+                        tag: tag.clone(),
+                    })
+                }
+            };
+            match lookup {
+                Some(v) => {
+                    slots.push(Slot::Ref(slot));
+                    lookups.push(v);
+                }
+                None => ref_outcomes[slot] = Some(Err("Invalid repo".to_string())),
+            }
+        }
+
+        if !lookups.is_empty() {
+            let (mut response_data, errors) = self.fetch_lookups(&lookups).await?;
+            for (index, slot) in slots.into_iter().enumerate() {
+                let alias = lookup_batch::alias(index);
+                let repository = response_data.remove(&alias).flatten();
+                let message = || {
+                    let message = errors
+                        .iter()
+                        .find(|e| {
+                            matches!(e.path.as_deref(), Some([PathFragment::Key(k), ..]) if *k == alias)
+                        })
+                        .map_or("Not found", |e| e.message.as_str());
+                    debug!("Lookup {:?} failed: {}", lookups[index], message);
+                    message.to_string()
+                };
+                match slot {
+                    Slot::Search(slot) => {
+                        let outcome = match repository.and_then(|r| r.issue_or_pull_request) {
+                            Some(v) => match search_result(searches[slot].repo.clone(), v) {
+                                Some(v) => {
+                                    cache_search_result(self.storage, &v);
+                                    Ok(v)
+                                }
+                                None => Err("Unable to read result".to_string()),
+                            },
+                            None => Err(message()),
+                        };
+                        outcomes[slot] = Some(outcome);
+                    }
+                    Slot::Ref(slot) => {
+                        let repo = match &refs[slot] {
+                            RefSearch::Commit { repo, .. } | RefSearch::Release { repo, .. } => {
+                                repo.clone()
+                            }
+                        };
+                        let outcome = match repository {
+                            Some(lookup_batch::Repository {
+                                object: Some(GitObject::Commit(v)),
+                                ..
+                            }) => commit_result(repo, v),
+                            Some(lookup_batch::Repository {
+                                object: Some(GitObject::Other {}),
+                                ..
+                            }) => Err("Not a commit".to_string()),
+                            Some(lookup_batch::Repository {
+                                release: Some(v), ..
+                            }) => release_result(repo, v),
+                            _ => Err(message()),
+                        };
+                        ref_outcomes[slot] = Some(outcome);
+                    }
+                }
+            }
+        }
+        Ok((
+            outcomes
+                .into_iter()
+                .map(|o| o.unwrap_or_else(|| Err("Unable to read result".to_string())))
+                .collect(),
+            ref_outcomes
+                .into_iter()
+                .map(|o| o.unwrap_or_else(|| Err("Unable to read result".to_string())))
+                .collect(),
+        ))
+    }
+
+    /// Sends a batch query for the supplied lookups, returning results keyed by alias and any query errors
+    ///
+    /// Returns an error message if the query could not be made, such as when rate limited
+    async fn fetch_lookups(&self, lookups: &[Lookup]) -> Result<BatchResponse, String> {
+        let storage = self.storage;
+        if let Some(reset_at) = rate_limited_until(storage) {
+            warn!(
+                "Github rate limit reached, refusing to search until {:?}",
+                reset_at
+            );
+            return Err(format!(
+                "Github search rate limit reached. Searches will resume at {} UTC",
+                humantime::format_rfc3339_seconds(reset_at)
+            ));
+        }
+        let query = LookupBatch::build_query(lookups);
+        let response_body = match self
+            .api_client
+            .post(self.graphql_url)
+            .bearer_auth(self.token)
+            .header(header::USER_AGENT, self.user_agent.clone())
+            .json(&query)
+            .send()
+            .await
+        {
+            Ok(r) => {
+                let headers = r.headers().clone();
+                let response_body: graphql_client::Response<lookup_batch::ResponseData> =
+                    match r.json().await {
+                        Ok(b) => b,
+                        Err(e) => {
+                            error!("No response body found. Error is {:?}", e);
+                            return Err(UNAVAILABLE.to_string());
+                        }
+                    };
+                match response_body.data.as_ref().map(|d| &d.rate_limit) {
+                    Some(Some(v)) => save_rate_limit(storage, v.cost, v.remaining, &v.reset_at),
+                    _ => save_rate_limit_from_headers(storage, &headers),
+                }
+                response_body
+            }
+            Err(e) => {
+                error!("Query failed, Error is {:?}", e);
+                return Err(UNAVAILABLE.to_string());
+            }
+        };
+        let errors = response_body.errors.unwrap_or_default();
+        match response_body.data {
+            Some(d) => Ok((d.lookups, errors)),
+            None => {
+                error!("Missing response data. Errors are {:?}", errors);
+                Err(UNAVAILABLE.to_string())
+            }
+        }
+    }
+}
+
+/// Converts a found issue or pull into a search result
+fn search_result(repo: String, details: IssueOrPullDetails) -> Option<SearchResult> {
+    let url = match details {
+        Issue(ref v) => &v.url,
+        PullRequest(ref v) => &v.url,
+    };
+    let url = match Url::parse(url) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Unable to parse result {:?} to Url due to error {:?}",
+                url, e
+            );
+            return None;
+        }
+    };
+    let result = match details {
+        Issue(v) => SearchResult {
+            repo,
+            number: v.number,
+            kind: SearchResultKind::Issue,
+            title: v.title,
+            state: match v.state {
+                IssueState::OPEN => SearchResultState::Open,
+                _ => SearchResultState::Closed,
+            },
+            author: v.author.map(|a| a.login),
+            labels: v
+                .labels
+                .and_then(|l| l.nodes)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|l| l.name)
+                .collect(),
+            url,
+        },
+        PullRequest(v) => SearchResult {
+            repo,
+            number: v.number,
+            kind: SearchResultKind::PullRequest,
+            title: v.title,
+            state: match v.state {
+                PullRequestState::OPEN => SearchResultState::Open,
+                PullRequestState::MERGED => SearchResultState::Merged,
+                _ => SearchResultState::Closed,
+            },
+            author: v.author.map(|a| a.login),
+            labels: v
+                .labels
+                .and_then(|l| l.nodes)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|l| l.name)
+                .collect(),
+            url,
+        },
+    };
+    Some(result)
+}
+
+/// Converts a found commit into a ref result
+fn commit_result(repo: String, commit: CommitDetails) -> RefOutcome {
+    let url = Url::parse(&commit.url).map_err(|e| {
+        error!(
+            "Unable to parse result {:?} to Url. Error is {:?}",
+            commit.url, e
+        );
+        "Unable to read result".to_string()
+    })?;
+    let author = commit
+        .author
+        .and_then(|a| a.user.map(|u| u.login).or(a.name));
+    Ok(RefResult {
+        repo,
+        kind: RefResultKind::Commit,
+        name: commit.abbreviated_oid,
+        title: Some(commit.message_headline),
+        author,
+        date: Some(date(&commit.committed_date)),
+        url,
+    })
+}
+
+/// Converts a found release into a ref result
+fn release_result(repo: String, release: ReleaseDetails) -> RefOutcome {
+    let url = Url::parse(&release.url).map_err(|e| {
+        error!(
+            "Unable to parse result {:?} to Url. Error is {:?}",
+            release.url, e
+        );
+        "Unable to read result".to_string()
+    })?;
+    Ok(RefResult {
+        repo,
+        kind: if release.is_prerelease {
+            RefResultKind::Prerelease
+        } else {
+            RefResultKind::Release
+        },
+        name: release.tag_name,
+        title: release.name.filter(|n| !n.is_empty()),
+        author: release.author.map(|a| a.login),
+        date: release.published_at.as_deref().map(date),
+        url,
+    })
+}
+
+/// Returns the date portion of an ISO-8601 timestamp
+fn date(timestamp: &str) -> String {
+    timestamp.split('T').next().unwrap_or(timestamp).to_string()
+}
+
+/// Returns the time searches can resume if the last known rate limit budget is too low to make another query
+pub(super) fn rate_limited_until(storage: &Database) -> Option<SystemTime> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    let rate_limit = match r.get().primary::<GithubRateLimit>(1u8) {
+        Ok(v) => v?,
+        Err(e) => {
+            error!("Unable to fetch github rate limit from db. Error is {}", e);
+            return None;
+        }
+    };
+    let reset_at = SystemTime::UNIX_EPOCH + Duration::from_secs(rate_limit.reset_at);
+    if rate_limit.remaining < rate_limit.cost.max(1) && SystemTime::now() < reset_at {
+        Some(reset_at)
+    } else {
+        None
+    }
+}
+
+/// Saves the rate limit information returned by a query
+fn save_rate_limit(storage: &Database, cost: i64, remaining: i64, reset_at: &str) {
+    let reset_at = match humantime::parse_rfc3339(reset_at) {
+        Ok(v) => v
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        Err(e) => {
+            error!(
+                "Unable to parse rate limit reset time {:?}. Error is {}",
+                reset_at, e
+            );
+            return;
+        }
+    };
+    store_rate_limit(
+        storage,
+        GithubRateLimit {
+            id: 1,
+            cost,
+            remaining,
+            reset_at,
+        },
+    );
+}
+
+/// Saves the rate limit information from response headers
+///
+/// Used when the response has no data, such as when the rate limit has already been exceeded,
+/// and for mutations which cannot query the rate limit
+pub(super) fn save_rate_limit_from_headers(storage: &Database, headers: &header::HeaderMap) {
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    match (
+        header_value("x-ratelimit-remaining"),
+        header_value("x-ratelimit-reset"),
+    ) {
+        (Some(remaining), Some(reset_at)) => store_rate_limit(
+            storage,
+            GithubRateLimit {
+                id: 1,
+                cost: 1,
+                remaining: remaining as i64,
+                reset_at,
+            },
+        ),
+        _ => debug!("No rate limit information found in response"),
+    }
+}
+
+fn store_rate_limit(storage: &Database, rate_limit: GithubRateLimit) {
+    trace!("Saving github rate limit {:?}", rate_limit);
+    let rw = match storage.rw_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Unable to get read write transaction from db. Error is {}",
+                e
+            );
+            return;
+        }
+    };
+    let old = match rw.get().primary::<GithubRateLimit>(1u8) {
+        Ok(Some(v)) => v,
+        _ => rate_limit.clone(),
+    };
+    if let Err(e) = insert_or_update(&rw, old, rate_limit) {
+        error!("Unable to save github rate limit. Error is {}", e);
+        return;
+    }
+    if let Err(e) = rw.commit() {
+        error!("Unable to commit github rate limit to db. Error is {}", e);
+    }
+}
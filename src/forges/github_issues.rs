@@ -0,0 +1,270 @@
+//! Issue management for repos hosted on github.com
+//!
+//! Mutations require node IDs, so targets are looked up by repo and number first
+
+use super::github::{rate_limited_until, save_rate_limit_from_headers};
+use super::GithubBackend;
+use crate::helpers::remove_cached_search_result;
+use crate::queries::{
+    add_comment, add_labels, close_issue, close_pull_request, create_issue, issue_target,
+    label_target, repository_target, AddComment, AddLabels, CloseIssue, ClosePullRequest,
+    CreateIssue, IssueTarget, LabelTarget, RepositoryTarget,
+};
+use graphql_client::GraphQLQuery;
+use reqwest::header;
+use reqwest::Url;
+use tracing::{debug, error, warn};
+
+/// Error message used when github could not be updated
+const UNAVAILABLE: &str = "Unable to update github";
+
+/// An issue or pull found by number
+enum Target {
+    /// An issue with its node ID and URL
+    Issue { id: String, url: String },
+    /// A pull with its node ID and URL
+    PullRequest { id: String, url: String },
+}
+
+impl Target {
+    fn id(&self) -> &str {
+        match self {
+            Target::Issue { id, .. } | Target::PullRequest { id, .. } => id,
+        }
+    }
+
+    fn url(&self) -> &str {
+        match self {
+            Target::Issue { url, .. } | Target::PullRequest { url, .. } => url,
+        }
+    }
+}
+
+impl GithubBackend<'_> {
+    /// Creates an issue in the supplied repo, returning its URL
+    pub async fn create_issue(
+        &self,
+        repo: &str,
+        title: &str,
+        body: Option<&str>,
+    ) -> Result<Url, String> {
+        let (owner, name) = split_repo(repo)?;
+        let repository = self
+            .post::<RepositoryTarget>(repository_target::Variables {
+                owner: owner.to_string(),
+                name: name.to_string(),
+            })
+            .await?
+            .repository
+            .ok_or_else(|| "Repo not found".to_string())?;
+        let issue = self
+            .post::<CreateIssue>(create_issue::Variables {
+                repository_id: repository.id,
+                title: title.to_string(),
+                body: body.map(|b| b.to_string()),
+            })
+            .await?
+            .create_issue
+            .and_then(|c| c.issue)
+            .ok_or_else(|| UNAVAILABLE.to_string())?;
+        parse_url(&issue.url)
+    }
+
+    /// Closes the supplied issue or pull, returning its URL
+    pub async fn close_issue(&self, repo: &str, number: i64) -> Result<Url, String> {
+        let target = self.target(repo, number).await?;
+        match &target {
+            Target::Issue { id, .. } => {
+                self.post::<CloseIssue>(close_issue::Variables {
+                    issue_id: id.clone(),
+                })
+                .await?;
+            }
+            Target::PullRequest { id, .. } => {
+                self.post::<ClosePullRequest>(close_pull_request::Variables {
+                    pull_request_id: id.clone(),
+                })
+                .await?;
+            }
+        }
+        remove_cached_search_result(self.storage, repo, number);
+        parse_url(target.url())
+    }
+
+    /// Adds existing labels to the supplied issue or pull in a single mutation
+    ///
+    /// Returns its URL and the names of any labels that were not found and so were not added
+    pub async fn add_labels(
+        &self,
+        repo: &str,
+        number: i64,
+        labels: &[String],
+    ) -> Result<(Url, Vec<String>), String> {
+        let (owner, name) = split_repo(repo)?;
+        let mut missing = labels.to_vec();
+        let mut label_ids = Vec::new();
+        let mut target = None;
+        let mut after = None;
+        loop {
+            let repository = self
+                .post::<LabelTarget>(label_target::Variables {
+                    owner: owner.to_string(),
+                    name: name.to_string(),
+                    number,
+                    after,
+                })
+                .await?
+                .repository
+                .ok_or_else(|| "Repo not found".to_string())?;
+            if target.is_none() {
+                target = Some(match repository.issue_or_pull_request {
+                    Some(label_target::TargetDetails::Issue(v)) => Target::Issue {
+                        id: v.id,
+                        url: v.url,
+                    },
+                    Some(label_target::TargetDetails::PullRequest(v)) => Target::PullRequest {
+                        id: v.id,
+                        url: v.url,
+                    },
+                    None => return Err("Not found".to_string()),
+                });
+            }
+            for label in repository
+                .labels
+                .iter()
+                .flat_map(|l| l.nodes.iter().flatten().flatten())
+            {
+                let before = missing.len();
+                missing.retain(|m| !m.eq_ignore_ascii_case(&label.name));
+                if missing.len() != before {
+                    label_ids.push(label.id.clone());
+                }
+            }
+            let page_info = repository.labels.map(|l| l.page_info);
+            match page_info {
+                Some(v) if v.has_next_page && !missing.is_empty() => after = v.end_cursor,
+                _ => break,
+            }
+        }
+        // The loop only ends after the first page has set the target
+        let target = target.ok_or_else(|| "Not found".to_string())?;
+        if label_ids.is_empty() {
+            return Err(format!("Labels not found: {}", missing.join(", ")));
+        }
+        self.post::<AddLabels>(add_labels::Variables {
+            labelable_id: target.id().to_string(),
+            label_ids,
+        })
+        .await?;
+        remove_cached_search_result(self.storage, repo, number);
+        Ok((parse_url(target.url())?, missing))
+    }
+
+    /// Comments on the supplied issue or pull, returning the URL of the comment
+    pub async fn add_comment(&self, repo: &str, number: i64, body: &str) -> Result<Url, String> {
+        let target = self.target(repo, number).await?;
+        let comment = self
+            .post::<AddComment>(add_comment::Variables {
+                subject_id: target.id().to_string(),
+                body: body.to_string(),
+            })
+            .await?
+            .add_comment
+            .and_then(|c| c.comment_edge)
+            .and_then(|e| e.node);
+        match comment {
+            Some(v) => parse_url(&v.url),
+            None => parse_url(target.url()),
+        }
+    }
+
+    /// Finds the node ID and URL of an issue or pull
+    async fn target(&self, repo: &str, number: i64) -> Result<Target, String> {
+        let (owner, name) = split_repo(repo)?;
+        let target = self
+            .post::<IssueTarget>(issue_target::Variables {
+                owner: owner.to_string(),
+                name: name.to_string(),
+                number,
+            })
+            .await?
+            .repository
+            .ok_or_else(|| "Repo not found".to_string())?
+            .issue_or_pull_request;
+        match target {
+            Some(issue_target::TargetDetails::Issue(v)) => Ok(Target::Issue {
+                id: v.id,
+                url: v.url,
+            }),
+            Some(issue_target::TargetDetails::PullRequest(v)) => Ok(Target::PullRequest {
+                id: v.id,
+                url: v.url,
+            }),
+            None => Err("Not found".to_string()),
+        }
+    }
+
+    /// Sends a query or mutation, returning its data or the first error message github returned
+    ///
+    /// Refuses to send anything while rate limited
+    async fn post<Q: GraphQLQuery>(
+        &self,
+        variables: Q::Variables,
+    ) -> Result<Q::ResponseData, String> {
+        if let Some(reset_at) = rate_limited_until(self.storage) {
+            warn!(
+                "Github rate limit reached, refusing to update github until {:?}",
+                reset_at
+            );
+            return Err(format!(
+                "Github rate limit reached. Updates will resume at {} UTC",
+                humantime::format_rfc3339_seconds(reset_at)
+            ));
+        }
+        let query = Q::build_query(variables);
+        let response = match self
+            .api_client
+            .post(self.graphql_url)
+            .bearer_auth(self.token)
+            .header(header::USER_AGENT, self.user_agent.clone())
+            .json(&query)
+            .send()
+            .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Query failed, Error is {:?}", e);
+                return Err(UNAVAILABLE.to_string());
+            }
+        };
+        save_rate_limit_from_headers(self.storage, response.headers());
+        let response_body: graphql_client::Response<Q::ResponseData> = match response.json().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("No response body found. Error is {:?}", e);
+                return Err(UNAVAILABLE.to_string());
+            }
+        };
+        if let Some(e) = response_body.errors.and_then(|e| e.into_iter().next()) {
+            debug!("{} failed: {}", query.operation_name, e.message);
+            return Err(e.message);
+        }
+        response_body.data.ok_or_else(|| {
+            error!("Missing response data for {}", query.operation_name);
+            UNAVAILABLE.to_string()
+        })
+    }
+}
+
+/// Splits a repo into its owner and name
+fn split_repo(repo: &str) -> Result<(&str, &str), String> {
+    repo.split_once('/')
+        .ok_or_else(|| "Invalid repo".to_string())
+}
+
+fn parse_url(url: &str) -> Result<Url, String> {
+    Url::parse(url).map_err(|e| {
+        error!("Unable to parse result {:?} to Url. Error is {:?}", url, e);
+        "Unable to read result".to_string()
+    })
+}
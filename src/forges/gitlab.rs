@@ -0,0 +1,135 @@
+//! Search backend for repos hosted on GitLab instances
+//!
+//! GitLab numbers issues and merge requests separately, so issues are searched first and
+//! merge requests are searched if no issue exists
+
+use super::{Search, SearchBackend, SearchOutcome};
+use crate::helpers::{SearchResult, SearchResultKind, SearchResultState};
+use axum::async_trait;
+use reqwest::header::{self, HeaderValue};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+/// Searches a GitLab instance with its REST API
+pub struct GitlabBackend<'a> {
+    /// Reqwest client used for requests
+    pub api_client: &'a reqwest::Client,
+    /// Base URL of the instance
+    pub url: &'a Url,
+    /// Optional access token for private repos
+    pub token: Option<&'a str>,
+    /// UserAgent used by reqwest
+    pub user_agent: &'a HeaderValue,
+}
+
+#[derive(Debug, Deserialize)]
+/// Issue or merge request as returned by the GitLab API
+struct GitlabItem {
+    iid: i64,
+    title: String,
+    state: String,
+    web_url: String,
+    author: Option<GitlabUser>,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabUser {
+    username: String,
+}
+
+#[async_trait]
+impl SearchBackend for GitlabBackend<'_> {
+    async fn search(&self, searches: &[Search]) -> Result<Vec<SearchOutcome>, String> {
+        let mut outcomes = Vec::new();
+        for search in searches {
+            outcomes.push(self.search_one(search).await);
+        }
+        Ok(outcomes)
+    }
+}
+
+impl GitlabBackend<'_> {
+    async fn search_one(&self, search: &Search) -> SearchOutcome {
+        match self.fetch(search, "issues").await? {
+            Some(v) => self.search_result(search, v, SearchResultKind::Issue),
+            None => match self.fetch(search, "merge_requests").await? {
+                Some(v) => self.search_result(search, v, SearchResultKind::PullRequest),
+                None => Err("Not found".to_string()),
+            },
+        }
+    }
+
+    /// Fetches an item of the supplied kind, returning `None` if it does not exist
+    ///
+    /// Returns an error message if the instance could not be searched
+    async fn fetch(&self, search: &Search, kind: &str) -> Result<Option<GitlabItem>, String> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| format!("Invalid forge url {}", self.url))?
+            .pop_if_empty()
+            .extend(&["api", "v4", "projects", &search.repo, kind])
+            .push(&search.number.to_string());
+        let mut request = self
+            .api_client
+            .get(url)
+            .header(header::USER_AGENT, self.user_agent.clone());
+        if let Some(token) = self.token {
+            request = request.header("PRIVATE-TOKEN", token);
+        }
+        let response = match request.send().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Gitlab query failed. Error is {:?}", e);
+                return Err("Unable to search forge".to_string());
+            }
+        };
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => return Ok(None),
+            s => {
+                debug!("Gitlab search for {:?} returned status {}", search, s);
+                return Err(format!("Forge returned {}", s));
+            }
+        }
+        match response.json().await {
+            Ok(v) => Ok(Some(v)),
+            Err(e) => {
+                error!("Unable to parse gitlab response. Error is {:?}", e);
+                Err("Unable to read result".to_string())
+            }
+        }
+    }
+
+    fn search_result(
+        &self,
+        search: &Search,
+        item: GitlabItem,
+        kind: SearchResultKind,
+    ) -> SearchOutcome {
+        let url = Url::parse(&item.web_url).map_err(|e| {
+            error!(
+                "Unable to parse result {:?} to Url. Error is {:?}",
+                item.web_url, e
+            );
+            "Unable to read result".to_string()
+        })?;
+        let state = match item.state.as_str() {
+            "opened" => SearchResultState::Open,
+            "merged" => SearchResultState::Merged,
+            _ => SearchResultState::Closed,
+        };
+        Ok(SearchResult {
+            repo: search.repo.clone(),
+            number: item.iid,
+            kind,
+            title: item.title,
+            state,
+            author: item.author.map(|a| a.username),
+            labels: item.labels,
+            url,
+        })
+    }
+}
@@ -0,0 +1,118 @@
+//! Search backends for the forges that host searchable repos
+//!
+//! Relevant tests are in a test submodule
+//!
+//! Tests run the REST backends against a local mock server
+
+#[cfg(test)]
+mod tests;
+
+mod gitea;
+mod github;
+mod github_issues;
+mod gitlab;
+
+pub use gitea::GiteaBackend;
+pub use github::GithubBackend;
+pub use gitlab::GitlabBackend;
+
+use crate::config::{Forge, MatrixListenerConfig};
+use crate::helpers::{RefResult, SearchResult};
+use axum::async_trait;
+use github::GITHUB_GRAPHQL_URL;
+use native_db::Database;
+use std::fmt;
+
+/// Result of a single search, with a message to show the user if it failed
+pub type SearchOutcome = Result<SearchResult, String>;
+
+/// Result of a single ref search, with a message to show the user if it failed
+pub type RefOutcome = Result<RefResult, String>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A single issue or pull to search for
+pub struct Search {
+    /// Path of the repo on its forge
+    pub repo: String,
+    /// Issue or pull number
+    pub number: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A single commit or release to search for
+///
+/// Only supported on github
+pub enum RefSearch {
+    /// A commit by full or abbreviated sha
+    Commit {
+        /// Path of the repo on its forge
+        repo: String,
+        /// Full or abbreviated sha of the commit
+        sha: String,
+    },
+    /// A release by tag name
+    Release {
+        /// Path of the repo on its forge
+        repo: String,
+        /// Tag name of the release
+        tag: String,
+    },
+}
+
+impl fmt::Display for RefSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefSearch::Commit { repo, sha } => write!(f, "{}@{}", repo, sha),
+            RefSearch::Release { repo, tag } => write!(f, "{}@{}", repo, tag),
+        }
+    }
+}
+
+#[async_trait]
+/// A forge that can be searched for issues and pulls
+pub trait SearchBackend {
+    /// Looks up all supplied searches, returning an outcome for each in the same order
+    ///
+    /// Returns an error message if the forge could not be searched at all
+    async fn search(&self, searches: &[Search]) -> Result<Vec<SearchOutcome>, String>;
+}
+
+/// Returns the backend used to search repos on the supplied forge
+pub fn backend<'a>(
+    forge: &'a Forge,
+    config: &'a MatrixListenerConfig,
+    api_client: &'a reqwest::Client,
+    storage: &'a Database<'a>,
+) -> Box<dyn SearchBackend + Send + Sync + 'a> {
+    match forge {
+        Forge::Github => Box::new(github_backend(config, api_client, storage)),
+        Forge::Gitea { url, token } => Box::new(GiteaBackend {
+            api_client,
+            url,
+            token: token.as_deref(),
+            user_agent: &config.user_agent,
+        }),
+        Forge::Gitlab { url, token } => Box::new(GitlabBackend {
+            api_client,
+            url,
+            token: token.as_deref(),
+            user_agent: &config.user_agent,
+        }),
+    }
+}
+
+/// Returns the github backend, which also supports searching commits and releases
+pub fn github_backend<'a>(
+    config: &'a MatrixListenerConfig,
+    api_client: &'a reqwest::Client,
+    storage: &'a Database<'a>,
+) -> GithubBackend<'a> {
+    GithubBackend {
+        api_client,
+        graphql_url: GITHUB_GRAPHQL_URL,
+        token: &config.gh_access_token,
+        user_agent: &config.user_agent,
+        storage,
+        cache_ttl: config.gh_search_cache_ttl,
+    }
+}
@@ -0,0 +1,110 @@
+use super::super::{GiteaBackend, Search, SearchBackend, SearchOutcome};
+use crate::helpers::{SearchResultKind, SearchResultState};
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn search(number: i64) -> Search {
+    Search {
+        repo: "jellyfin/jellyfin".to_string(),
+        number,
+    }
+}
+
+async fn run(server: &MockServer, token: Option<&str>, searches: &[Search]) -> Vec<SearchOutcome> {
+    let client = reqwest::Client::new();
+    let url = Url::parse(&server.uri()).unwrap();
+    let user_agent = HeaderValue::from_static("jellyfin-matrix-bot/tester");
+    let backend = GiteaBackend {
+        api_client: &client,
+        url: &url,
+        token,
+        user_agent: &user_agent,
+    };
+    backend.search(searches).await.unwrap()
+}
+
+#[tokio::test]
+async fn issue() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/jellyfin/jellyfin/issues/12"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "number": 12,
+            "title": "Playback fails",
+            "state": "open",
+            "html_url": "https://git.example.com/jellyfin/jellyfin/issues/12",
+            "user": { "login": "someone" },
+            "labels": [{ "name": "bug" }],
+            "pull_request": null
+        })))
+        .mount(&server)
+        .await;
+
+    let result = run(&server, None, &[search(12)]).await.remove(0).unwrap();
+    assert_eq!(SearchResultKind::Issue, result.kind);
+    assert_eq!(SearchResultState::Open, result.state);
+    assert_eq!("Playback fails", result.title);
+    assert_eq!(Some("someone".to_string()), result.author);
+    assert_eq!(vec!["bug".to_string()], result.labels);
+    assert_eq!("jellyfin/jellyfin", result.repo);
+}
+
+#[tokio::test]
+async fn merged_pull() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/jellyfin/jellyfin/issues/34"))
+        .and(header("Authorization", "token secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "number": 34,
+            "title": "Fix playback",
+            "state": "closed",
+            "html_url": "https://git.example.com/jellyfin/jellyfin/pulls/34",
+            "user": { "login": "someone" },
+            "labels": [],
+            "pull_request": { "merged": true }
+        })))
+        .mount(&server)
+        .await;
+
+    let result = run(&server, Some("secret"), &[search(34)])
+        .await
+        .remove(0)
+        .unwrap();
+    assert_eq!(SearchResultKind::PullRequest, result.kind);
+    assert_eq!(SearchResultState::Merged, result.state);
+}
+
+#[tokio::test]
+async fn partial_failure() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/jellyfin/jellyfin/issues/12"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "number": 12,
+            "title": "Playback fails",
+            "state": "closed",
+            "html_url": "https://git.example.com/jellyfin/jellyfin/issues/12",
+            "user": { "login": "someone" },
+            "labels": [],
+            "pull_request": null
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/jellyfin/jellyfin/issues/99999"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let outcomes = run(&server, None, &[search(12), search(99999)]).await;
+    assert_eq!(2, outcomes.len());
+    assert_eq!(
+        SearchResultState::Closed,
+        outcomes[0].as_ref().unwrap().state
+    );
+    assert!(matches!(&outcomes[1], Err(e) if e == "Not found"));
+}
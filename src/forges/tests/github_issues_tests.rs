@@ -0,0 +1,213 @@
+use super::super::GithubBackend;
+use crate::test_helpers::database;
+use native_db::Database;
+use reqwest::header::HeaderValue;
+use serde_json::{json, Value};
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Answers requests for one operation with the supplied data, expecting it to be sent `calls` times
+async fn mock_operation(server: &MockServer, operation: Value, data: Value, calls: u64) {
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_partial_json(operation))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": data })))
+        .expect(calls)
+        .mount(server)
+        .await;
+}
+
+/// Creates a backend sending requests to the mock server
+fn backend<'a>(server: &MockServer, storage: &'a Database<'a>) -> GithubBackend<'a> {
+    GithubBackend {
+        api_client: Box::leak(Box::new(reqwest::Client::new())),
+        graphql_url: Box::leak(format!("{}/graphql", server.uri()).into_boxed_str()),
+        token: "secret",
+        user_agent: Box::leak(Box::new(HeaderValue::from_static(
+            "jellyfin-matrix-bot/tester",
+        ))),
+        storage,
+        cache_ttl: Duration::from_secs(60),
+    }
+}
+
+fn issue_target(typename: &str, url: &str) -> Value {
+    json!({ "__typename": typename, "id": "TARGET", "url": url })
+}
+
+#[tokio::test]
+async fn creates_issue() {
+    let server = MockServer::start().await;
+    let storage = database();
+    mock_operation(
+        &server,
+        json!({ "operationName": "RepositoryTarget" }),
+        json!({ "repository": { "id": "REPO" } }),
+        1,
+    )
+    .await;
+    mock_operation(
+        &server,
+        json!({
+            "operationName": "CreateIssue",
+            "variables": { "repositoryId": "REPO", "title": "Crash", "body": "On start" }
+        }),
+        json!({ "createIssue": { "issue": { "url": "https://github.com/jellyfin/jellyfin/issues/1" } } }),
+        1,
+    )
+    .await;
+
+    let url = backend(&server, &storage)
+        .create_issue("jellyfin/jellyfin", "Crash", Some("On start"))
+        .await
+        .unwrap();
+    assert_eq!(
+        "https://github.com/jellyfin/jellyfin/issues/1",
+        url.as_str()
+    );
+}
+
+#[tokio::test]
+async fn closes_pull_request() {
+    let server = MockServer::start().await;
+    let storage = database();
+    let url = "https://github.com/jellyfin/jellyfin/pull/2";
+    mock_operation(
+        &server,
+        json!({ "operationName": "IssueTarget", "variables": { "number": 2 } }),
+        json!({ "repository": { "issueOrPullRequest": issue_target("PullRequest", url) } }),
+        1,
+    )
+    .await;
+    mock_operation(
+        &server,
+        json!({ "operationName": "ClosePullRequest", "variables": { "pullRequestId": "TARGET" } }),
+        json!({ "closePullRequest": { "pullRequest": { "url": url } } }),
+        1,
+    )
+    .await;
+    mock_operation(
+        &server,
+        json!({ "operationName": "CloseIssue" }),
+        json!({ "closeIssue": { "issue": { "url": url } } }),
+        0,
+    )
+    .await;
+
+    let result = backend(&server, &storage)
+        .close_issue("jellyfin/jellyfin", 2)
+        .await
+        .unwrap();
+    assert_eq!(url, result.as_str());
+}
+
+#[tokio::test]
+async fn adds_comment() {
+    let server = MockServer::start().await;
+    let storage = database();
+    let comment = "https://github.com/jellyfin/jellyfin/issues/3#issuecomment-1";
+    mock_operation(
+        &server,
+        json!({ "operationName": "IssueTarget" }),
+        json!({ "repository": { "issueOrPullRequest": issue_target("Issue", "https://github.com/jellyfin/jellyfin/issues/3") } }),
+        1,
+    )
+    .await;
+    mock_operation(
+        &server,
+        json!({
+            "operationName": "AddComment",
+            "variables": { "subjectId": "TARGET", "body": "Fixed in 10.9" }
+        }),
+        json!({ "addComment": { "commentEdge": { "node": { "url": comment } } } }),
+        1,
+    )
+    .await;
+
+    let result = backend(&server, &storage)
+        .add_comment("jellyfin/jellyfin", 3, "Fixed in 10.9")
+        .await
+        .unwrap();
+    assert_eq!(comment, result.as_str());
+}
+
+/// Mounts two pages of labels, the second only returned for the cursor of the first
+async fn mock_labels(server: &MockServer) {
+    let target = issue_target("Issue", "https://github.com/jellyfin/jellyfin/issues/4");
+    mock_operation(
+        server,
+        json!({ "operationName": "LabelTarget", "variables": { "after": "PAGE1" } }),
+        json!({ "repository": {
+            "issueOrPullRequest": target,
+            "labels": {
+                "nodes": [{ "id": "DOCS", "name": "docs" }],
+                "pageInfo": { "hasNextPage": false, "endCursor": "PAGE2" }
+            }
+        } }),
+        1,
+    )
+    .await;
+    mock_operation(
+        server,
+        json!({ "operationName": "LabelTarget" }),
+        json!({ "repository": {
+            "issueOrPullRequest": target,
+            "labels": {
+                "nodes": [{ "id": "BUG", "name": "bug" }, { "id": "ENHANCEMENT", "name": "enhancement" }],
+                "pageInfo": { "hasNextPage": true, "endCursor": "PAGE1" }
+            }
+        } }),
+        1,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn adds_found_labels_in_one_mutation() {
+    let server = MockServer::start().await;
+    let storage = database();
+    mock_labels(&server).await;
+    mock_operation(
+        &server,
+        json!({
+            "operationName": "AddLabels",
+            "variables": { "labelableId": "TARGET", "labelIds": ["BUG", "DOCS"] }
+        }),
+        json!({ "addLabelsToLabelable": { "clientMutationId": null } }),
+        1,
+    )
+    .await;
+
+    let labels = ["Bug".to_string(), "docs".to_string(), "missing".to_string()];
+    let (url, missing) = backend(&server, &storage)
+        .add_labels("jellyfin/jellyfin", 4, &labels)
+        .await
+        .unwrap();
+    assert_eq!(
+        "https://github.com/jellyfin/jellyfin/issues/4",
+        url.as_str()
+    );
+    assert_eq!(vec!["missing".to_string()], missing);
+}
+
+#[tokio::test]
+async fn no_mutation_without_found_labels() {
+    let server = MockServer::start().await;
+    let storage = database();
+    mock_labels(&server).await;
+    mock_operation(
+        &server,
+        json!({ "operationName": "AddLabels" }),
+        json!({ "addLabelsToLabelable": { "clientMutationId": null } }),
+        0,
+    )
+    .await;
+
+    let labels = ["missing".to_string(), "unknown".to_string()];
+    let error = backend(&server, &storage)
+        .add_labels("jellyfin/jellyfin", 4, &labels)
+        .await
+        .unwrap_err();
+    assert_eq!("Labels not found: missing, unknown", error);
+}
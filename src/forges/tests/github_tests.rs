@@ -0,0 +1,143 @@
+use super::super::{GithubBackend, Search, SearchOutcome};
+use crate::database::models::{GithubRateLimit, GithubSearchCache};
+use crate::helpers::{SearchResultKind, SearchResultState};
+use crate::test_helpers::database;
+use native_db::Database;
+use reqwest::header::HeaderValue;
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn search() -> Search {
+    Search {
+        repo: "jellyfin/jellyfin".to_string(),
+        number: 12,
+    }
+}
+
+fn response(title: &str) -> Value {
+    json!({
+        "data": {
+            "rateLimit": { "cost": 1, "remaining": 4999, "resetAt": "2030-01-01T00:00:00Z" },
+            "lookup0": {
+                "issueOrPullRequest": {
+                    "__typename": "Issue",
+                    "number": 12,
+                    "title": title,
+                    "state": "OPEN",
+                    "url": "https://github.com/jellyfin/jellyfin/issues/12",
+                    "resourcePath": "/jellyfin/jellyfin/issues/12",
+                    "author": { "__typename": "User", "login": "someone" },
+                    "labels": { "nodes": [{ "name": "bug" }] }
+                }
+            }
+        }
+    })
+}
+
+async fn mock_github(title: &str, calls: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response(title)))
+        .expect(calls)
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn run(
+    server: &MockServer,
+    storage: &Database<'_>,
+    cache_ttl: Duration,
+) -> Result<Vec<SearchOutcome>, String> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/graphql", server.uri());
+    let user_agent = HeaderValue::from_static("jellyfin-matrix-bot/tester");
+    let backend = GithubBackend {
+        api_client: &client,
+        graphql_url: &url,
+        token: "secret",
+        user_agent: &user_agent,
+        storage,
+        cache_ttl,
+    };
+    backend.lookup(&[search()], &[]).await.map(|(v, _)| v)
+}
+
+#[tokio::test]
+async fn refuses_when_rate_limited() {
+    let server = mock_github("Playback fails", 0).await;
+    let storage = database();
+    let rw = storage.rw_transaction().unwrap();
+    rw.insert(GithubRateLimit {
+        id: 1,
+        cost: 1,
+        remaining: 0,
+        reset_at: 1893456000,
+    })
+    .unwrap();
+    rw.commit().unwrap();
+
+    let error = run(&server, &storage, Duration::from_secs(60))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "Github search rate limit reached. Searches will resume at 2030-01-01T00:00:00Z UTC",
+        error
+    );
+}
+
+#[tokio::test]
+async fn cached_within_ttl() {
+    let server = mock_github("Playback fails", 1).await;
+    let storage = database();
+
+    for _ in 0..2 {
+        let result = run(&server, &storage, Duration::from_secs(60))
+            .await
+            .unwrap()
+            .remove(0)
+            .unwrap();
+        assert_eq!(SearchResultKind::Issue, result.kind);
+        assert_eq!(SearchResultState::Open, result.state);
+        assert_eq!("Playback fails", result.title);
+        assert_eq!(vec!["bug".to_string()], result.labels);
+    }
+}
+
+#[tokio::test]
+async fn refetched_after_ttl() {
+    let server = mock_github("Playback fails on Android", 1).await;
+    let storage = database();
+    let rw = storage.rw_transaction().unwrap();
+    rw.insert(GithubSearchCache {
+        key: GithubSearchCache::key("jellyfin/jellyfin", 12),
+        repo: "jellyfin/jellyfin".to_string(),
+        number: 12,
+        kind: SearchResultKind::Issue,
+        title: "Playback fails".to_string(),
+        state: SearchResultState::Open,
+        author: Some("someone".to_string()),
+        labels: Vec::new(),
+        url: "https://github.com/jellyfin/jellyfin/issues/12".to_string(),
+        fetched_at: now() - 120,
+    })
+    .unwrap();
+    rw.commit().unwrap();
+
+    let result = run(&server, &storage, Duration::from_secs(60))
+        .await
+        .unwrap()
+        .remove(0)
+        .unwrap();
+    assert_eq!("Playback fails on Android", result.title);
+}
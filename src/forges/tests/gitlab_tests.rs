@@ -0,0 +1,99 @@
+use super::super::{GitlabBackend, Search, SearchBackend, SearchOutcome};
+use crate::helpers::{SearchResultKind, SearchResultState};
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn search(number: i64) -> Search {
+    Search {
+        repo: "jellyfin/clients/jellyfin-web".to_string(),
+        number,
+    }
+}
+
+async fn run(server: &MockServer, token: Option<&str>, searches: &[Search]) -> Vec<SearchOutcome> {
+    let client = reqwest::Client::new();
+    let url = Url::parse(&server.uri()).unwrap();
+    let user_agent = HeaderValue::from_static("jellyfin-matrix-bot/tester");
+    let backend = GitlabBackend {
+        api_client: &client,
+        url: &url,
+        token,
+        user_agent: &user_agent,
+    };
+    backend.search(searches).await.unwrap()
+}
+
+#[tokio::test]
+async fn issue() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/api/v4/projects/jellyfin%2Fclients%2Fjellyfin-web/issues/34",
+        ))
+        .and(header("PRIVATE-TOKEN", "secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "iid": 34,
+            "title": "Subtitles missing",
+            "state": "opened",
+            "web_url": "https://gitlab.example.com/jellyfin/clients/jellyfin-web/-/issues/34",
+            "author": { "username": "someone" },
+            "labels": ["bug", "web"]
+        })))
+        .mount(&server)
+        .await;
+
+    let result = run(&server, Some("secret"), &[search(34)])
+        .await
+        .remove(0)
+        .unwrap();
+    assert_eq!(SearchResultKind::Issue, result.kind);
+    assert_eq!(SearchResultState::Open, result.state);
+    assert_eq!("Subtitles missing", result.title);
+    assert_eq!(Some("someone".to_string()), result.author);
+    assert_eq!(vec!["bug".to_string(), "web".to_string()], result.labels);
+}
+
+#[tokio::test]
+async fn merge_request_fallback() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/api/v4/projects/jellyfin%2Fclients%2Fjellyfin-web/issues/56",
+        ))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/api/v4/projects/jellyfin%2Fclients%2Fjellyfin-web/merge_requests/56",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "iid": 56,
+            "title": "Add subtitles",
+            "state": "merged",
+            "web_url": "https://gitlab.example.com/jellyfin/clients/jellyfin-web/-/merge_requests/56",
+            "author": { "username": "someone" },
+            "labels": []
+        })))
+        .mount(&server)
+        .await;
+
+    let result = run(&server, None, &[search(56)]).await.remove(0).unwrap();
+    assert_eq!(SearchResultKind::PullRequest, result.kind);
+    assert_eq!(SearchResultState::Merged, result.state);
+}
+
+#[tokio::test]
+async fn not_found() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let outcomes = run(&server, None, &[search(78)]).await;
+    assert!(matches!(&outcomes[0], Err(e) if e == "Not found"));
+}
@@ -0,0 +1,4 @@
+mod gitea_tests;
+mod github_issues_tests;
+mod github_tests;
+mod gitlab_tests;
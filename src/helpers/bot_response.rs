@@ -1,6 +1,6 @@
 //! Helper type and associated functions to enable simple response building
 
-use super::ConvertedUnit;
+use super::{escape_html, ConvertedUnit, RefResult, SearchResult};
 use reqwest::Url;
 use ruma::OwnedUserId;
 use std::collections::HashSet;
@@ -12,11 +12,15 @@ pub struct MatrixNoticeResponse {
     /// List of converted units for response building
     conversions: Option<Vec<ConvertedUnit>>,
     /// List of gh search results for response building
-    gh_results: Option<Vec<Url>>,
+    gh_results: Option<Vec<SearchResult>>,
+    /// List of commit and release results for response building
+    ref_results: Option<Vec<RefResult>>,
     /// List of link results for response building
     links: Option<Vec<Url>>,
     /// Expanded text for response building
     expanded_text: Option<Vec<String>>,
+    /// List of errors to report back to the user for response building
+    errors: Option<Vec<String>>,
 }
 
 #[derive(Debug, Default)]
@@ -37,12 +41,18 @@ impl MatrixNoticeResponse {
     pub fn set_unit_conversions(&mut self, conversions: Vec<ConvertedUnit>) {
         self.conversions = Some(conversions)
     }
-    /// Sets member gh_results with supplied list of Urls
+    /// Sets member gh_results with supplied list of SearchResults
     ///
     /// Will overwrite if suppled a second time
-    pub fn set_gh_results(&mut self, gh_results: Vec<Url>) {
+    pub fn set_gh_results(&mut self, gh_results: Vec<SearchResult>) {
         self.gh_results = Some(gh_results)
     }
+    /// Sets member ref_results with supplied list of RefResults
+    ///
+    /// Will overwrite if suppled a second time
+    pub fn set_ref_results(&mut self, ref_results: Vec<RefResult>) {
+        self.ref_results = Some(ref_results)
+    }
     /// Sets member links with supplied list of Urls
     ///
     /// Will overwrite if suppled a second time
@@ -55,12 +65,62 @@ impl MatrixNoticeResponse {
     pub fn set_expanded_text(&mut self, expanded_text: Vec<String>) {
         self.expanded_text = Some(expanded_text)
     }
+    /// Adds supplied errors to member errors
+    ///
+    /// Will append if supplied a second time
+    pub fn add_errors(&mut self, errors: Vec<String>) {
+        match &mut self.errors {
+            Some(v) => v.extend(errors),
+            None => self.errors = Some(errors),
+        }
+    }
     /// Returns `true` if any member field is `Some`
     pub fn is_some(&self) -> bool {
         self.conversions.is_some()
             || self.gh_results.is_some()
+            || self.ref_results.is_some()
             || self.links.is_some()
             || self.expanded_text.is_some()
+            || self.errors.is_some()
+    }
+    /// Formats all member fields as HTML for use in formatted notices
+    pub fn format_text(&self) -> Option<String> {
+        if !self.is_some() {
+            return None;
+        }
+        let mut formatted_text = Vec::new();
+        if let Some(v) = &self.conversions {
+            for s in v {
+                formatted_text.push(escape_html(&s.to_string()));
+            }
+        }
+        if let Some(v) = &self.gh_results {
+            for s in v {
+                formatted_text.push(s.format_text());
+            }
+        }
+        if let Some(v) = &self.ref_results {
+            for s in v {
+                formatted_text.push(s.format_text());
+            }
+        }
+        if let Some(v) = &self.links {
+            for s in v {
+                let link = escape_html(s.as_ref());
+                formatted_text.push(format!("<a href=\"{}\">{}</a>", link, link));
+            }
+        }
+        if let Some(v) = &self.expanded_text {
+            for s in v {
+                formatted_text.push(escape_html(s).replace('\n', "<br>"));
+            }
+        }
+        if let Some(v) = &self.errors {
+            for s in v {
+                formatted_text.push(format!("<font color=\"#ff4b55\">{}</font>", escape_html(s)));
+            }
+        }
+        Some(formatted_text.join("<br>"))
     }
 }
 
@@ -129,7 +189,13 @@ impl fmt::Display for MatrixNoticeResponse {
         }
         if let Some(v) = &self.gh_results {
             for s in v {
-                response.push_str(s.as_ref());
+                response.push_str(&s.to_string());
+                response.push('\n')
+            }
+        }
+        if let Some(v) = &self.ref_results {
+            for s in v {
+                response.push_str(&s.to_string());
                 response.push('\n')
             }
         }
@@ -145,6 +211,12 @@ impl fmt::Display for MatrixNoticeResponse {
                 response.push('\n')
             }
         }
+        if let Some(v) = &self.errors {
+            for s in v {
+                response.push_str(s);
+                response.push('\n')
+            }
+        }
         let response = response.trim();
         write!(f, "{}", response)
     }
@@ -0,0 +1,19 @@
+//! Helper function used to make user supplied text safe to embed in HTML formatted messages
+
+/// Escapes the characters in supplied text that have special meaning in HTML
+///
+/// Returns the result
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,124 @@
+//! Helper functions for reading and writing group pings edited from chat
+
+use crate::config::MatrixListenerConfig;
+use crate::database::insert_or_update;
+use crate::database::models::GroupPing;
+use anyhow::Context;
+use native_db::Database;
+use ruma::{OwnedUserId, UserId};
+use std::collections::HashSet;
+use tracing::error;
+
+/// Returns the stored group ping with the supplied name if one exists
+pub fn stored_group_ping(storage: &Database, name: &str) -> Option<GroupPing> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    match r.get().primary::<GroupPing>(name.to_string()) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to fetch group ping from db. Error is {}", e);
+            None
+        }
+    }
+}
+
+/// Returns all stored group pings sorted by name
+pub fn stored_group_pings(storage: &Database) -> Vec<GroupPing> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return Vec::new();
+        }
+    };
+    let groups = match r.scan().primary::<GroupPing>() {
+        Ok(v) => v.all().collect(),
+        Err(e) => {
+            error!("Unable to scan group pings in db. Error is {}", e);
+            Vec::new()
+        }
+    };
+    groups
+}
+
+/// Saves a group ping, replacing any previous entry with the same name
+pub fn save_group_ping(storage: &Database, group: GroupPing) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let old = match rw.get().primary::<GroupPing>(group.name.clone()) {
+        Ok(Some(v)) => v,
+        _ => group.clone(),
+    };
+    insert_or_update(&rw, old, group)?;
+    rw.commit().context("Unable to commit group ping to db")
+}
+
+/// Removes the stored group ping with the supplied name, returning `false` if none exists
+pub fn remove_group_ping(storage: &Database, name: &str) -> anyhow::Result<bool> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let group = rw
+        .get()
+        .primary::<GroupPing>(name.to_string())
+        .context("Unable to fetch group ping from db")?;
+    match group {
+        Some(v) => {
+            rw.remove(v).context("Unable to remove group ping")?;
+            rw.commit()
+                .context("Unable to commit group ping removal to db")?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Returns everyone pinged by a group, following aliases to the groups they name
+///
+/// Members come from both the config file and chat for every group reached, and each group is only visited once so
+/// aliases that form a cycle can not loop forever
+pub fn group_members(
+    name: &str,
+    config: &MatrixListenerConfig,
+    stored: &[GroupPing],
+) -> HashSet<OwnedUserId> {
+    let mut users = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![name];
+    while let Some(group) = pending.pop() {
+        if !visited.insert(group) {
+            continue;
+        }
+        if let Some(v) = config.group_pings.get(group) {
+            users.extend(v.iter().cloned());
+        }
+        if let Some(v) = stored.iter().find(|g| g.name == group) {
+            insert_members(v, &mut users);
+        }
+        if let Some(v) = config.group_aliases.get(group) {
+            pending.extend(v.iter().map(|a| a.as_ref()));
+        }
+    }
+    users
+}
+
+/// Adds the valid members of a stored group
+pub fn insert_members(group: &GroupPing, users: &mut HashSet<OwnedUserId>) {
+    for member in &group.members {
+        match UserId::parse(member.as_str()) {
+            Ok(v) => {
+                users.insert(v);
+            }
+            Err(e) => error!(
+                "Invalid user {} stored in group {}. Error is {}",
+                member, group.name, e
+            ),
+        }
+    }
+}
@@ -1,15 +1,59 @@
 //! Exports various helper functions and types
+//!
+//! Relevant tests are in a test submodule
+
+#[cfg(test)]
+mod tests;
 
 pub mod bot_response;
 mod check_format;
 mod clean_text;
 mod convert_unit;
+mod escape_html;
+mod group_pings;
+mod moderation_records;
+mod muted_users;
+mod permissions;
+mod policy_rules;
+mod power_levels;
+mod response_records;
+mod scheduled_expiries;
+mod search_cache;
+mod search_result;
+mod text_expansions;
 
 // Public re-exports
 pub use bot_response::{MatrixFormattedTextResponse, MatrixNoticeResponse};
 pub use check_format::check_format;
 pub use clean_text::clean_text;
 pub use convert_unit::convert_unit;
+pub use escape_html::escape_html;
+pub use group_pings::{
+    group_members, insert_members, remove_group_ping, save_group_ping, stored_group_ping,
+    stored_group_pings,
+};
+pub use moderation_records::{save_moderation_record, stored_moderation_records};
+pub use muted_users::{save_muted_user, take_muted_user};
+pub use permissions::{is_moderator, is_permitted, listed_moderators};
+pub use policy_rules::{
+    is_glob, matching_policy_rule, policy_rule_matches, remove_policy_rule, save_policy_rule,
+};
+pub use power_levels::{has_elevated_power_level, save_power_levels, stored_power_level};
+pub use response_records::{
+    remove_expired_response_records, remove_response_record, save_response_record,
+    stored_response_record,
+};
+pub use scheduled_expiries::{
+    due_scheduled_expiries, remove_scheduled_expiries, remove_scheduled_expiry,
+    save_scheduled_expiry,
+};
+pub use search_cache::{cache_search_result, cached_search_result, remove_cached_search_result};
+pub use search_result::{
+    RefResult, RefResultKind, SearchResult, SearchResultKind, SearchResultState,
+};
+pub use text_expansions::{
+    remove_text_expansion, save_text_expansion, stored_text_expansion, stored_text_expansions,
+};
 
 // Private re-exports
 use convert_unit::ConvertedUnit;
@@ -0,0 +1,45 @@
+//! Helper functions for reading and writing the moderation log
+
+use crate::database::models::ModerationRecord;
+use anyhow::Context;
+use native_db::Database;
+use tracing::error;
+
+/// Returns the latest stored moderation records against the target, newest first
+pub fn stored_moderation_records(
+    storage: &Database,
+    target: &str,
+    limit: usize,
+) -> Vec<ModerationRecord> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return Vec::new();
+        }
+    };
+    let mut records = match r.scan().primary::<ModerationRecord>() {
+        Ok(v) => v
+            .all()
+            .filter(|m: &ModerationRecord| m.target == target)
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            error!("Unable to scan moderation records in db. Error is {}", e);
+            Vec::new()
+        }
+    };
+    records.sort_by(|a, b| b.id.cmp(&a.id));
+    records.truncate(limit);
+    records
+}
+
+/// Saves a moderation record
+pub fn save_moderation_record(storage: &Database, record: ModerationRecord) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    rw.insert(record)
+        .context("Unable to insert moderation record")?;
+    rw.commit()
+        .context("Unable to commit moderation record to db")
+}
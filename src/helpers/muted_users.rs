@@ -0,0 +1,44 @@
+//! Helper functions for reading and writing the power levels users had before they were muted
+
+use crate::database::models::MutedUser;
+use anyhow::Context;
+use native_db::Database;
+use ruma::{RoomId, UserId};
+
+/// Saves a mute unless the user is already muted in the room, so the level from before the first
+/// mute is kept
+pub fn save_muted_user(storage: &Database, muted_user: MutedUser) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let existing = rw
+        .get()
+        .primary::<MutedUser>(muted_user.id.clone())
+        .context("Unable to fetch muted user from db")?;
+    if existing.is_none() {
+        rw.insert(muted_user)
+            .context("Unable to insert muted user")?;
+    }
+    rw.commit().context("Unable to commit muted user to db")
+}
+
+/// Removes and returns the mute of the user in the room
+pub fn take_muted_user(
+    storage: &Database,
+    room_id: &RoomId,
+    user: &UserId,
+) -> anyhow::Result<Option<MutedUser>> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let muted_user = rw
+        .get()
+        .primary::<MutedUser>(MutedUser::id(room_id.as_str(), user.as_str()))
+        .context("Unable to fetch muted user from db")?;
+    if let Some(v) = muted_user.clone() {
+        rw.remove(v).context("Unable to remove muted user")?;
+    }
+    rw.commit()
+        .context("Unable to commit muted user removal to db")?;
+    Ok(muted_user)
+}
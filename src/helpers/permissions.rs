@@ -0,0 +1,83 @@
+//! Checks whether users are allowed to perform actions
+
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::helpers::{has_elevated_power_level, stored_power_level};
+use native_db::Database;
+use ruma::{OwnedUserId, RoomId, UserId};
+use std::collections::HashSet;
+
+/// Capabilities that make a user a moderator
+const MODERATION_CAPABILITIES: [Capability; 4] = [
+    Capability::Ban,
+    Capability::Kick,
+    Capability::Mute,
+    Capability::Redact,
+];
+
+/// Returns true if any role of the user grants the capability
+///
+/// Users have a role if they are listed in it, or if their power level meets the power level of the role in every ban room.
+/// Without ban rooms, the power level in the room is used instead, as commands only apply to it.
+/// Power levels never grant the invite capability, since inviters are admins of rooms they create
+///
+/// Members of groups from the config file can always make group pings. Groups from chat can be joined by anyone,
+/// so their members only can if a role allows them to
+pub fn is_permitted(
+    sender: &UserId,
+    room_id: &RoomId,
+    capability: Capability,
+    config: &MatrixListenerConfig,
+    storage: &Database<'_>,
+) -> bool {
+    let mut roles = config
+        .roles
+        .values()
+        .filter(|r| r.capabilities.contains(&capability));
+    let power_level = if capability == Capability::Invite {
+        None
+    } else if config.ban_rooms.is_empty() {
+        stored_power_level(storage, room_id, sender)
+    } else {
+        // The lowest power level across the ban rooms, unknown if any of them is unknown
+        config
+            .ban_rooms
+            .iter()
+            .map(|r| stored_power_level(storage, r, sender))
+            .min()
+            .flatten()
+    };
+    if roles.any(|r| {
+        r.users.contains(sender)
+            || matches!((r.power_level, power_level), (Some(required), Some(v)) if v >= required)
+    }) {
+        return true;
+    }
+    capability == Capability::GroupPing && config.group_ping_users.contains(sender)
+}
+
+/// Returns true if the user has a role with a moderation capability or a power level above the default in the room
+pub fn is_moderator(
+    user: &UserId,
+    room_id: &RoomId,
+    config: &MatrixListenerConfig,
+    storage: &Database<'_>,
+) -> bool {
+    MODERATION_CAPABILITIES
+        .iter()
+        .any(|c| is_permitted(user, room_id, *c, config, storage))
+        || has_elevated_power_level(storage, room_id, user)
+}
+
+/// Returns the users listed in a role with a moderation capability
+pub fn listed_moderators(config: &MatrixListenerConfig) -> HashSet<OwnedUserId> {
+    config
+        .roles
+        .values()
+        .filter(|r| {
+            MODERATION_CAPABILITIES
+                .iter()
+                .any(|c| r.capabilities.contains(c))
+        })
+        .flat_map(|r| r.users.iter().cloned())
+        .collect()
+}
@@ -0,0 +1,102 @@
+//! Helper functions for reading, writing, and matching ban rules read from policy rooms
+
+use crate::database::insert_or_update;
+use crate::database::models::{PolicyRule, PolicyRuleKind};
+use anyhow::Context;
+use native_db::Database;
+use regex::RegexBuilder;
+use ruma::{OwnedRoomId, UserId};
+use std::collections::HashSet;
+use tracing::error;
+
+/// Returns true if the entity of a rule is a glob rather than a single user or server
+pub fn is_glob(entity: &str) -> bool {
+    entity.contains(['*', '?'])
+}
+
+/// Matches text against a glob where `*` matches any run of characters and `?` matches any single character
+pub fn glob_matches(glob: &str, text: &str, case_insensitive: bool) -> bool {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    match RegexBuilder::new(&format!("^{}$", pattern))
+        .case_insensitive(case_insensitive)
+        .build()
+    {
+        Ok(v) => v.is_match(text),
+        Err(e) => {
+            error!("Unable to build regex for glob {}. Error is {}", glob, e);
+            false
+        }
+    }
+}
+
+/// Returns true if the rule applies to the user, matching server rules against the server of the user
+pub fn policy_rule_matches(rule: &PolicyRule, user: &UserId) -> bool {
+    match rule.kind {
+        PolicyRuleKind::User => glob_matches(&rule.entity, user.as_str(), false),
+        // Server names are case insensitive
+        PolicyRuleKind::Server => glob_matches(&rule.entity, user.server_name().as_str(), true),
+    }
+}
+
+/// Returns the first stored rule from one of the policy rooms that applies to the user
+///
+/// Rules from rooms that are no longer policy rooms stay stored, but are not applied
+pub fn matching_policy_rule(
+    storage: &Database,
+    policy_rooms: &HashSet<OwnedRoomId>,
+    user: &UserId,
+) -> Option<PolicyRule> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    let rule = match r.scan().primary::<PolicyRule>() {
+        Ok(v) => v.all().find(|rule: &PolicyRule| {
+            policy_rooms
+                .iter()
+                .any(|r| r.as_str() == rule.policy_room())
+                && policy_rule_matches(rule, user)
+        }),
+        Err(e) => {
+            error!("Unable to scan policy rules in db. Error is {}", e);
+            None
+        }
+    };
+    rule
+}
+
+/// Saves a rule, replacing the rule previously set by the same state event
+///
+/// Returns false if the rule was already stored unchanged
+pub fn save_policy_rule(storage: &Database, rule: PolicyRule) -> anyhow::Result<bool> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let old = match rw.get().primary::<PolicyRule>(rule.id.clone()) {
+        Ok(Some(v)) if v == rule => return Ok(false),
+        Ok(Some(v)) => v,
+        _ => rule.clone(),
+    };
+    insert_or_update(&rw, old, rule)?;
+    rw.commit().context("Unable to commit policy rule to db")?;
+    Ok(true)
+}
+
+/// Removes the rule with the supplied id
+pub fn remove_policy_rule(storage: &Database, id: &str) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let rule = rw
+        .get()
+        .primary::<PolicyRule>(id.to_string())
+        .context("Unable to fetch policy rule from db")?;
+    if let Some(v) = rule {
+        rw.remove(v).context("Unable to remove policy rule")?;
+    }
+    rw.commit()
+        .context("Unable to commit policy rule removal to db")
+}
@@ -0,0 +1,79 @@
+//! Helper functions for reading and writing room power levels seen in sync
+
+use crate::database::insert_or_update;
+use crate::database::models::RoomPowerLevels;
+use anyhow::Context;
+use native_db::Database;
+use ruma::events::room::power_levels::RoomPowerLevelsEventContent;
+use ruma::{RoomId, UserId};
+use tracing::error;
+
+/// Returns the power level of the user in the room if the power levels of the room are known
+pub fn stored_power_level(storage: &Database, room_id: &RoomId, user: &UserId) -> Option<i64> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    match r.get().primary::<RoomPowerLevels>(room_id.to_string()) {
+        Ok(v) => v.map(|p| *p.users.get(user.as_str()).unwrap_or(&p.users_default)),
+        Err(e) => {
+            error!("Unable to fetch room power levels from db. Error is {}", e);
+            None
+        }
+    }
+}
+
+/// Returns true if the power level of the user in the room is above the room default
+pub fn has_elevated_power_level(storage: &Database, room_id: &RoomId, user: &UserId) -> bool {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return false;
+        }
+    };
+    match r.get().primary::<RoomPowerLevels>(room_id.to_string()) {
+        Ok(v) => v.is_some_and(|p| {
+            p.users
+                .get(user.as_str())
+                .is_some_and(|l| *l > p.users_default)
+        }),
+        Err(e) => {
+            error!("Unable to fetch room power levels from db. Error is {}", e);
+            false
+        }
+    }
+}
+
+/// Saves the power levels of a room, replacing any previously seen power levels
+pub fn save_power_levels(
+    storage: &Database,
+    room_id: &RoomId,
+    content: &RoomPowerLevelsEventContent,
+) -> anyhow::Result<()> {
+    let power_levels = RoomPowerLevels {
+        room_id: room_id.to_string(),
+        users: content
+            .users
+            .iter()
+            .map(|(k, v)| (k.to_string(), i64::from(*v)))
+            .collect(),
+        users_default: i64::from(content.users_default),
+    };
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let old = match rw
+        .get()
+        .primary::<RoomPowerLevels>(power_levels.room_id.clone())
+    {
+        Ok(Some(v)) => v,
+        _ => power_levels.clone(),
+    };
+    insert_or_update(&rw, old, power_levels)?;
+    rw.commit()
+        .context("Unable to commit room power levels to db")
+}
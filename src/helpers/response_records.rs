@@ -0,0 +1,101 @@
+//! Helper functions for reading and writing the responses sent to messages, so they can be updated when a message is edited
+
+use crate::database::insert_or_update;
+use crate::database::models::ResponseRecord;
+use anyhow::Context;
+use native_db::Database;
+use ruma::EventId;
+use std::time::{Duration, SystemTime};
+use tracing::error;
+
+/// How long responses are kept track of after they are sent
+const RESPONSE_RECORD_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Returns the unix time before which records are expired
+fn expiry_cutoff() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .saturating_sub(RESPONSE_RECORD_TTL)
+        .as_secs()
+}
+
+/// Returns the record of the responses to the message, if there were any
+///
+/// Records older than a day are ignored even before they are removed, as responses to messages edited after that
+/// are not updated
+pub fn stored_response_record(storage: &Database, trigger: &EventId) -> Option<ResponseRecord> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    let record = match r.get().primary::<ResponseRecord>(trigger.to_string()) {
+        Ok(v) => v.filter(|record: &ResponseRecord| record.sent_at >= expiry_cutoff()),
+        Err(e) => {
+            error!("Unable to fetch response record from db. Error is {}", e);
+            None
+        }
+    };
+    record
+}
+
+/// Saves the record of the responses to a message, replacing any previous record for it
+pub fn save_response_record(storage: &Database, record: ResponseRecord) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let old = match rw
+        .get()
+        .primary::<ResponseRecord>(record.trigger_event_id.clone())
+    {
+        Ok(Some(v)) => v,
+        _ => record.clone(),
+    };
+    insert_or_update(&rw, old, record)?;
+    rw.commit()
+        .context("Unable to commit response record to db")
+}
+
+/// Removes the record of the responses to the message
+pub fn remove_response_record(storage: &Database, trigger: &EventId) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let record = rw
+        .get()
+        .primary::<ResponseRecord>(trigger.to_string())
+        .context("Unable to fetch response record from db")?;
+    if let Some(v) = record {
+        rw.remove(v).context("Unable to remove response record")?;
+    }
+    rw.commit()
+        .context("Unable to commit response record removal to db")
+}
+
+/// Removes every record older than a day, returning how many were removed
+///
+/// Run periodically by the scheduler so saving a record does not need to look through the others
+pub fn remove_expired_response_records(storage: &Database) -> anyhow::Result<usize> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let cutoff = expiry_cutoff();
+    let expired = rw
+        .scan()
+        .primary::<ResponseRecord>()
+        .context("Unable to scan response records in db")?
+        .all()
+        .filter(|r: &ResponseRecord| r.sent_at < cutoff)
+        .collect::<Vec<_>>();
+    let count = expired.len();
+    for old in expired {
+        rw.remove(old)
+            .context("Unable to remove expired response record")?;
+    }
+    rw.commit()
+        .context("Unable to commit response record removal to db")?;
+    Ok(count)
+}
@@ -0,0 +1,79 @@
+//! Helper functions for reading and writing timed bans and mutes waiting to be lifted
+
+use crate::database::models::{ModerationAction, ScheduledExpiry};
+use anyhow::Context;
+use native_db::Database;
+use tracing::error;
+
+/// Returns all stored expiries that are due at the supplied unix time
+pub fn due_scheduled_expiries(storage: &Database, now: u64) -> Vec<ScheduledExpiry> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return Vec::new();
+        }
+    };
+    let expiries = match r.scan().primary::<ScheduledExpiry>() {
+        Ok(v) => v
+            .all()
+            .filter(|e: &ScheduledExpiry| e.expires_at <= now)
+            .collect(),
+        Err(e) => {
+            error!("Unable to scan scheduled expiries in db. Error is {}", e);
+            Vec::new()
+        }
+    };
+    expiries
+}
+
+/// Saves an expiry to be run by the scheduler
+pub fn save_scheduled_expiry(storage: &Database, expiry: ScheduledExpiry) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    rw.insert(expiry)
+        .context("Unable to insert scheduled expiry")?;
+    rw.commit()
+        .context("Unable to commit scheduled expiry to db")
+}
+
+/// Removes the expiry with the supplied id
+pub fn remove_scheduled_expiry(storage: &Database, id: &str) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let expiry = rw
+        .get()
+        .primary::<ScheduledExpiry>(id.to_string())
+        .context("Unable to fetch scheduled expiry from db")?;
+    if let Some(v) = expiry {
+        rw.remove(v).context("Unable to remove scheduled expiry")?;
+    }
+    rw.commit()
+        .context("Unable to commit scheduled expiry removal to db")
+}
+
+/// Removes every pending expiry of the action for the user, so a later ban or mute is not lifted early
+pub fn remove_scheduled_expiries(
+    storage: &Database,
+    action: ModerationAction,
+    user: &str,
+) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let expiries = rw
+        .scan()
+        .primary::<ScheduledExpiry>()
+        .context("Unable to scan scheduled expiries in db")?
+        .all()
+        .filter(|e: &ScheduledExpiry| e.action == action && e.user == user)
+        .collect::<Vec<_>>();
+    for expiry in expiries {
+        rw.remove(expiry)
+            .context("Unable to remove scheduled expiry")?;
+    }
+    rw.commit()
+        .context("Unable to commit scheduled expiry removal to db")
+}
@@ -0,0 +1,146 @@
+//! Helper functions for reading and writing cached issue and pull request search results
+
+use super::SearchResult;
+use crate::database::insert_or_update;
+use crate::database::models::GithubSearchCache;
+use native_db::Database;
+use reqwest::Url;
+use std::time::{Duration, SystemTime};
+use tracing::{error, trace};
+
+/// Returns the cached result for an issue or pull if it was fetched within the supplied TTL
+pub fn cached_search_result(
+    storage: &Database,
+    repo: &str,
+    number: i64,
+    ttl: Duration,
+) -> Option<SearchResult> {
+    if ttl.is_zero() {
+        return None;
+    }
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    let cached = match r
+        .get()
+        .primary::<GithubSearchCache>(GithubSearchCache::key(repo, number))
+    {
+        Ok(v) => v?,
+        Err(e) => {
+            error!(
+                "Unable to fetch github search cache from db. Error is {}",
+                e
+            );
+            return None;
+        }
+    };
+    if now().saturating_sub(cached.fetched_at) >= ttl.as_secs() {
+        trace!("Cached result for {}#{} has expired", repo, number);
+        return None;
+    }
+    let url = match Url::parse(&cached.url) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Unable to parse cached url {:?} for {}#{}. Error is {}",
+                cached.url, repo, number, e
+            );
+            return None;
+        }
+    };
+    Some(SearchResult {
+        repo: cached.repo,
+        number: cached.number,
+        kind: cached.kind,
+        title: cached.title,
+        state: cached.state,
+        author: cached.author,
+        labels: cached.labels,
+        url,
+    })
+}
+
+/// Saves a result in the cache, replacing any previous entry for the same issue or pull
+pub fn cache_search_result(storage: &Database, result: &SearchResult) {
+    let new = GithubSearchCache {
+        key: GithubSearchCache::key(&result.repo, result.number),
+        repo: result.repo.clone(),
+        number: result.number,
+        kind: result.kind,
+        title: result.title.clone(),
+        state: result.state,
+        author: result.author.clone(),
+        labels: result.labels.clone(),
+        url: result.url.to_string(),
+        fetched_at: now(),
+    };
+    trace!("Caching github search result {:?}", new);
+    let rw = match storage.rw_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Unable to get read write transaction from db. Error is {}",
+                e
+            );
+            return;
+        }
+    };
+    let old = match rw.get().primary::<GithubSearchCache>(new.key.clone()) {
+        Ok(Some(v)) => v,
+        _ => new.clone(),
+    };
+    if let Err(e) = insert_or_update(&rw, old, new) {
+        error!("Unable to write github search cache. Error is {}", e);
+        return;
+    }
+    if let Err(e) = rw.commit() {
+        error!("Unable to commit github search cache. Error is {}", e);
+    }
+}
+
+/// Removes the cached result for an issue or pull if one exists
+pub fn remove_cached_search_result(storage: &Database, repo: &str, number: i64) {
+    let rw = match storage.rw_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Unable to get read write transaction from db. Error is {}",
+                e
+            );
+            return;
+        }
+    };
+    match rw
+        .get()
+        .primary::<GithubSearchCache>(GithubSearchCache::key(repo, number))
+    {
+        Ok(Some(v)) => {
+            if let Err(e) = rw.remove(v) {
+                error!("Unable to remove github search cache. Error is {}", e);
+                return;
+            }
+        }
+        Ok(None) => return,
+        Err(e) => {
+            error!(
+                "Unable to fetch github search cache from db. Error is {}",
+                e
+            );
+            return;
+        }
+    }
+    if let Err(e) = rw.commit() {
+        error!("Unable to commit github search cache. Error is {}", e);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
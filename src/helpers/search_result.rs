@@ -0,0 +1,203 @@
+//! Helper types used to represent a found issue, pull request, commit, or release for response building
+
+use super::escape_html;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Kind of item a search result represents
+pub enum SearchResultKind {
+    /// An issue
+    Issue,
+    /// A pull or merge request
+    PullRequest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// State of the item a search result represents
+pub enum SearchResultState {
+    /// Issue or pull request is open
+    Open,
+    /// Issue or pull request was closed without merging
+    Closed,
+    /// Pull request was merged
+    Merged,
+}
+
+#[derive(Clone, Debug)]
+/// Type representing a found issue or pull request with all data used in replies
+pub struct SearchResult {
+    /// Repo the result was found in, in owner/repo form
+    pub repo: String,
+    /// Issue or pull request number
+    pub number: i64,
+    /// Kind of the result
+    pub kind: SearchResultKind,
+    /// Title of the issue or pull request
+    pub title: String,
+    /// State of the issue or pull request
+    pub state: SearchResultState,
+    /// Login of the author if they still exist
+    pub author: Option<String>,
+    /// Names of the labels applied to the issue or pull request
+    pub labels: Vec<String>,
+    /// Link to the issue or pull request
+    pub url: Url,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Kind of git ref a ref result represents
+pub enum RefResultKind {
+    /// A commit
+    Commit,
+    /// A release
+    Release,
+    /// A release marked as a prerelease
+    Prerelease,
+}
+
+#[derive(Clone, Debug)]
+/// Type representing a found commit or release with all data used in replies
+pub struct RefResult {
+    /// Repo the result was found in, in owner/repo form
+    pub repo: String,
+    /// Kind of the result
+    pub kind: RefResultKind,
+    /// Abbreviated sha of a commit or tag name of a release
+    pub name: String,
+    /// First line of the commit message or name of the release
+    pub title: Option<String>,
+    /// Name or login of the author if known
+    pub author: Option<String>,
+    /// Date the commit was made or the release was published in YYYY-MM-DD form
+    pub date: Option<String>,
+    /// Link to the commit or release
+    pub url: Url,
+}
+
+impl SearchResultState {
+    /// Color used for the state in formatted responses
+    fn color(&self) -> &'static str {
+        match self {
+            SearchResultState::Open => "#1a7f37",
+            SearchResultState::Closed => "#cf222e",
+            SearchResultState::Merged => "#8250df",
+        }
+    }
+}
+
+impl SearchResult {
+    /// Formats the result as HTML for formatted responses
+    pub fn format_text(&self) -> String {
+        let mut formatted_text = format!(
+            "<a href=\"{}\">{}#{}</a> ({}): <b>{}</b> <font color=\"{}\">[{}]</font>",
+            escape_html(self.url.as_ref()),
+            escape_html(&self.repo),
+            self.number,
+            self.kind,
+            escape_html(&self.title),
+            self.state.color(),
+            self.state
+        );
+        if let Some(author) = &self.author {
+            formatted_text.push_str(" by ");
+            formatted_text.push_str(&escape_html(author));
+        }
+        for label in &self.labels {
+            formatted_text.push_str(" <code>");
+            formatted_text.push_str(&escape_html(label));
+            formatted_text.push_str("</code>");
+        }
+        formatted_text
+    }
+}
+
+impl RefResult {
+    /// Formats the result as HTML for formatted responses
+    pub fn format_text(&self) -> String {
+        let mut formatted_text = format!(
+            "<a href=\"{}\">{}@{}</a> ({})",
+            escape_html(self.url.as_ref()),
+            escape_html(&self.repo),
+            escape_html(&self.name),
+            self.kind
+        );
+        if let Some(title) = &self.title {
+            formatted_text.push_str(": <b>");
+            formatted_text.push_str(&escape_html(title));
+            formatted_text.push_str("</b>");
+        }
+        if let Some(author) = &self.author {
+            formatted_text.push_str(" by ");
+            formatted_text.push_str(&escape_html(author));
+        }
+        if let Some(date) = &self.date {
+            formatted_text.push_str(" on ");
+            formatted_text.push_str(&escape_html(date));
+        }
+        formatted_text
+    }
+}
+
+impl fmt::Display for SearchResultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchResultKind::Issue => write!(f, "issue"),
+            SearchResultKind::PullRequest => write!(f, "pull request"),
+        }
+    }
+}
+
+impl fmt::Display for SearchResultState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchResultState::Open => write!(f, "open"),
+            SearchResultState::Closed => write!(f, "closed"),
+            SearchResultState::Merged => write!(f, "merged"),
+        }
+    }
+}
+
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}#{} ({}): {} [{}]",
+            self.repo, self.number, self.kind, self.title, self.state
+        )?;
+        if let Some(author) = &self.author {
+            write!(f, " by {}", author)?;
+        }
+        if !self.labels.is_empty() {
+            write!(f, " ({})", self.labels.join(", "))?;
+        }
+        write!(f, " {}", self.url)
+    }
+}
+
+impl fmt::Display for RefResultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefResultKind::Commit => write!(f, "commit"),
+            RefResultKind::Release => write!(f, "release"),
+            RefResultKind::Prerelease => write!(f, "prerelease"),
+        }
+    }
+}
+
+impl fmt::Display for RefResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{} ({})", self.repo, self.name, self.kind)?;
+        if let Some(title) = &self.title {
+            write!(f, ": {}", title)?;
+        }
+        if let Some(author) = &self.author {
+            write!(f, " by {}", author)?;
+        }
+        if let Some(date) = &self.date {
+            write!(f, " on {}", date)?;
+        }
+        write!(f, " {}", self.url)
+    }
+}
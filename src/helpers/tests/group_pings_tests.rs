@@ -0,0 +1,63 @@
+use super::super::group_members;
+use crate::database::models::GroupPing;
+use crate::test_helpers::listener_config;
+use ruma::{OwnedUserId, UserId};
+use std::collections::HashSet;
+
+const GROUPS: &str = "[group_pings]
+backend = ['@user1:matrix.org']
+server = ['%backend']
+web = ['@user3:matrix.org']
+api = ['%server', '%web', '@user5:matrix.org']";
+
+fn users(users: &[&str]) -> HashSet<OwnedUserId> {
+    users.iter().map(|u| UserId::parse(*u).unwrap()).collect()
+}
+
+#[test]
+fn members_joined_from_chat_pinged_through_aliases() {
+    let config = listener_config("", GROUPS);
+    let stored = vec![GroupPing {
+        name: "backend".to_string(),
+        members: vec!["@joined:matrix.org".to_string()],
+    }];
+
+    assert_eq!(
+        users(&[
+            "@user1:matrix.org",
+            "@joined:matrix.org",
+            "@user3:matrix.org",
+            "@user5:matrix.org"
+        ]),
+        group_members("api", &config, &stored)
+    );
+    assert_eq!(
+        users(&["@user1:matrix.org", "@joined:matrix.org"]),
+        group_members("server", &config, &stored)
+    );
+}
+
+#[test]
+fn alias_cycles_end() {
+    let mut config = listener_config("", GROUPS);
+    config
+        .group_aliases
+        .get_mut("backend")
+        .unwrap()
+        .insert("api".into());
+
+    assert_eq!(
+        users(&[
+            "@user1:matrix.org",
+            "@user3:matrix.org",
+            "@user5:matrix.org"
+        ]),
+        group_members("backend", &config, &[])
+    );
+}
+
+#[test]
+fn unknown_group_has_no_members() {
+    let config = listener_config("", GROUPS);
+    assert!(group_members("frontend", &config, &[]).is_empty());
+}
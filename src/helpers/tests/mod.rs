@@ -0,0 +1,5 @@
+mod group_pings_tests;
+mod muted_users_tests;
+mod permissions_tests;
+mod policy_rules_tests;
+mod response_records_tests;
@@ -0,0 +1,58 @@
+use super::super::{save_muted_user, take_muted_user};
+use crate::database::models::MutedUser;
+use crate::test_helpers::database;
+use ruma::{room_id, user_id};
+
+#[test]
+fn previous_level_restored() {
+    let storage = database();
+    let room_id = room_id!("!room:matrix.org");
+    let user = user_id!("@moderator:matrix.org");
+    save_muted_user(
+        &storage,
+        MutedUser::new(room_id.as_str(), user.as_str(), Some(50)),
+    )
+    .unwrap();
+    let muted_user = take_muted_user(&storage, room_id, user).unwrap().unwrap();
+    assert_eq!(muted_user.previous_level, Some(50));
+    assert!(take_muted_user(&storage, room_id, user).unwrap().is_none());
+}
+
+#[test]
+fn muting_again_keeps_first_level() {
+    let storage = database();
+    let room_id = room_id!("!room:matrix.org");
+    let user = user_id!("@moderator:matrix.org");
+    save_muted_user(
+        &storage,
+        MutedUser::new(room_id.as_str(), user.as_str(), Some(50)),
+    )
+    .unwrap();
+    save_muted_user(
+        &storage,
+        MutedUser::new(room_id.as_str(), user.as_str(), Some(-1)),
+    )
+    .unwrap();
+    let muted_user = take_muted_user(&storage, room_id, user).unwrap().unwrap();
+    assert_eq!(muted_user.previous_level, Some(50));
+}
+
+#[test]
+fn muted_per_room() {
+    let storage = database();
+    let user = user_id!("@user:matrix.org");
+    save_muted_user(
+        &storage,
+        MutedUser::new("!room:matrix.org", user.as_str(), None),
+    )
+    .unwrap();
+    assert!(
+        take_muted_user(&storage, room_id!("!other:matrix.org"), user)
+            .unwrap()
+            .is_none()
+    );
+    let muted_user = take_muted_user(&storage, room_id!("!room:matrix.org"), user)
+        .unwrap()
+        .unwrap();
+    assert_eq!(muted_user.previous_level, None);
+}
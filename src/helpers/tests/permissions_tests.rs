@@ -0,0 +1,138 @@
+use super::super::{is_permitted, save_group_ping};
+use crate::config::Capability;
+use crate::database::models::{GroupPing, RoomPowerLevels};
+use crate::test_helpers::{database, listener_config};
+use native_db::Database;
+use ruma::{room_id, user_id, RoomId, UserId};
+use std::collections::HashMap;
+
+fn save_power_level(storage: &Database, room_id: &RoomId, user: &UserId, level: i64) {
+    let rw = storage.rw_transaction().unwrap();
+    rw.insert(RoomPowerLevels {
+        room_id: room_id.to_string(),
+        users: HashMap::from([(user.to_string(), level)]),
+        users_default: 0,
+    })
+    .unwrap();
+    rw.commit().unwrap();
+}
+
+const MODERATOR: &str = "[roles.moderator]
+capabilities = ['invite', 'ban']
+power_level = 50";
+
+#[test]
+fn power_levels_only_count_in_ban_rooms() {
+    let config = listener_config(
+        "ban_rooms = ['!ban1:matrix.org', '!ban2:matrix.org']",
+        MODERATOR,
+    );
+    let storage = database();
+    let user = user_id!("@user:matrix.org");
+    let own_room = room_id!("!own:matrix.org");
+    let ban1 = room_id!("!ban1:matrix.org");
+    let ban2 = room_id!("!ban2:matrix.org");
+
+    // Admin of a room outside the ban rooms
+    save_power_level(&storage, own_room, user, 100);
+    assert!(!is_permitted(
+        user,
+        own_room,
+        Capability::Ban,
+        &config,
+        &storage
+    ));
+
+    // Moderator of only one of the ban rooms
+    save_power_level(&storage, ban1, user, 50);
+    assert!(!is_permitted(
+        user,
+        ban1,
+        Capability::Ban,
+        &config,
+        &storage
+    ));
+
+    save_power_level(&storage, ban2, user, 50);
+    assert!(is_permitted(user, ban1, Capability::Ban, &config, &storage));
+    assert!(is_permitted(
+        user,
+        own_room,
+        Capability::Ban,
+        &config,
+        &storage
+    ));
+}
+
+#[test]
+fn power_levels_in_room_without_ban_rooms() {
+    let config = listener_config("", MODERATOR);
+    let storage = database();
+    let user = user_id!("@user:matrix.org");
+    let room = room_id!("!room:matrix.org");
+
+    save_power_level(&storage, room, user, 50);
+    assert!(is_permitted(user, room, Capability::Ban, &config, &storage));
+    assert!(!is_permitted(
+        user,
+        room_id!("!other:matrix.org"),
+        Capability::Ban,
+        &config,
+        &storage
+    ));
+}
+
+#[test]
+fn power_levels_never_allow_invites() {
+    let config = listener_config("", MODERATOR);
+    let storage = database();
+    let user = user_id!("@user:matrix.org");
+    let room = room_id!("!room:matrix.org");
+
+    save_power_level(&storage, room, user, 100);
+    assert!(!is_permitted(
+        user,
+        room,
+        Capability::Invite,
+        &config,
+        &storage
+    ));
+    assert!(is_permitted(
+        user_id!("@admin:matrix.org"),
+        room,
+        Capability::Invite,
+        &config,
+        &storage
+    ));
+}
+
+#[test]
+fn joining_a_group_does_not_allow_pings() {
+    let config = listener_config("", "[group_pings]\nbackend = ['@configured:matrix.org']");
+    let storage = database();
+    let room = room_id!("!room:matrix.org");
+    let joined = user_id!("@joined:matrix.org");
+    save_group_ping(
+        &storage,
+        GroupPing {
+            name: "frontend".to_string(),
+            members: vec![joined.to_string()],
+        },
+    )
+    .unwrap();
+
+    assert!(!is_permitted(
+        joined,
+        room,
+        Capability::GroupPing,
+        &config,
+        &storage
+    ));
+    assert!(is_permitted(
+        user_id!("@configured:matrix.org"),
+        room,
+        Capability::GroupPing,
+        &config,
+        &storage
+    ));
+}
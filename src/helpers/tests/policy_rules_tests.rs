@@ -0,0 +1,81 @@
+use super::super::policy_rules::glob_matches;
+use super::super::{is_glob, policy_rule_matches};
+use crate::database::models::{PolicyRule, PolicyRuleKind};
+use ruma::UserId;
+
+fn rule(kind: PolicyRuleKind, entity: &str) -> PolicyRule {
+    PolicyRule::new(
+        "!policy:example.com",
+        kind,
+        "rule",
+        entity.to_string(),
+        String::new(),
+    )
+}
+
+#[test]
+fn globs() {
+    assert!(glob_matches(
+        "@spam*:example.com",
+        "@spammer:example.com",
+        false
+    ));
+    assert!(glob_matches(
+        "@spam?:example.com",
+        "@spam1:example.com",
+        false
+    ));
+    assert!(glob_matches("*", "@anyone:example.com", false));
+    assert!(!glob_matches(
+        "@spam?:example.com",
+        "@spam12:example.com",
+        false
+    ));
+    assert!(!glob_matches("@spam*", "@notspam:example.com", false));
+    // Other regex characters are matched literally
+    assert!(!glob_matches("@a.c:example.com", "@abc:example.com", false));
+    assert!(glob_matches("@a.c:example.com", "@a.c:example.com", false));
+    assert!(glob_matches("EXAMPLE.com", "example.com", true));
+    assert!(!glob_matches("EXAMPLE.com", "example.com", false));
+}
+
+#[test]
+fn glob_detection() {
+    assert!(is_glob("@spam*:example.com"));
+    assert!(is_glob("*.example.com"));
+    assert!(!is_glob("@spammer:example.com"));
+}
+
+#[test]
+fn user_rules() {
+    let user = UserId::parse("@spammer:example.com").unwrap();
+    assert!(policy_rule_matches(
+        &rule(PolicyRuleKind::User, "@spammer:example.com"),
+        &user
+    ));
+    assert!(policy_rule_matches(
+        &rule(PolicyRuleKind::User, "@spam*:*"),
+        &user
+    ));
+    assert!(!policy_rule_matches(
+        &rule(PolicyRuleKind::User, "@other:example.com"),
+        &user
+    ));
+}
+
+#[test]
+fn server_rules() {
+    let user = UserId::parse("@spammer:spam.example.com").unwrap();
+    assert!(policy_rule_matches(
+        &rule(PolicyRuleKind::Server, "spam.example.com"),
+        &user
+    ));
+    assert!(policy_rule_matches(
+        &rule(PolicyRuleKind::Server, "*.EXAMPLE.com"),
+        &user
+    ));
+    assert!(!policy_rule_matches(
+        &rule(PolicyRuleKind::Server, "example.com"),
+        &user
+    ));
+}
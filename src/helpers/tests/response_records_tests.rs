@@ -0,0 +1,31 @@
+use super::super::{remove_expired_response_records, save_response_record, stored_response_record};
+use crate::database::models::{ResponseRecord, SentResponse};
+use crate::test_helpers::database;
+use ruma::event_id;
+
+fn record(trigger: &str) -> ResponseRecord {
+    ResponseRecord::new(
+        trigger.to_string(),
+        "@user:matrix.org".to_string(),
+        None,
+        vec![SentResponse {
+            event_id: "$response:matrix.org".to_string(),
+            content: "{}".to_string(),
+        }],
+    )
+}
+
+#[test]
+fn expired_records_removed() {
+    let storage = database();
+    let mut old = record("$old:matrix.org");
+    old.sent_at -= 60 * 60 * 25;
+    save_response_record(&storage, old).unwrap();
+    save_response_record(&storage, record("$new:matrix.org")).unwrap();
+
+    // Expired records are ignored until they are removed
+    assert!(stored_response_record(&storage, event_id!("$old:matrix.org")).is_none());
+    assert_eq!(1, remove_expired_response_records(&storage).unwrap());
+    assert_eq!(0, remove_expired_response_records(&storage).unwrap());
+    assert!(stored_response_record(&storage, event_id!("$new:matrix.org")).is_some());
+}
@@ -0,0 +1,77 @@
+//! Helper functions for reading and writing text expansions edited from chat
+
+use crate::database::insert_or_update;
+use crate::database::models::TextExpansion;
+use anyhow::Context;
+use native_db::Database;
+use tracing::error;
+
+/// Returns the stored text expansion for a keyword if one exists
+pub fn stored_text_expansion(storage: &Database, keyword: &str) -> Option<TextExpansion> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return None;
+        }
+    };
+    match r.get().primary::<TextExpansion>(keyword.to_string()) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to fetch text expansion from db. Error is {}", e);
+            None
+        }
+    }
+}
+
+/// Returns all stored text expansions sorted by keyword
+pub fn stored_text_expansions(storage: &Database) -> Vec<TextExpansion> {
+    let r = match storage.r_transaction() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Unable to get read transaction from db. Error is {}", e);
+            return Vec::new();
+        }
+    };
+    let expansions = match r.scan().primary::<TextExpansion>() {
+        Ok(v) => v.all().collect(),
+        Err(e) => {
+            error!("Unable to scan text expansions in db. Error is {}", e);
+            Vec::new()
+        }
+    };
+    expansions
+}
+
+/// Saves a text expansion, replacing any previous entry for the same keyword
+pub fn save_text_expansion(storage: &Database, expansion: TextExpansion) -> anyhow::Result<()> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let old = match rw.get().primary::<TextExpansion>(expansion.keyword.clone()) {
+        Ok(Some(v)) => v,
+        _ => expansion.clone(),
+    };
+    insert_or_update(&rw, old, expansion)?;
+    rw.commit().context("Unable to commit text expansion to db")
+}
+
+/// Removes the stored text expansion for a keyword, returning `false` if none exists
+pub fn remove_text_expansion(storage: &Database, keyword: &str) -> anyhow::Result<bool> {
+    let rw = storage
+        .rw_transaction()
+        .context("Unable to get read write transaction from db")?;
+    let expansion = rw
+        .get()
+        .primary::<TextExpansion>(keyword.to_string())
+        .context("Unable to fetch text expansion from db")?;
+    match expansion {
+        Some(v) => {
+            rw.remove(v).context("Unable to remove text expansion")?;
+            rw.commit()
+                .context("Unable to commit text expansion removal to db")?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
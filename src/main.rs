@@ -35,6 +35,8 @@
 //!
 //!     Uses GraphQL to be API cost effective (REST might require 2 hits depending on returned result)
 //!
+//!     Repos can also be searched on Gitea, Forgejo and GitLab instances
+//!
 //! - ### A configrable general purpose linker
 //!
 //!     This can be turned off by supplying no linkable urls
@@ -67,12 +69,15 @@ mod bot;
 mod config;
 mod database;
 mod events;
+mod forges;
 mod helpers;
 mod logging;
 mod messages;
 mod queries;
 mod regex;
 mod services;
+#[cfg(test)]
+mod test_helpers;
 
 #[tokio::main]
 #[allow(clippy::missing_docs_in_private_items)]
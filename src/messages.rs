@@ -1,18 +1,71 @@
-use ruma::{events::room::message::RoomMessageEventContent, OwnedRoomId, OwnedUserId};
+use crate::database::models::PolicyRule;
+use ruma::{
+    events::room::message::{Relation, RoomMessageEventContent},
+    EventId, OwnedEventId, OwnedRoomId, OwnedUserId,
+};
 use std::collections::HashSet;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct MatrixMessage {
     pub room_id: Option<OwnedRoomId>,
+    /// Event a response answers, sent as a reply to it
+    pub reply_to: Option<MatrixReply>,
     pub message: MatrixMessageType,
     // pub resp: Responder<MatrixMessageResult>,
 }
 
+#[derive(Debug, Clone)]
+pub struct MatrixReply {
+    pub event_id: OwnedEventId,
+    /// Root of the thread the event was sent in, so responses are sent in the same thread
+    pub thread_root: Option<OwnedEventId>,
+}
+
+impl MatrixReply {
+    pub fn new(event_id: &EventId, relates_to: Option<&Relation>) -> Self {
+        let thread_root = match relates_to {
+            Some(Relation::Thread(t)) => Some(t.event_id.clone()),
+            _ => None,
+        };
+        Self {
+            event_id: event_id.to_owned(),
+            thread_root,
+        }
+    }
+
+    /// Creates a reply that starts a thread from the event, unless it was already sent in one
+    ///
+    /// Events with any other relation, such as replies, can not be thread roots and are answered with a plain reply
+    pub fn threaded(event_id: &EventId, relates_to: Option<&Relation>) -> Self {
+        let mut reply_to = Self::new(event_id, relates_to);
+        // Messages without a relation are deserialized with a custom one, so only known relations are checked
+        let has_relation = matches!(
+            relates_to,
+            Some(Relation::Reply { .. } | Relation::Replacement(_) | Relation::Thread(_))
+        );
+        if !has_relation {
+            reply_to.thread_root = Some(event_id.to_owned());
+        }
+        reply_to
+    }
+}
+
 #[derive(Debug)]
 pub enum MatrixMessageType {
     Invite(MatrixInviteMessage),
     Response(RoomMessageEventContent),
+    Responses(MatrixResponsesMessage),
     Ban(MatrixBanMessage),
+    Unban(MatrixUnbanMessage),
+    Kick(MatrixKickMessage),
+    Mute(MatrixMuteMessage),
+    Unmute(MatrixUnmuteMessage),
+    Redact(MatrixRedactMessage),
+    Purge(MatrixPurgeMessage),
+    PolicyBan(MatrixPolicyBanMessage),
+    Warn(MatrixWarnMessage),
+    Welcome(MatrixWelcomeMessage),
 }
 
 #[derive(Debug)]
@@ -29,11 +82,100 @@ pub struct MatrixInviteMessage {
 
 #[derive(Debug)]
 pub struct MatrixBanMessage {
+    pub sender: OwnedUserId,
+    pub user: OwnedUserId,
+    pub duration: Option<Duration>,
+    pub reason: Option<String>,
+    pub rooms: HashSet<OwnedRoomId>,
+    /// Publishes the ban as a policy rule if a policy publish room is configured
+    pub publish: bool,
+}
+
+#[derive(Debug)]
+pub struct MatrixUnbanMessage {
+    pub sender: OwnedUserId,
+    pub user: OwnedUserId,
+    pub reason: Option<String>,
+    pub rooms: HashSet<OwnedRoomId>,
+    /// Removes the published policy rule banning the user
+    pub publish: bool,
+}
+
+#[derive(Debug)]
+pub struct MatrixKickMessage {
+    pub sender: OwnedUserId,
+    pub user: OwnedUserId,
+    pub reason: Option<String>,
+    pub rooms: HashSet<OwnedRoomId>,
+}
+
+#[derive(Debug)]
+pub struct MatrixMuteMessage {
+    pub sender: OwnedUserId,
+    pub user: OwnedUserId,
+    pub duration: Option<Duration>,
+    pub reason: Option<String>,
+    pub rooms: HashSet<OwnedRoomId>,
+}
+
+#[derive(Debug)]
+pub struct MatrixUnmuteMessage {
+    pub sender: OwnedUserId,
     pub user: OwnedUserId,
     pub reason: Option<String>,
     pub rooms: HashSet<OwnedRoomId>,
 }
 
+#[derive(Debug)]
+pub struct MatrixRedactMessage {
+    pub sender: OwnedUserId,
+    pub room_id: OwnedRoomId,
+    pub event_id: OwnedEventId,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct MatrixPurgeMessage {
+    pub sender: OwnedUserId,
+    pub user: OwnedUserId,
+    pub count: u32,
+    pub rooms: HashSet<OwnedRoomId>,
+}
+
+#[derive(Debug)]
+pub struct MatrixWarnMessage {
+    pub sender: OwnedUserId,
+    pub user: OwnedUserId,
+    pub room_id: OwnedRoomId,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct MatrixResponsesMessage {
+    /// Sender of the message being responded to, the only user whose edits update the responses
+    pub sender: OwnedUserId,
+    pub responses: Vec<RoomMessageEventContent>,
+    /// Whether the message was edited, so the responses replace those sent before the edit
+    pub edited: bool,
+}
+
+#[derive(Debug)]
+pub struct MatrixWelcomeMessage {
+    pub users: Vec<OwnedUserId>,
+    /// Welcome message with {user} and {room} still to be replaced
+    pub template: String,
+}
+
+#[derive(Debug)]
+pub struct MatrixPolicyBanMessage {
+    pub sender: OwnedUserId,
+    pub policy_room: OwnedRoomId,
+    pub rule: PolicyRule,
+    pub rooms: HashSet<OwnedRoomId>,
+    /// Moderators listed in the config, who are never banned by the rule
+    pub exempt: HashSet<OwnedUserId>,
+}
+
 // #[derive(Debug)]
 // pub enum MatrixMessageResult {
 //     Sent,
@@ -15,6 +15,9 @@ use graphql_client::*;
 #[allow(clippy::upper_case_acronyms)]
 type URI = String;
 
+/// Type that represents ISO-8601 encoded UTC date strings from query
+type DateTime = String;
+
 #[derive(GraphQLQuery)]
 #[graphql(
     schema_path = "src/queries/github_schema.graphql",
@@ -24,4 +27,285 @@ type URI = String;
 /// Query struct derived from file github_issueorpull.graphql
 ///
 /// Reference that file for further details on structure composition
+///
+/// Searches are sent with `IssueOrPullBatch`, which reuses the types generated here
+#[allow(dead_code)]
 pub struct IssueOrPull;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issueorpull.graphql",
+    response_derives = "Debug"
+)]
+/// Query struct derived from file github_issueorpull.graphql
+///
+/// Lookups are sent with `LookupBatch`, which reuses the types generated here
+#[allow(dead_code)]
+pub struct CommitLookup;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issueorpull.graphql",
+    response_derives = "Debug"
+)]
+/// Query struct derived from file github_issueorpull.graphql
+///
+/// Lookups are sent with `LookupBatch`, which reuses the types generated here
+#[allow(dead_code)]
+pub struct ReleaseLookup;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Query struct derived from file github_issue_management.graphql
+///
+/// Finds the node ID of a repo to create issues in
+pub struct RepositoryTarget;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Query struct derived from file github_issue_management.graphql
+///
+/// Finds the node ID of an issue or pull to close or comment on
+pub struct IssueTarget;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Query struct derived from file github_issue_management.graphql
+///
+/// Finds the node ID of an issue or pull and a page of the labels that can be added to it
+pub struct LabelTarget;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Mutation struct derived from file github_issue_management.graphql
+pub struct CreateIssue;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Mutation struct derived from file github_issue_management.graphql
+pub struct CloseIssue;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Mutation struct derived from file github_issue_management.graphql
+pub struct ClosePullRequest;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Mutation struct derived from file github_issue_management.graphql
+pub struct AddLabels;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/queries/github_schema.graphql",
+    query_path = "src/queries/github_issue_management.graphql",
+    response_derives = "Debug"
+)]
+/// Mutation struct derived from file github_issue_management.graphql
+pub struct AddComment;
+
+/// Query struct for looking up many issues, pulls, commits, and releases in a single request
+///
+/// GraphQL has no way to look up a list of repo and number pairs in one field, so the document
+/// is built at runtime with one aliased `repository` field per lookup. The fragments from
+/// github_issueorpull.graphql are reused so results share their types with the derived queries
+pub struct LookupBatch;
+
+impl LookupBatch {
+    /// Name of the operation built by `build_query`
+    pub const OPERATION_NAME: &'static str = "LookupBatch";
+
+    /// Builds the request body for the supplied lookups
+    ///
+    /// Results for a lookup are found under the alias returned by `lookup_batch::alias`
+    /// for its index in `lookups`
+    pub fn build_query(lookups: &[lookup_batch::Lookup]) -> serde_json::Value {
+        use lookup_batch::Lookup;
+
+        let mut parameters = Vec::new();
+        let mut fields = String::new();
+        let mut variables = serde_json::Map::new();
+        for (index, lookup) in lookups.iter().enumerate() {
+            let (owner, name, field) = match lookup {
+                Lookup::IssueOrPull {
+                    owner,
+                    name,
+                    number,
+                } => {
+                    parameters.push(format!("$number{}: Int!", index));
+                    variables.insert(format!("number{}", index), (*number).into());
+                    (
+                        owner,
+                        name,
+                        format!(
+                            "issueOrPullRequest(number: $number{}) {{\n      ...IssueOrPullDetails\n    }}",
+                            index
+                        ),
+                    )
+                }
+                Lookup::Commit {
+                    owner,
+                    name,
+                    expression,
+                } => {
+                    parameters.push(format!("$expression{}: String!", index));
+                    variables.insert(format!("expression{}", index), expression.clone().into());
+                    (
+                        owner,
+                        name,
+                        format!(
+                            "object(expression: $expression{}) {{\n      __typename\n      ...CommitDetails\n    }}",
+                            index
+                        ),
+                    )
+                }
+                Lookup::Release { owner, name, tag } => {
+                    parameters.push(format!("$tag{}: String!", index));
+                    variables.insert(format!("tag{}", index), tag.clone().into());
+                    (
+                        owner,
+                        name,
+                        format!(
+                            "release(tagName: $tag{}) {{\n      ...ReleaseDetails\n    }}",
+                            index
+                        ),
+                    )
+                }
+            };
+            parameters.push(format!("$owner{i}: String!, $name{i}: String!", i = index));
+            fields.push_str(&format!(
+                "  {alias}: repository(owner: $owner{i}, name: $name{i}) {{\n    {field}\n  }}\n",
+                alias = lookup_batch::alias(index),
+                i = index,
+                field = field
+            ));
+            variables.insert(format!("owner{}", index), owner.clone().into());
+            variables.insert(format!("name{}", index), name.clone().into());
+        }
+        let query = format!(
+            "{}\nquery {}({}) {{\n  rateLimit {{\n    ...RateLimitDetails\n  }}\n{}}}\n",
+            issue_or_pull::QUERY,
+            Self::OPERATION_NAME,
+            parameters.join(", "),
+            fields
+        );
+        serde_json::json!({
+            "query": query,
+            "variables": variables,
+            "operationName": Self::OPERATION_NAME,
+        })
+    }
+}
+
+/// Types used to build and read `LookupBatch` queries
+pub mod lookup_batch {
+    use super::commit_lookup::CommitDetails;
+    use super::issue_or_pull::{IssueOrPullDetails, RateLimitDetails};
+    use super::release_lookup::ReleaseDetails;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    /// A single item to look up
+    pub enum Lookup {
+        /// An issue or pull by number
+        IssueOrPull {
+            /// Owner of the repo
+            owner: String,
+            /// Name of the repo
+            name: String,
+            /// Issue or pull number
+            number: i64,
+        },
+        /// A commit by sha or any other git revision expression
+        Commit {
+            /// Owner of the repo
+            owner: String,
+            /// Name of the repo
+            name: String,
+            /// Git revision expression, such as a full or abbreviated sha
+            expression: String,
+        },
+        /// A release by tag name
+        Release {
+            /// Owner of the repo
+            owner: String,
+            /// Name of the repo
+            name: String,
+            /// Tag name of the release
+            tag: String,
+        },
+    }
+
+    #[derive(Debug, Deserialize)]
+    /// Response data of a batch query
+    pub struct ResponseData {
+        /// Rate limit information for the query
+        #[serde(rename = "rateLimit")]
+        pub rate_limit: Option<RateLimitDetails>,
+        /// Results keyed by the alias of each lookup
+        #[serde(flatten)]
+        pub lookups: HashMap<String, Option<Repository>>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    /// Result of a single aliased lookup
+    ///
+    /// Only the field matching the kind of lookup will be set
+    pub struct Repository {
+        /// The found issue or pull if it exists
+        #[serde(rename = "issueOrPullRequest", default)]
+        pub issue_or_pull_request: Option<IssueOrPullDetails>,
+        /// The found git object if it exists
+        #[serde(default)]
+        pub object: Option<GitObject>,
+        /// The found release if it exists
+        #[serde(default)]
+        pub release: Option<ReleaseDetails>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    /// A git object found by a revision expression
+    pub enum GitObject {
+        /// The object is a commit
+        Commit(CommitDetails),
+        /// The object is a tree, blob, or tag which are not supported
+        Other {},
+    }
+
+    /// Returns the alias used for the lookup at the supplied index
+    pub fn alias(index: usize) -> String {
+        format!("lookup{}", index)
+    }
+}
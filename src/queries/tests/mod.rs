@@ -1,6 +1,6 @@
 mod common;
 
-use super::issue_or_pull::IssueOrPullRepositoryIssueOrPullRequest::{Issue, PullRequest};
+use super::issue_or_pull::IssueOrPullDetails::{Issue, PullRequest};
 use super::*;
 use common::load_access_token;
 use reqwest::header::{self, HeaderValue};
@@ -112,3 +112,103 @@ async fn not_found() {
         }
     }
 }
+
+#[test]
+fn batch_query_aliases() {
+    let lookups = vec![
+        lookup_batch::Lookup::IssueOrPull {
+            owner: "jellyfin".to_string(),
+            name: "jellyfin".to_string(),
+            number: 1234,
+        },
+        lookup_batch::Lookup::Commit {
+            owner: "jellyfin".to_string(),
+            name: "jellyfin-web".to_string(),
+            expression: "abc1234".to_string(),
+        },
+        lookup_batch::Lookup::Release {
+            owner: "jellyfin".to_string(),
+            name: "jellyfin".to_string(),
+            tag: "v10.8.0".to_string(),
+        },
+    ];
+    let query = LookupBatch::build_query(&lookups);
+    let document = query["query"].as_str().unwrap();
+
+    assert!(document.contains("lookup0: repository(owner: $owner0, name: $name0)"));
+    assert!(document.contains("issueOrPullRequest(number: $number0)"));
+    assert!(document.contains("lookup1: repository(owner: $owner1, name: $name1)"));
+    assert!(document.contains("object(expression: $expression1)"));
+    assert!(document.contains("release(tagName: $tag2)"));
+    assert!(document.contains("fragment IssueOrPullDetails"));
+    assert_eq!("jellyfin-web", query["variables"]["name1"]);
+    assert_eq!("abc1234", query["variables"]["expression1"]);
+    assert_eq!("v10.8.0", query["variables"]["tag2"]);
+    assert_eq!(1234, query["variables"]["number0"]);
+    assert_eq!(LookupBatch::OPERATION_NAME, query["operationName"]);
+}
+
+#[tokio::test]
+async fn batch() {
+    let access_token = load_access_token();
+    let client = reqwest::Client::new();
+    let lookups = vec![
+        lookup_batch::Lookup::IssueOrPull {
+            owner: "jellyfin".to_string(),
+            name: "jellyfin".to_string(),
+            number: 1234,
+        },
+        lookup_batch::Lookup::IssueOrPull {
+            owner: "jellyfin".to_string(),
+            name: "jellyfin".to_string(),
+            number: 123456,
+        },
+        lookup_batch::Lookup::Release {
+            owner: "jellyfin".to_string(),
+            name: "jellyfin".to_string(),
+            tag: "v10.8.0".to_string(),
+        },
+    ];
+    let query = LookupBatch::build_query(&lookups);
+
+    let response = client
+        .post("https://api.github.com/graphql")
+        .bearer_auth(access_token)
+        .header(
+            header::USER_AGENT,
+            HeaderValue::from_static("jellyfin-matrix-bot/tester"),
+        )
+        .json(&query)
+        .send()
+        .await
+        .unwrap();
+    let response_body: Response<lookup_batch::ResponseData> = response.json().await.unwrap();
+    let errors = response_body.errors.expect("no errors found");
+    let mut response_data = response_body.data.expect("missing response data");
+
+    match response_data
+        .lookups
+        .remove(&lookup_batch::alias(0))
+        .flatten()
+        .and_then(|r| r.issue_or_pull_request)
+    {
+        Some(Issue(v)) => assert_eq!("/jellyfin/jellyfin/issues/1234", v.resource_path),
+        _ => panic!("Did not get an issue back like expected"),
+    }
+    assert!(response_data
+        .lookups
+        .remove(&lookup_batch::alias(1))
+        .flatten()
+        .and_then(|r| r.issue_or_pull_request)
+        .is_none());
+    match response_data
+        .lookups
+        .remove(&lookup_batch::alias(2))
+        .flatten()
+        .and_then(|r| r.release)
+    {
+        Some(v) => assert_eq!("v10.8.0", v.tag_name),
+        None => panic!("Did not get a release back like expected"),
+    }
+    assert_eq!(1, errors.len());
+}
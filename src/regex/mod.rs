@@ -30,6 +30,48 @@ pub static GITHUB_SEARCH: Lazy<Regex> = Lazy::new(|| {
     ").unwrap()
 });
 
+pub static COMMIT_SEARCH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)
+    ([[:alpha:]-]+)                 # The repo to search against (captured)
+    ([^[:alnum:]\s\#/-])             # The sigil before a commit, checked against config later (captured)
+    ([[:xdigit:]]{7,40})            # The full or abbreviated commit sha to search for (captured)
+    \b
+    ",
+    )
+    .unwrap()
+});
+
+pub static FORGE_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?x)
+    https?://[^\s<>"]+             # Any http or https URL, checked against searchable repos later
+    "#,
+    )
+    .unwrap()
+});
+
+pub static URL_DOMAIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?x)
+    https?://                      # Any http or https URL
+    (?:[^\s/@<>"]*@)?              # Skip any userinfo before the host
+    ([[:alnum:].-]+)               # The domain of the URL (captured)
+    "#,
+    )
+    .unwrap()
+});
+
+pub static USER_MENTION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)
+    (@[[:alnum:]._=/+-]+           # The localpart of a user ID (captured with the server)
+    :[[:alnum:].-]+(?::[0-9]+)?)   # The server name of the user ID with optional port
+    ",
+    )
+    .unwrap()
+});
+
 pub static LINK_URL: Lazy<Regex> = Lazy::new(|| {
     Regex::new(
         r"(?x)
@@ -76,3 +118,7 @@ pub static MX_REPLY: Lazy<Regex> =
 pub static PARAGRAPH_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)(</*?p>)*").unwrap());
 
 pub static FORMATTED_USERNAME: Lazy<Regex> = Lazy::new(|| Regex::new("(@.+:[^\"]+)").unwrap());
+
+pub static FORMATTED_PILL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<a href="https://matrix.to/#/(@[^"]+:[^"]+)">([^<]*)</a>"#).unwrap()
+});
@@ -177,4 +177,85 @@ mod capture {
 
         assert_eq!(actual_username, captured_username);
     }
+
+    #[test]
+    fn capture_forge_urls() {
+        let input_string = "Fixed in https://github.com/jellyfin/jellyfin/pull/1234, see <a href=\"https://github.com/jellyfin/jellyfin/issues/12\">this</a>";
+
+        let captured_urls = FORGE_URL
+            .find_iter(input_string)
+            .map(|m| m.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "https://github.com/jellyfin/jellyfin/pull/1234,",
+                "https://github.com/jellyfin/jellyfin/issues/12"
+            ],
+            captured_urls
+        );
+    }
+
+    #[test]
+    fn capture_url_domains() {
+        let input_string = "Free stuff at https://spam.example.com/win and <a href=\"http://user@Other-Site.org:8080\">here</a>";
+
+        let captured_domains = URL_DOMAIN
+            .captures_iter(input_string)
+            .map(|c| c[1].to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec!["spam.example.com".to_string(), "Other-Site.org".to_string()],
+            captured_domains
+        );
+    }
+
+    #[test]
+    fn capture_user_mentions() {
+        let input_string = "hey @alice:example.com and <a href=\"https://matrix.to/#/@bob.b:matrix.org:8448\">bob</a>, email me at bob@example";
+
+        let captured_users = USER_MENTION
+            .captures_iter(input_string)
+            .map(|c| c[1].to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "@alice:example.com".to_string(),
+                "@bob.b:matrix.org:8448".to_string()
+            ],
+            captured_users
+        );
+    }
+
+    #[test]
+    fn capture_commit_searches() {
+        let input_string =
+            "Broken by jf@abc1234 and jf-web^0123456789abcdef0123456789abcdef01234567, not jf@docs";
+
+        let captured_commits = COMMIT_SEARCH
+            .captures_iter(input_string)
+            .map(|c| (c[1].to_string(), c[2].to_string(), c[3].to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                ("jf".to_string(), "@".to_string(), "abc1234".to_string()),
+                (
+                    "jf-web".to_string(),
+                    "^".to_string(),
+                    "0123456789abcdef0123456789abcdef01234567".to_string()
+                )
+            ],
+            captured_commits
+        );
+    }
+
+    #[test]
+    fn no_capture_commit_searches_in_urls() {
+        let input_string = "https://github.com/jellyfin/jellyfin/commit/abc1234 and jf#1234567";
+
+        assert_eq!(false, COMMIT_SEARCH.is_match(input_string));
+    }
 }
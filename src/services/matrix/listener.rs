@@ -2,24 +2,31 @@
 //! plus main loop initialization.
 
 use super::MatrixClient;
-use crate::config::{Config, MatrixListenerConfig};
+use crate::config::MatrixListenerConfig;
 use crate::database::insert_or_update;
 use crate::database::models::LastSync;
+use crate::helpers::save_power_levels;
 use crate::messages::MatrixMessage;
-use crate::services::matrix::matrix_handlers::listeners::{handle_invite_event, handle_text_event};
+use crate::services::matrix::matrix_handlers::listeners::{
+    anti_spam_handler, handle_edit_event, handle_invite_event, handle_text_event, policy_handler,
+    policy_join_handler, welcome_handler, welcome_join_handler, SpamTracker, WelcomeQueue,
+};
 use native_db::Database;
 use ruma::{
     api::client::sync::sync_events,
     events::{
+        room::member::{MembershipChange, MembershipState},
         room::message::{
             MessageType, OriginalSyncRoomMessageEvent, Relation, RoomMessageEventContent,
             SyncRoomMessageEvent,
         },
-        AnyStrippedStateEvent, AnySyncMessageLikeEvent, AnySyncTimelineEvent,
+        AnyStrippedStateEvent, AnySyncMessageLikeEvent, AnySyncStateEvent, AnySyncTimelineEvent,
+        SyncStateEvent,
     },
     presence::PresenceState,
 };
 
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::watch::Receiver;
@@ -27,29 +34,34 @@ use tracing::{debug, error, trace};
 
 /// Struct representing all required data for a functioning bot instance.
 pub struct MatrixListener<'a> {
-    /// Configuration data.
-    pub config: MatrixListenerConfig,
+    /// Configuration data, replaced when the config is reloaded.
+    pub config: Receiver<Arc<MatrixListenerConfig>>,
     /// Reqwest client used for external API calls.
     pub api_client: reqwest::Client,
     send: Sender<MatrixMessage>,
     /// Storage data.
     pub storage: &'a Database<'a>,
+    /// Recent messages used to flag spam.
+    spam_tracker: SpamTracker,
+    /// Users waiting to be welcomed to rooms.
+    welcome_queue: WelcomeQueue,
 }
 
 impl<'a> MatrixListener<'a> {
     /// Loads storage data, config data, and then creates a reqwest client and then returns a Bot instance.
     pub fn new(
-        config: &Config,
+        config: Receiver<Arc<MatrixListenerConfig>>,
         send: Sender<MatrixMessage>,
         storage: &'a Database<'a>,
     ) -> anyhow::Result<MatrixListener<'a>> {
-        let config = MatrixListenerConfig::new(config);
         let api_client = reqwest::Client::new();
         Ok(Self {
             config,
             api_client,
             send,
             storage,
+            spam_tracker: SpamTracker::default(),
+            welcome_queue: WelcomeQueue::default(),
         })
     }
 
@@ -67,6 +79,8 @@ impl<'a> MatrixListener<'a> {
                     .map_or_else(|| None, |v| Some(v.last_sync))
             };
             req.since = last_sync.as_deref();
+            // The first sync returns old joins, so only later syncs welcome users
+            let first_sync = last_sync.is_none();
             req.full_state = false;
             req.set_presence = &PresenceState::Unavailable;
             req.timeout = Some(Duration::new(30, 0));
@@ -87,6 +101,8 @@ impl<'a> MatrixListener<'a> {
 
                     match response {
                         Some(v) => {
+                                // Use the same config for every event in this sync, even if it is reloaded part way through
+                                let config = self.config.borrow().clone();
 
                                 let rw = self.storage.rw_transaction().unwrap();
                                 match insert_or_update(&rw, LastSync {id: 1, last_sync: last_sync.map_or(String::new(), |v| v)}, LastSync {id: 1, last_sync: v.next_batch}) {
@@ -99,9 +115,103 @@ impl<'a> MatrixListener<'a> {
                                 };
 
                             for (room_id, joined_room) in &v.rooms.join {
+                                for raw_event in &joined_room.state.events {
+                                    if let Err(e) = policy_handler(
+                                        raw_event,
+                                        room_id,
+                                        self.storage,
+                                        &config,
+                                        &mut self.send,
+                                    )
+                                    .await
+                                    {
+                                        error!("{:#}", e);
+                                    }
+                                    match raw_event.deserialize() {
+                                        Ok(AnySyncStateEvent::RoomPowerLevels(
+                                            SyncStateEvent::Original(e),
+                                        )) => {
+                                            if let Err(e) =
+                                                save_power_levels(self.storage, room_id, &e.content)
+                                            {
+                                                error!("{:#}", e);
+                                            }
+                                        }
+                                        Ok(AnySyncStateEvent::RoomMember(SyncStateEvent::Original(e)))
+                                            if e.content.membership == MembershipState::Join =>
+                                        {
+                                            if let Err(e) = policy_join_handler(
+                                                &e.state_key,
+                                                room_id,
+                                                self.storage,
+                                                &config,
+                                                &mut self.send,
+                                            )
+                                            .await
+                                            {
+                                                error!("{:#}", e);
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
                                 for raw_event in &joined_room.timeline.events {
+                                    if let Err(e) = policy_handler(
+                                        raw_event,
+                                        room_id,
+                                        self.storage,
+                                        &config,
+                                        &mut self.send,
+                                    )
+                                    .await
+                                    {
+                                        error!("{:#}", e);
+                                    }
                                     let event = raw_event.deserialize();
                                     match event {
+                                        Ok(AnySyncTimelineEvent::State(
+                                            AnySyncStateEvent::RoomPowerLevels(SyncStateEvent::Original(e)),
+                                        )) => {
+                                            if let Err(e) =
+                                                save_power_levels(self.storage, room_id, &e.content)
+                                            {
+                                                error!("{:#}", e);
+                                            }
+                                        }
+                                        Ok(AnySyncTimelineEvent::State(
+                                            AnySyncStateEvent::RoomMember(SyncStateEvent::Original(e)),
+                                        )) => {
+                                            match e.membership_change() {
+                                                MembershipChange::Joined if !first_sync => {
+                                                    welcome_join_handler(
+                                                        &e.state_key,
+                                                        room_id,
+                                                        &mut self.welcome_queue,
+                                                        &config,
+                                                    )
+                                                }
+                                                MembershipChange::Left
+                                                | MembershipChange::Banned
+                                                | MembershipChange::Kicked
+                                                | MembershipChange::KickedAndBanned => {
+                                                    self.welcome_queue.leave(room_id, &e.state_key)
+                                                }
+                                                _ => {}
+                                            }
+                                            if e.content.membership == MembershipState::Join {
+                                                if let Err(e) = policy_join_handler(
+                                                    &e.state_key,
+                                                    room_id,
+                                                    self.storage,
+                                                    &config,
+                                                    &mut self.send,
+                                                )
+                                                .await
+                                                {
+                                                    error!("{:#}", e);
+                                                }
+                                            }
+                                        }
                                         Ok(AnySyncTimelineEvent::MessageLike(
                                             AnySyncMessageLikeEvent::RoomMessage(
                                                 SyncRoomMessageEvent::Original(
@@ -112,23 +222,78 @@ impl<'a> MatrixListener<'a> {
                                                                 relates_to,
                                                                 ..
                                                             },
+                                                        event_id,
                                                         sender,
                                                         ..
                                                     },
                                                 ),
                                             ),
                                         )) => {
-                                            if matches!(relates_to, Some(Relation::Replacement(_))) {
-                                                debug!("Message is an edit, skipping handling");
+                                            if let Some(Relation::Replacement(r)) = &relates_to {
+                                                debug!("Message is an edit of {}", r.event_id);
+                                                if let MessageType::Text(new) =
+                                                    &r.new_content.msgtype
+                                                {
+                                                    // Edits are checked too so spam can not be edited into a message.
+                                                    // Only the edit is redacted, as anyone can send an edit of any message
+                                                    match anti_spam_handler(
+                                                        new,
+                                                        &event_id,
+                                                        &sender,
+                                                        room_id,
+                                                        &mut self.spam_tracker,
+                                                        self.storage,
+                                                        &config,
+                                                        &mut self.send,
+                                                    )
+                                                    .await
+                                                    {
+                                                        Ok(true) => continue,
+                                                        Ok(false) => (),
+                                                        Err(e) => error!("{:#}", e),
+                                                    }
+                                                    if let Err(e) = handle_edit_event(
+                                                        new,
+                                                        &r.event_id,
+                                                        &sender,
+                                                        room_id,
+                                                        self.storage,
+                                                        &config,
+                                                        &self.api_client,
+                                                        &client,
+                                                        &mut self.send,
+                                                    )
+                                                    .await
+                                                    {
+                                                        error!("{:#}", e);
+                                                    }
+                                                }
                                                 continue;
                                             }
+                                            match anti_spam_handler(
+                                                &t,
+                                                &event_id,
+                                                &sender,
+                                                room_id,
+                                                &mut self.spam_tracker,
+                                                self.storage,
+                                                &config,
+                                                &mut self.send,
+                                            )
+                                            .await
+                                            {
+                                                Ok(true) => continue,
+                                                Ok(false) => (),
+                                                Err(e) => error!("{:#}", e),
+                                            }
                                             if let Err(e) = handle_text_event(
                                                 &t,
+                                                &event_id,
                                                 relates_to.as_ref(),
                                                 &sender,
                                                 room_id,
                                                 &mut self.storage,
-                                                &self.config,
+                                                &config,
                                                 &self.api_client,
                                                 &mut self.send,
                                             )
@@ -155,7 +320,8 @@ impl<'a> MatrixListener<'a> {
                                             if let Err(e) = handle_invite_event(
                                                 &s.sender,
                                                 room_id,
-                                                &self.config,
+                                                self.storage,
+                                                &config,
                                                 &mut self.send,
                                             )
                                             .await
@@ -175,6 +341,16 @@ impl<'a> MatrixListener<'a> {
                                     }
                                 }
                             }
+                            if let Err(e) = welcome_handler(
+                                &mut self.welcome_queue,
+                                self.storage,
+                                &config,
+                                &mut self.send,
+                            )
+                            .await
+                            {
+                                error!("{:#}", e);
+                            }
                         }
                         None => debug!("Response deserialization failed. Doing nothing this loop."),
                     }
@@ -0,0 +1,240 @@
+//! Flags spam in chat and acts against the users who send it
+//!
+//! Recent messages are only kept in memory, for as long as the flood and repeat periods need them
+
+use super::moderation_handler::target_rooms;
+use crate::config::{AntiSpam, Capability, MatrixListenerConfig, SpamAction};
+use crate::helpers::is_permitted;
+use crate::messages::{
+    MatrixBanMessage, MatrixMessage, MatrixMessageType, MatrixMuteMessage, MatrixRedactMessage,
+    MatrixWarnMessage,
+};
+use crate::regex::{URL_DOMAIN, USER_MENTION};
+use anyhow::bail;
+use native_db::Database;
+use ruma::{
+    events::room::message::TextMessageEventContent, EventId, OwnedRoomId, OwnedUserId, RoomId,
+    UserId,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tracing::info;
+
+/// A message recently sent by a user
+struct RecentMessage {
+    sent: Instant,
+    room_id: OwnedRoomId,
+    body: String,
+}
+
+#[derive(Default)]
+/// Recent messages of each user, used to find floods and repeated messages
+pub struct SpamTracker {
+    recent: HashMap<OwnedUserId, VecDeque<RecentMessage>>,
+}
+
+impl SpamTracker {
+    /// Records a message sent at the supplied time and checks it against the rules
+    ///
+    /// Returns the action to take and why if the message is spam. Flagged floods and repeats are forgotten so they are only acted on once
+    pub fn check(
+        &mut self,
+        rules: &AntiSpam,
+        sender: &UserId,
+        room_id: &RoomId,
+        body: &str,
+        formatted: Option<&str>,
+        now: Instant,
+    ) -> Option<(SpamAction, String)> {
+        if let Some(rule) = &rules.blocked_domains {
+            if let Some(domain) = linked_domains(body, formatted)
+                .into_iter()
+                .find(|d| is_blocked(&rule.domains, d))
+            {
+                return Some((rule.action, format!("linked to blocked domain {}", domain)));
+            }
+        }
+        if let Some(rule) = &rules.mentions {
+            let count = mentioned_users(body, formatted).len();
+            if count >= rule.limit {
+                return Some((rule.action, format!("mentioned {} users", count)));
+            }
+        }
+
+        let kept_for = rules
+            .flood
+            .iter()
+            .chain(rules.repeats.iter())
+            .map(|r| r.period)
+            .max()?;
+        self.recent.retain(|_, messages| {
+            while messages
+                .front()
+                .is_some_and(|m| now.duration_since(m.sent) > kept_for)
+            {
+                messages.pop_front();
+            }
+            !messages.is_empty()
+        });
+        let messages = self.recent.entry(sender.to_owned()).or_default();
+        messages.push_back(RecentMessage {
+            sent: now,
+            room_id: room_id.to_owned(),
+            body: body.trim().to_string(),
+        });
+
+        let within = |period: Duration| {
+            messages
+                .iter()
+                .filter(move |m| now.duration_since(m.sent) <= period)
+        };
+        if let Some(rule) = &rules.flood {
+            let count = within(rule.period).filter(|m| m.room_id == room_id).count();
+            if count >= rule.limit {
+                messages.clear();
+                return Some((
+                    rule.action,
+                    format!(
+                        "sent {} messages within {}",
+                        count,
+                        humantime::format_duration(rule.period)
+                    ),
+                ));
+            }
+        }
+        if let Some(rule) = &rules.repeats {
+            let count = within(rule.period)
+                .filter(|m| m.body == body.trim())
+                .count();
+            if count >= rule.limit {
+                messages.clear();
+                return Some((
+                    rule.action,
+                    format!(
+                        "sent the same message {} times within {}",
+                        count,
+                        humantime::format_duration(rule.period)
+                    ),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Checks messages against the anti-spam rules and acts against the senders of spam
+///
+/// Users allowed to redact messages are never flagged. Returns true if the message was flagged, so it is not handled any further
+#[allow(clippy::too_many_arguments)]
+pub async fn anti_spam_handler(
+    text: &TextMessageEventContent,
+    event_id: &EventId,
+    sender: &UserId,
+    room_id: &RoomId,
+    tracker: &mut SpamTracker,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<bool> {
+    let rules = match &config.anti_spam {
+        Some(v) => v,
+        None => return Ok(false),
+    };
+    if (!rules.rooms.is_empty() && !rules.rooms.contains(room_id))
+        || sender == config.mx_uname
+        || is_permitted(sender, room_id, Capability::Redact, config, storage)
+    {
+        return Ok(false);
+    }
+    let (action, reason) = match tracker.check(
+        rules,
+        sender,
+        room_id,
+        &text.body,
+        text.formatted.as_ref().map(|f| f.body.as_str()),
+        Instant::now(),
+    ) {
+        Some(v) => v,
+        None => return Ok(false),
+    };
+
+    info!(
+        "Message {} of {} in {} flagged as spam: {}",
+        event_id, sender, room_id, reason
+    );
+    let reason = format!("spam, {}", reason);
+    let message = match action {
+        SpamAction::Warn => MatrixMessageType::Warn(MatrixWarnMessage {
+            sender: config.mx_uname.clone(),
+            user: sender.to_owned(),
+            room_id: room_id.to_owned(),
+            reason,
+        }),
+        SpamAction::Redact => MatrixMessageType::Redact(MatrixRedactMessage {
+            sender: config.mx_uname.clone(),
+            room_id: room_id.to_owned(),
+            event_id: event_id.to_owned(),
+            reason: Some(reason),
+        }),
+        SpamAction::Mute => MatrixMessageType::Mute(MatrixMuteMessage {
+            sender: config.mx_uname.clone(),
+            user: sender.to_owned(),
+            duration: rules.mute_duration,
+            reason: Some(reason),
+            rooms: target_rooms(config, room_id),
+        }),
+        SpamAction::Ban => MatrixMessageType::Ban(MatrixBanMessage {
+            sender: config.mx_uname.clone(),
+            user: sender.to_owned(),
+            duration: None,
+            reason: Some(reason),
+            rooms: target_rooms(config, room_id),
+            publish: false,
+        }),
+    };
+    if send
+        .send(MatrixMessage {
+            room_id: None,
+            reply_to: None,
+            message,
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed, unable to send mesage.");
+    }
+    Ok(true)
+}
+
+/// Returns the lowercase domains of every URL in the message
+fn linked_domains(body: &str, formatted: Option<&str>) -> Vec<String> {
+    std::iter::once(body)
+        .chain(formatted)
+        .flat_map(|text| URL_DOMAIN.captures_iter(text))
+        .map(|c| c[1].trim_end_matches('.').to_lowercase())
+        .collect()
+}
+
+/// Returns true if the domain or any domain it is a subdomain of is blocked
+fn is_blocked(blocked: &HashSet<Box<str>>, domain: &str) -> bool {
+    let mut domain = domain;
+    loop {
+        if blocked.contains(domain) {
+            return true;
+        }
+        match domain.split_once('.') {
+            Some((_, parent)) => domain = parent,
+            None => return false,
+        }
+    }
+}
+
+/// Returns every user ID mentioned in the message, including those in pills
+fn mentioned_users(body: &str, formatted: Option<&str>) -> HashSet<String> {
+    std::iter::once(body)
+        .chain(formatted)
+        .flat_map(|text| USER_MENTION.captures_iter(text))
+        .map(|c| c[1].to_string())
+        .collect()
+}
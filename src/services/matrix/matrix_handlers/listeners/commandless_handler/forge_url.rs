@@ -0,0 +1,80 @@
+//! Parses pasted forge URLs into searches for configured repos
+
+use crate::config::{Forge, SearchableRepo};
+use crate::forges::{RefSearch, Search};
+use std::collections::HashMap;
+
+/// Search parsed from a pasted URL
+#[derive(Debug, PartialEq, Eq)]
+pub enum ForgeUrl<'a> {
+    /// An issue or pull on the supplied forge
+    IssueOrPull(&'a Forge, Search),
+    /// A commit or release on github
+    Ref(RefSearch),
+}
+
+/// Parses a pasted URL into a search if it points at an issue, pull, commit, or release of a configured repo
+///
+/// Commits and releases are only supported for github repos
+pub fn parse_forge_url<'a>(
+    url: &str,
+    repos: &'a HashMap<Box<str>, SearchableRepo>,
+) -> Option<ForgeUrl<'a>> {
+    let url = url.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '>']);
+    let url = url.split(['?', '#']).next()?;
+    let lowercase_url = url.to_lowercase();
+    for repo in repos.values() {
+        let prefix = match &repo.forge {
+            Forge::Github => format!("https://github.com/{}/", repo.repo),
+            Forge::Gitea { url, .. } => {
+                format!("{}/{}/", url.as_str().trim_end_matches('/'), repo.repo)
+            }
+            Forge::Gitlab { url, .. } => {
+                format!("{}/{}/-/", url.as_str().trim_end_matches('/'), repo.repo)
+            }
+        };
+        if !lowercase_url.starts_with(&prefix.to_lowercase()) {
+            continue;
+        }
+        let path = url.get(prefix.len()..)?.split('/').collect::<Vec<_>>();
+        let number = |n: &str| n.parse::<i64>().ok();
+        let search = |n: &str| {
+            number(n).map(|number| {
+                ForgeUrl::IssueOrPull(
+                    &repo.forge,
+                    Search {
+                        repo: repo.repo.to_string(),
+                        number,
+                    },
+                )
+            })
+        };
+        return match (&repo.forge, path.as_slice()) {
+            (Forge::Github, ["issues", n, ..]) | (Forge::Github, ["pull", n, ..]) => search(n),
+            (Forge::Gitea { .. }, ["issues", n, ..]) | (Forge::Gitea { .. }, ["pulls", n, ..]) => {
+                search(n)
+            }
+            (Forge::Gitlab { .. }, ["issues", n, ..])
+            | (Forge::Gitlab { .. }, ["merge_requests", n, ..]) => search(n),
+            (Forge::Github, ["commit", sha, ..]) if is_sha(sha) => {
+                Some(ForgeUrl::Ref(RefSearch::Commit {
+                    repo: repo.repo.to_string(),
+                    sha: sha.to_string(),
+                }))
+            }
+            (Forge::Github, ["releases", "tag", tag @ ..]) if !tag.is_empty() => {
+                Some(ForgeUrl::Ref(RefSearch::Release {
+                    repo: repo.repo.to_string(),
+                    tag: tag.join("/"),
+                }))
+            }
+            _ => None,
+        };
+    }
+    None
+}
+
+/// Returns `true` if the supplied text could be a full or abbreviated commit sha
+pub fn is_sha(text: &str) -> bool {
+    (7..=40).contains(&text.len()) && text.chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -1,58 +1,59 @@
 //! Performs search of issues and pulls in message text and builds proper response
 
-use crate::config::MatrixListenerConfig;
+use super::forge_url::{parse_forge_url, ForgeUrl};
+use crate::config::{Forge, MatrixListenerConfig};
+use crate::forges::{backend, github_backend, RefSearch, Search};
 use crate::helpers::{clean_text, MatrixNoticeResponse};
-use crate::queries::issue_or_pull::IssueOrPullRepositoryIssueOrPullRequest::{Issue, PullRequest};
-use crate::queries::*;
-use crate::regex::GITHUB_SEARCH;
-use graphql_client::GraphQLQuery;
-use reqwest::{header, Url};
+use crate::regex::{COMMIT_SEARCH, FORGE_URL, GITHUB_SEARCH};
+use native_db::Database;
 use ruma::events::room::message::TextMessageEventContent;
+use ruma::RoomId;
 use tracing::{debug, error, trace};
 
 /// Searches and links found issues or pulls requested and builds response text
+///
+/// Commits requested with the configured sigil, such as "jf@abc1234", are searched in github repos.
+/// Pasted issue, pull, commit, and release URLs of configured repos are searched as well
+/// unless the room is excluded from unfurling
 pub async fn github_search(
     text: &TextMessageEventContent,
+    room_id: &RoomId,
     config: &MatrixListenerConfig,
     api_client: &reqwest::Client,
+    storage: &Database<'_>,
     notice_response: &mut MatrixNoticeResponse,
 ) {
+    let text = match &text.formatted {
+        Some(v) => clean_text(&v.body),
+        None => text.body.clone(),
+    };
+    let unfurl_urls = !config.url_unfurl_exclusion.contains(room_id);
+    if !(GITHUB_SEARCH.is_match(&text)
+        || COMMIT_SEARCH.is_match(&text)
+        || unfurl_urls && FORGE_URL.is_match(&text))
+    {
+        debug!("There are no remaining matches after cleaning tags. Doing nothing.");
+        return;
+    }
     let mut repos_to_search = Vec::new();
-    match &text.formatted {
-        Some(v) => {
-            let clean_text = clean_text(&v.body);
-            if GITHUB_SEARCH.is_match(&clean_text) {
-                for cap in GITHUB_SEARCH.captures_iter(&clean_text.to_lowercase()) {
-                    trace!("{:?}", cap);
-                    repos_to_search.push((cap[1].to_string(), cap[2].to_string()))
-                }
-            } else {
-                debug!("There are no remaining matches after cleaning tags. Doing nothing.");
-                return;
-            }
-        }
-        None => {
-            for cap in GITHUB_SEARCH.captures_iter(&text.body.to_lowercase()) {
-                repos_to_search.push((cap[1].to_string(), cap[2].to_string()))
-            }
-        }
+    for cap in GITHUB_SEARCH.captures_iter(&text.to_lowercase()) {
+        trace!("{:?}", cap);
+        repos_to_search.push((cap[1].to_string(), cap[2].to_string()))
     }
     let repos_to_search = repos_to_search;
-    let mut searches = Vec::new();
+    let mut searches: Vec<(&Forge, Search)> = Vec::new();
+    let mut refs: Vec<RefSearch> = Vec::new();
     for (repo, number) in repos_to_search {
         match number.parse::<i64>() {
             Ok(n) => match config.repos.get(&repo.to_lowercase().into_boxed_str()) {
                 Some(r) => {
-                    let index = match r.find('/') {
-                        Some(v) => v,
-                        None => {
-                            debug!("No / was found in repo/owner pair {:?}. Unable to search such a thing.", r);
-                            continue;
-                        }
+                    let search = Search {
+                        repo: r.repo.to_string(),
+                        number: n,
                     };
-                    let (owner, repo) = r.split_at(index);
-                    let repo = repo.replace('/', "");
-                    searches.push((owner.to_string(), repo.to_string(), n))
+                    if !searches.iter().any(|(f, s)| *f == &r.forge && *s == search) {
+                        searches.push((&r.forge, search))
+                    }
                 }
                 None => {
                     debug!("Repo {:?} not found", repo);
@@ -67,89 +68,120 @@ pub async fn github_search(
             }
         }
     }
+    for cap in COMMIT_SEARCH.captures_iter(&text) {
+        trace!("{:?}", cap);
+        if !cap[2].starts_with(config.commit_search_sigil) {
+            continue;
+        }
+        match config.repos.get(&cap[1].to_lowercase().into_boxed_str()) {
+            Some(r) if r.forge == Forge::Github => {
+                let search = RefSearch::Commit {
+                    repo: r.repo.to_string(),
+                    sha: cap[3].to_lowercase(),
+                };
+                if !refs.contains(&search) {
+                    refs.push(search)
+                }
+            }
+            Some(_) => debug!("Repo {:?} does not support commit searches", &cap[1]),
+            None => debug!("Repo {:?} not found", &cap[1]),
+        }
+    }
+    if unfurl_urls {
+        for url in FORGE_URL.find_iter(&text) {
+            trace!("Found url {:?}", url.as_str());
+            match parse_forge_url(url.as_str(), &config.repos) {
+                Some(ForgeUrl::IssueOrPull(forge, search)) => {
+                    if !searches.iter().any(|(f, s)| *f == forge && *s == search) {
+                        searches.push((forge, search))
+                    }
+                }
+                Some(ForgeUrl::Ref(search)) => {
+                    if !refs.contains(&search) {
+                        refs.push(search)
+                    }
+                }
+                None => debug!("Url {:?} is not for a searchable repo", url.as_str()),
+            }
+        }
+    }
     let searches = searches;
-    debug!("Queued searches: {:?}", searches);
-    if searches.is_empty() {
+    let refs = refs;
+    debug!("Queued searches: {:?} {:?}", searches, refs);
+    if searches.is_empty() && refs.is_empty() {
         debug!("No searches found after parsing numbers. No searches will be built.");
         return;
     }
-    let mut results = Vec::new();
-    for (owner, name, number) in searches {
-        let query = IssueOrPull::build_query(issue_or_pull::Variables {
-            name,
-            owner,
-            number,
-        });
-        let response_body = match api_client
-            .post("https://api.github.com/graphql")
-            .bearer_auth(config.gh_access_token.clone())
-            .header(header::USER_AGENT, config.user_agent.clone())
-            .json(&query)
-            .send()
-            .await
-        {
-            Ok(r) => {
-                let response_body: graphql_client::Response<issue_or_pull::ResponseData> =
-                    match r.json().await {
-                        Ok(b) => b,
-                        Err(e) => {
-                            error!("No response body found. Error is {:?}", e);
-                            continue;
-                        }
-                    };
-                response_body
-            }
-            Err(e) => {
-                error!("Query failed, Error is {:?}", e);
-                continue;
+
+    // Group searches by forge so each backend can look up all of its searches at once
+    let mut groups: Vec<(&Forge, Vec<usize>)> = Vec::new();
+    for (slot, (forge, _)) in searches.iter().enumerate() {
+        match groups.iter_mut().find(|(f, _)| f == forge) {
+            Some((_, slots)) => slots.push(slot),
+            None => groups.push((forge, vec![slot])),
+        }
+    }
+    if !refs.is_empty() && !groups.iter().any(|(f, _)| **f == Forge::Github) {
+        groups.push((&Forge::Github, Vec::new()));
+    }
+    let mut outcomes = vec![None; searches.len()];
+    let mut ref_outcomes = Vec::new();
+    let mut errors = Vec::new();
+    for (forge, slots) in groups {
+        let group = slots
+            .iter()
+            .map(|slot| searches[*slot].1.clone())
+            .collect::<Vec<_>>();
+        let outcome = match forge {
+            // Commits and releases are only supported on github, and are looked up in the same query
+            Forge::Github => github_backend(config, api_client, storage)
+                .lookup(&group, &refs)
+                .await
+                .map(|(v, r)| {
+                    ref_outcomes = r;
+                    v
+                }),
+            _ => {
+                backend(forge, config, api_client, storage)
+                    .search(&group)
+                    .await
             }
         };
-        let response_data = match response_body.data {
-            Some(d) => match d.repository {
-                Some(r) => match r.issue_or_pull_request {
-                    Some(v) => v,
-                    None => {
-                        error!("Missing issue or pull request data");
-                        continue;
-                    }
-                },
-                None => {
-                    error!("Missing repository data");
-                    continue;
+        match outcome {
+            Ok(v) => {
+                for (slot, outcome) in slots.into_iter().zip(v) {
+                    outcomes[slot] = Some(outcome);
                 }
-            },
-            None => {
-                error!("Missing response data");
-                continue;
             }
-        };
+            Err(e) => errors.push(e),
+        }
+    }
 
-        match response_data {
-            Issue(v) => {
-                let result = "https://github.com".to_string() + &v.resource_path + "\n";
-                match Url::parse(&result) {
-                    Ok(v) => results.push(v),
-                    Err(e) => error!(
-                        "Unable to parse result {:?} to Url due to error {:?}",
-                        result, e
-                    ),
-                }
-            }
-            PullRequest(v) => {
-                let result = "https://github.com".to_string() + &v.resource_path + "\n";
-                match Url::parse(&result) {
-                    Ok(v) => results.push(v),
-                    Err(e) => error!(
-                        "Unable to parse result {:?} to Url due to error {:?}",
-                        result, e
-                    ),
-                }
-            }
+    let mut results = Vec::new();
+    for ((_, search), outcome) in searches.iter().zip(outcomes) {
+        match outcome {
+            Some(Ok(v)) => results.push(v),
+            Some(Err(e)) => errors.push(format!("{}#{}: {}", search.repo, search.number, e)),
+            None => (),
         }
     }
-    if results.is_empty() {
+    let mut ref_results = Vec::new();
+    for (search, outcome) in refs.iter().zip(ref_outcomes) {
+        match outcome {
+            Ok(v) => ref_results.push(v),
+            Err(e) => errors.push(format!("{}: {}", search, e)),
+        }
+    }
+    if results.is_empty() && ref_results.is_empty() {
         error!("No search resulted returned. Doing nothing");
-    } else {
+    }
+    if !results.is_empty() {
         notice_response.set_gh_results(results)
     }
+    if !ref_results.is_empty() {
+        notice_response.set_ref_results(ref_results)
+    }
+    if !errors.is_empty() {
+        notice_response.add_errors(errors);
+    }
 }
@@ -1,35 +1,45 @@
 //! Performs group pings based on message text and builds proper response
 
-use crate::config::MatrixListenerConfig;
-use crate::helpers::{clean_text, MatrixFormattedTextResponse};
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::database::models::GroupPing;
+use crate::helpers::{
+    clean_text, group_members, insert_members, is_permitted, stored_group_pings,
+    MatrixFormattedTextResponse,
+};
 use crate::regex::GROUP_PING;
-use ruma::{events::room::message::TextMessageEventContent, OwnedUserId, UserId};
+use native_db::Database;
+use ruma::{events::room::message::TextMessageEventContent, OwnedUserId, RoomId, UserId};
 use std::collections::HashSet;
 use tracing::{debug, trace};
 
 /// Finds requested users to ping and builds response text
+///
+/// Groups edited from chat are merged with groups from the config file
 pub fn group_ping(
     text: &TextMessageEventContent,
     sender: &UserId,
+    room_id: &RoomId,
     config: &MatrixListenerConfig,
+    storage: &Database<'_>,
     text_response: &mut MatrixFormattedTextResponse,
 ) {
     let mut users: HashSet<OwnedUserId> = HashSet::new();
-    if !config.group_ping_users.contains(sender) {
+    if !is_permitted(sender, room_id, Capability::GroupPing, config, storage) {
         debug!("User not authorized for group pings. Ignoring...");
         return;
     }
+    let stored = stored_group_pings(storage);
     match &text.formatted {
         Some(v) => {
             let clean_text = clean_text(&v.body);
             if GROUP_PING.is_match(&clean_text) {
-                determine_users(config, &clean_text, &mut users)
+                determine_users(config, &stored, &clean_text, &mut users)
             } else {
                 debug!("There are no remaining matches after cleaning tags. Doing nothing.");
                 return;
             }
         }
-        None => determine_users(config, &text.body, &mut users),
+        None => determine_users(config, &stored, &text.body, &mut users),
     }
     if users.is_empty() {
         debug!("No users to ping after processing.");
@@ -42,19 +52,25 @@ pub fn group_ping(
     }
 }
 
-fn determine_users(config: &MatrixListenerConfig, text: &str, users: &mut HashSet<OwnedUserId>) {
+/// Adds the members of every group pinged in the text, expanding aliases at ping time so members who joined an
+/// aliased group from chat are included
+fn determine_users(
+    config: &MatrixListenerConfig,
+    stored: &[GroupPing],
+    text: &str,
+    users: &mut HashSet<OwnedUserId>,
+) {
     for cap in GROUP_PING.captures_iter(&text.to_lowercase()) {
         trace!("{:?}", cap);
         if cap[1].eq("all") {
             for user in config.group_pings.values().flatten() {
                 users.insert(user.clone());
             }
-        } else {
-            if let Some(v) = config.group_pings.get(&cap[1]) {
-                for user in v {
-                    users.insert(user.clone());
-                }
+            for group in stored {
+                insert_members(group, users);
             }
+        } else {
+            users.extend(group_members(&cap[1], config, stored));
         }
     }
 }
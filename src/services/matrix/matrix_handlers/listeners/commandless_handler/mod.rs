@@ -1,5 +1,9 @@
 //! Contains handlers and response functions for text based non-command events
 
+#[cfg(test)]
+mod tests;
+
+mod forge_url;
 mod github_search;
 mod group_ping;
 mod link_url;
@@ -7,11 +11,14 @@ mod spellcheck;
 mod text_expansion;
 mod unit_conversion;
 
+use super::is_reply;
 use crate::config::MatrixListenerConfig;
 use crate::database::models::CorrectionTimeCooldown;
 use crate::helpers::{check_format, MatrixFormattedTextResponse, MatrixNoticeResponse};
-use crate::messages::{MatrixMessage, MatrixMessageType};
-use crate::regex::{GITHUB_SEARCH, GROUP_PING, LINK_URL, TEXT_EXPANSION, UNIT_CONVERSION};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply, MatrixResponsesMessage};
+use crate::regex::{
+    COMMIT_SEARCH, FORGE_URL, GITHUB_SEARCH, GROUP_PING, LINK_URL, TEXT_EXPANSION, UNIT_CONVERSION,
+};
 use anyhow::anyhow;
 use github_search::github_search;
 use group_ping::group_ping;
@@ -29,12 +36,16 @@ use tracing::{debug, trace};
 use unit_conversion::unit_conversion;
 
 /// Handler for all text based non-command events
+///
+/// Edited messages are handled again with their new content, replacing the responses to the original
 #[allow(clippy::too_many_arguments)]
 pub async fn commandless_handler(
     text: &TextMessageEventContent,
     relates_to: Option<&Relation>,
     sender: &UserId,
     room_id: &RoomId,
+    reply_to: &MatrixReply,
+    edited: bool,
     storage: &Database<'_>,
     config: &MatrixListenerConfig,
     api_client: &reqwest::Client,
@@ -52,9 +63,21 @@ pub async fn commandless_handler(
                     debug!("Entering commandless unit conversion path");
                     unit_conversion(text, config, &mut notice_response);
                 }
-                if GITHUB_SEARCH.is_match(&text.body) && !config.repos.is_empty() {
+                if (GITHUB_SEARCH.is_match(&text.body)
+                    || COMMIT_SEARCH.is_match(&text.body)
+                    || FORGE_URL.is_match(&text.body))
+                    && !config.repos.is_empty()
+                {
                     debug!("Entering commandless github search path");
-                    github_search(text, config, api_client, &mut notice_response).await;
+                    github_search(
+                        text,
+                        room_id,
+                        config,
+                        api_client,
+                        storage,
+                        &mut notice_response,
+                    )
+                    .await;
                 }
                 if LINK_URL.is_match(&text.body)
                     && !config.links.is_empty()
@@ -65,81 +88,73 @@ pub async fn commandless_handler(
                 }
                 if GROUP_PING.is_match(&text.body) {
                     debug!("Entering commandless group ping path");
-                    group_ping(text, sender, config, &mut text_response);
+                    group_ping(text, sender, room_id, config, storage, &mut text_response);
                 }
                 if TEXT_EXPANSION.is_match(&text.body) {
                     debug!("Entering commandless text expansion path");
-                    text_expansion(text, config, &mut notice_response);
+                    text_expansion(text, config, storage, &mut notice_response);
                 }
 
                 let notice_response = notice_response;
                 let text_response = text_response;
 
-                if notice_response.is_some()
-                    && send
-                        .send(MatrixMessage {
-                            room_id: Some(room_id.to_owned()),
-                            message: MatrixMessageType::Response(
-                                RoomMessageEventContent::notice_plain(notice_response.to_string()),
-                            ),
-                        })
-                        .await
-                        .is_err()
-                {
-                    return Err(anyhow!("Channel closed. Unable to send message."))?;
+                let mut responses = Vec::new();
+                if notice_response.is_some() {
+                    let formatted_text = notice_response.format_text().unwrap();
+                    responses.push(RoomMessageEventContent::notice_html(
+                        notice_response.to_string(),
+                        formatted_text,
+                    ));
                 }
-
                 if text_response.is_some() {
                     let formatted_text = text_response.format_text().unwrap();
-                    if send
-                        .send(MatrixMessage {
-                            room_id: Some(room_id.to_owned()),
-                            message: MatrixMessageType::Response(
-                                RoomMessageEventContent::text_html(
-                                    text_response.to_string(),
-                                    formatted_text,
-                                ),
-                            ),
-                        })
-                        .await
-                        .is_err()
-                    {
-                        return Err(anyhow!("Channel closed. Unable to send message."))?;
-                    }
+                    responses.push(RoomMessageEventContent::text_html(
+                        text_response.to_string(),
+                        formatted_text,
+                    ));
                 }
+                let mut corrected = false;
                 if config.enable_corrections
-                    && relates_to.is_none()
+                    && !is_reply(relates_to)
                     && correction_time_cooldown(&storage, room_id)
                     && !config.correction_exclusion.contains(room_id)
-                    && !notice_response.is_some()
-                    && !text_response.is_some()
+                    && responses.is_empty()
                 {
                     if let Some(v) = spellcheck(text, sender, config) {
-                        match send
-                            .send(MatrixMessage {
-                                room_id: Some(room_id.to_owned()),
-                                message: MatrixMessageType::Response(
-                                    RoomMessageEventContent::text_plain(v),
-                                ),
-                            })
-                            .await
-                        {
-                            Ok(_) => {
-                                let rw = storage.rw_transaction().unwrap();
-                                rw.insert(CorrectionTimeCooldown {
-                                    room_id: room_id.to_string(),
-                                    last_correction_time: SystemTime::now()
-                                        .duration_since(SystemTime::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs(),
-                                })
-                                .unwrap();
-                                rw.commit().unwrap();
-                            }
-                            Err(_) => Err(anyhow!("Channel closed. Unable to send message."))?,
-                        };
+                        responses.push(RoomMessageEventContent::text_plain(v));
+                        corrected = true;
                     }
                 }
+
+                // Edits are always passed on, so responses that no longer apply are removed
+                if (!responses.is_empty() || edited)
+                    && send
+                        .send(MatrixMessage {
+                            room_id: Some(room_id.to_owned()),
+                            reply_to: Some(reply_to.clone()),
+                            message: MatrixMessageType::Responses(MatrixResponsesMessage {
+                                sender: sender.to_owned(),
+                                responses,
+                                edited,
+                            }),
+                        })
+                        .await
+                        .is_err()
+                {
+                    return Err(anyhow!("Channel closed. Unable to send message."))?;
+                }
+                if corrected {
+                    let rw = storage.rw_transaction().unwrap();
+                    rw.insert(CorrectionTimeCooldown {
+                        room_id: room_id.to_string(),
+                        last_correction_time: SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs(),
+                    })
+                    .unwrap();
+                    rw.commit().unwrap();
+                }
             }
             Err(e) => {
                 Err(anyhow!("{}", e))?;
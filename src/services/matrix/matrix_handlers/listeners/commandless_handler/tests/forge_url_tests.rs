@@ -0,0 +1,141 @@
+use super::super::forge_url::{parse_forge_url, ForgeUrl};
+use crate::config::{Forge, SearchableRepo};
+use crate::forges::{RefSearch, Search};
+use reqwest::Url;
+use std::collections::HashMap;
+
+fn repos() -> HashMap<Box<str>, SearchableRepo> {
+    let mut repos = HashMap::new();
+    repos.insert(
+        "jf".into(),
+        SearchableRepo {
+            repo: "jellyfin/jellyfin".into(),
+            forge: Forge::Github,
+        },
+    );
+    repos.insert(
+        "jf-web".into(),
+        SearchableRepo {
+            repo: "jellyfin/jellyfin-web".into(),
+            forge: Forge::Github,
+        },
+    );
+    repos.insert(
+        "fj".into(),
+        SearchableRepo {
+            repo: "jellyfin/jellyfin-forgejo".into(),
+            forge: Forge::Gitea {
+                url: Url::parse("https://codeberg.org").unwrap(),
+                token: None,
+            },
+        },
+    );
+    repos.insert(
+        "gl".into(),
+        SearchableRepo {
+            repo: "jellyfin/clients/jellyfin-web".into(),
+            forge: Forge::Gitlab {
+                url: Url::parse("https://gitlab.com/").unwrap(),
+                token: None,
+            },
+        },
+    );
+    repos
+}
+
+fn search(repo: &str, number: i64) -> Search {
+    Search {
+        repo: repo.to_string(),
+        number,
+    }
+}
+
+#[test]
+fn github_issue_and_pull() {
+    let repos = repos();
+    assert_eq!(
+        Some(ForgeUrl::IssueOrPull(
+            &Forge::Github,
+            search("jellyfin/jellyfin", 12)
+        )),
+        parse_forge_url("https://github.com/jellyfin/jellyfin/issues/12", &repos)
+    );
+    assert_eq!(
+        Some(ForgeUrl::IssueOrPull(
+            &Forge::Github,
+            search("jellyfin/jellyfin-web", 1234)
+        )),
+        parse_forge_url(
+            "https://github.com/Jellyfin/Jellyfin-Web/pull/1234/files#diff-1).",
+            &repos
+        )
+    );
+}
+
+#[test]
+fn github_commit_and_release() {
+    let repos = repos();
+    assert_eq!(
+        Some(ForgeUrl::Ref(RefSearch::Commit {
+            repo: "jellyfin/jellyfin".to_string(),
+            sha: "abc1234def".to_string(),
+        })),
+        parse_forge_url(
+            "https://github.com/jellyfin/jellyfin/commit/abc1234def",
+            &repos
+        )
+    );
+    assert_eq!(
+        Some(ForgeUrl::Ref(RefSearch::Release {
+            repo: "jellyfin/jellyfin".to_string(),
+            tag: "v10.8.0".to_string(),
+        })),
+        parse_forge_url(
+            "https://github.com/jellyfin/jellyfin/releases/tag/v10.8.0",
+            &repos
+        )
+    );
+}
+
+#[test]
+fn other_forges() {
+    let repos = repos();
+    assert!(matches!(
+        parse_forge_url("https://codeberg.org/jellyfin/jellyfin-forgejo/pulls/7", &repos),
+        Some(ForgeUrl::IssueOrPull(Forge::Gitea { .. }, s)) if s == search("jellyfin/jellyfin-forgejo", 7)
+    ));
+    assert!(matches!(
+        parse_forge_url(
+            "https://gitlab.com/jellyfin/clients/jellyfin-web/-/merge_requests/56",
+            &repos
+        ),
+        Some(ForgeUrl::IssueOrPull(Forge::Gitlab { .. }, s)) if s == search("jellyfin/clients/jellyfin-web", 56)
+    ));
+}
+
+#[test]
+fn unsupported_urls() {
+    let repos = repos();
+    assert_eq!(
+        None,
+        parse_forge_url(
+            "https://github.com/jellyfin/jellyfin-tizen/issues/12",
+            &repos
+        )
+    );
+    assert_eq!(
+        None,
+        parse_forge_url("https://github.com/jellyfin/jellyfin/commit/nothex", &repos)
+    );
+    assert_eq!(
+        None,
+        parse_forge_url("https://github.com/jellyfin/jellyfin/pulls", &repos)
+    );
+    assert_eq!(
+        None,
+        parse_forge_url(
+            "https://codeberg.org/jellyfin/jellyfin-forgejo/commit/abc1234",
+            &repos
+        )
+    );
+}
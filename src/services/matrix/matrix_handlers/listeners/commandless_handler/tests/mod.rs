@@ -0,0 +1 @@
+mod forge_url_tests;
@@ -1,15 +1,17 @@
 //! Performs expansion of text in messages and builds proper response
 
 use crate::config::MatrixListenerConfig;
-use crate::helpers::{clean_text, MatrixNoticeResponse};
+use crate::helpers::{clean_text, stored_text_expansion, MatrixNoticeResponse};
 use crate::regex::TEXT_EXPANSION;
+use native_db::Database;
 use ruma::events::room::message::TextMessageEventContent;
-use tracing::{debug, error, trace};
+use tracing::{debug, trace};
 
 /// Finds and expands text plus builds response text
 pub fn text_expansion(
     text: &TextMessageEventContent,
     config: &MatrixListenerConfig,
+    storage: &Database<'_>,
     notice_response: &mut MatrixNoticeResponse,
 ) {
     let mut expanded_text: Vec<String> = Vec::new();
@@ -17,13 +19,13 @@ pub fn text_expansion(
         Some(v) => {
             let clean_text = clean_text(&v.body);
             if TEXT_EXPANSION.is_match(&clean_text) {
-                determine_expanded_text(config, &clean_text, &mut expanded_text)
+                determine_expanded_text(config, storage, &clean_text, &mut expanded_text)
             } else {
                 debug!("There are no remaining matches after cleaning tags. Doing nothing.");
                 return;
             }
         }
-        None => determine_expanded_text(config, &text.body, &mut expanded_text),
+        None => determine_expanded_text(config, storage, &text.body, &mut expanded_text),
     }
     if expanded_text.is_empty() {
         debug!("No text to send after processing.");
@@ -32,18 +34,23 @@ pub fn text_expansion(
     }
 }
 
+/// Finds expanded text for each keyword, preferring expansions edited from chat over the config file
 fn determine_expanded_text(
     config: &MatrixListenerConfig,
+    storage: &Database<'_>,
     text: &str,
     expanded_text: &mut Vec<String>,
 ) {
     for cap in TEXT_EXPANSION.captures_iter(&text.to_lowercase()) {
         trace!("{:?}", cap);
-        match config.text_expansions.get(&cap[1]) {
-            Some(v) => {
-                expanded_text.push(v.to_string());
-            }
-            None => error!("Somehow lost expanded text between regex match and insertion!"),
+        match stored_text_expansion(storage, &cap[1]) {
+            Some(v) => expanded_text.push(v.text),
+            None => match config.text_expansions.get(&cap[1]) {
+                Some(v) => {
+                    expanded_text.push(v.to_string());
+                }
+                None => debug!("No text expansion found for {}", &cap[1]),
+            },
         }
     }
 }
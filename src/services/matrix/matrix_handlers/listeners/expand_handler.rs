@@ -0,0 +1,248 @@
+//! Adds, edits, removes, and lists text expansions from chat
+
+use super::strip_command;
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::database::models::TextExpansion;
+use crate::helpers::bot_response::MatrixFormattedNoticeResponse;
+use crate::helpers::{
+    is_permitted, remove_text_expansion, save_text_expansion, stored_text_expansion,
+    stored_text_expansions,
+};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply};
+use anyhow::bail;
+use native_db::Database;
+use ruma::{
+    events::room::message::{RoomMessageEventContent, TextMessageEventContent},
+    RoomId, UserId,
+};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, trace};
+
+/// Usage shown when an expand command can not be parsed
+const USAGE: &str =
+    "Usage: !expand add kodi text | !expand edit kodi text | !expand rm kodi | !expand list";
+
+#[derive(Debug, PartialEq, Eq)]
+/// A parsed `!expand` command
+pub enum ExpandCommand<'a> {
+    /// Add a new keyword
+    Add {
+        /// Lowercase keyword
+        keyword: String,
+        /// Expanded text
+        text: &'a str,
+    },
+    /// Replace the text of an existing keyword
+    Edit {
+        /// Lowercase keyword
+        keyword: String,
+        /// Expanded text
+        text: &'a str,
+    },
+    /// Remove a keyword added from chat
+    Remove {
+        /// Lowercase keyword
+        keyword: String,
+    },
+    /// List all keywords
+    List,
+}
+
+/// Runs `!expand` commands and replies with the outcome
+///
+/// Listing is available to everyone, editing only to users allowed to edit expansions
+pub async fn expand_handler(
+    text: &TextMessageEventContent,
+    sender: &UserId,
+    room_id: &RoomId,
+    reply_to: &MatrixReply,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    trace!("Body text is: {:?}", text.body);
+    let result = match parse_expand_command(&text.body) {
+        Ok(ExpandCommand::List) => Ok(list_text_expansions(config, storage)),
+        Ok(_) if !is_permitted(sender, room_id, Capability::EditExpansions, config, storage) => {
+            debug!("Unauthorized user for editing text expansions. Skipping...");
+            return Ok(());
+        }
+        Ok(command) => {
+            debug!("Running expand command {:?}", command);
+            run_expand_command(command, sender, config, storage)
+        }
+        Err(e) => Err(vec![e, USAGE.to_string()]),
+    };
+
+    let message = match result {
+        Ok(v) => RoomMessageEventContent::notice_plain(v),
+        Err(errors) => {
+            let mut response = MatrixFormattedNoticeResponse::default();
+            response.add_errrors(errors);
+            let formatted_text = response.format_text().unwrap();
+            RoomMessageEventContent::notice_html(response.to_string(), formatted_text)
+        }
+    };
+    if send
+        .send(MatrixMessage {
+            room_id: Some(room_id.to_owned()),
+            reply_to: Some(reply_to.clone()),
+            message: MatrixMessageType::Response(message),
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed. Unable to send message.");
+    }
+    Ok(())
+}
+
+/// Runs a parsed editing command, returning a confirmation or the errors to show the user
+fn run_expand_command(
+    command: ExpandCommand<'_>,
+    sender: &UserId,
+    config: &MatrixListenerConfig,
+    storage: &Database<'_>,
+) -> Result<String, Vec<String>> {
+    let (keyword, text, confirmation) = match command {
+        ExpandCommand::Add { keyword, text } => {
+            if config.text_expansions.contains_key(keyword.as_str())
+                || stored_text_expansion(storage, &keyword).is_some()
+            {
+                return Err(vec![format!(
+                    "Text expansion ${} already exists. Use !expand edit to change it",
+                    keyword
+                )]);
+            }
+            (keyword, text, "Added")
+        }
+        ExpandCommand::Edit { keyword, text } => {
+            if !config.text_expansions.contains_key(keyword.as_str())
+                && stored_text_expansion(storage, &keyword).is_none()
+            {
+                return Err(vec![format!(
+                    "Text expansion ${} does not exist. Use !expand add to create it",
+                    keyword
+                )]);
+            }
+            (keyword, text, "Updated")
+        }
+        ExpandCommand::Remove { keyword } => {
+            return match remove_text_expansion(storage, &keyword) {
+                Ok(true) => Ok(format!("Removed text expansion ${}", keyword)),
+                Ok(false) if config.text_expansions.contains_key(keyword.as_str()) => {
+                    Err(vec![format!(
+                        "Text expansion ${} is defined in the config file and can not be removed from chat",
+                        keyword
+                    )])
+                }
+                Ok(false) => Err(vec![format!("Text expansion ${} does not exist", keyword)]),
+                Err(e) => {
+                    error!("{:#}", e);
+                    Err(vec!["Unable to remove text expansion".to_string()])
+                }
+            };
+        }
+        ExpandCommand::List => return Ok(list_text_expansions(config, storage)),
+    };
+    let expansion = TextExpansion {
+        keyword,
+        text: text.to_string(),
+        edited_by: sender.to_string(),
+        edited_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let keyword = expansion.keyword.clone();
+    match save_text_expansion(storage, expansion) {
+        Ok(_) => Ok(format!("{} text expansion ${}", confirmation, keyword)),
+        Err(e) => {
+            error!("{:#}", e);
+            Err(vec!["Unable to save text expansion".to_string()])
+        }
+    }
+}
+
+/// Lists every keyword with who last edited it from chat
+fn list_text_expansions(config: &MatrixListenerConfig, storage: &Database<'_>) -> String {
+    let stored = stored_text_expansions(storage);
+    let mut lines = stored
+        .iter()
+        .map(|e| {
+            format!(
+                "${} - edited by {} on {}",
+                e.keyword,
+                e.edited_by,
+                humantime::format_rfc3339_seconds(
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(e.edited_at)
+                )
+            )
+        })
+        .collect::<Vec<_>>();
+    for keyword in config.text_expansions.keys() {
+        if !stored.iter().any(|e| *e.keyword == **keyword) {
+            lines.push(format!("${} - from config file", keyword));
+        }
+    }
+    if lines.is_empty() {
+        return "No text expansions exist".to_string();
+    }
+    lines.sort();
+    format!("Text expansions:\n{}", lines.join("\n"))
+}
+
+/// Parses the body of an `!expand` message
+///
+/// Returns a message explaining the problem if it is not a valid command
+pub fn parse_expand_command(body: &str) -> Result<ExpandCommand<'_>, String> {
+    let rest = strip_command(body, "!expand").ok_or_else(|| "Not an expand command".to_string())?;
+    let (action, rest) = split_arg(rest).ok_or_else(|| "Missing action".to_string())?;
+    match action.to_lowercase().as_str() {
+        "add" | "edit" => {
+            let (keyword, rest) = split_arg(rest).ok_or_else(|| "Missing keyword".to_string())?;
+            let keyword = parse_keyword(keyword)?;
+            let text = rest.trim();
+            if text.is_empty() {
+                return Err("Missing text".to_string());
+            }
+            if action.eq_ignore_ascii_case("add") {
+                Ok(ExpandCommand::Add { keyword, text })
+            } else {
+                Ok(ExpandCommand::Edit { keyword, text })
+            }
+        }
+        "rm" | "remove" => {
+            let (keyword, _) = split_arg(rest).ok_or_else(|| "Missing keyword".to_string())?;
+            Ok(ExpandCommand::Remove {
+                keyword: parse_keyword(keyword)?,
+            })
+        }
+        "list" => Ok(ExpandCommand::List),
+        v => Err(format!("Unknown action {}", v)),
+    }
+}
+
+/// Lowercases a keyword, allowing an optional leading $
+///
+/// Keywords can only contain letters and numbers to match how they are found in messages
+fn parse_keyword(keyword: &str) -> Result<String, String> {
+    let keyword = keyword.strip_prefix('$').unwrap_or(keyword);
+    if keyword.is_empty() || !keyword.chars().all(|c| c.is_alphanumeric()) {
+        return Err(format!(
+            "Invalid keyword {}. Keywords can only contain letters and numbers",
+            keyword
+        ));
+    }
+    Ok(keyword.to_lowercase())
+}
+
+/// Splits off the next whitespace separated argument, returning it and the remaining text
+fn split_arg(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.split_once(char::is_whitespace).unwrap_or((text, "")))
+}
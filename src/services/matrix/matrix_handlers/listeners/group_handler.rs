@@ -0,0 +1,278 @@
+//! Creates, deletes, joins, and leaves group pings from chat
+
+use super::strip_command;
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::database::models::GroupPing;
+use crate::helpers::bot_response::MatrixFormattedNoticeResponse;
+use crate::helpers::{
+    group_members, is_permitted, remove_group_ping, save_group_ping, stored_group_ping,
+    stored_group_pings,
+};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply};
+use crate::regex::FORMATTED_USERNAME;
+use anyhow::bail;
+use native_db::Database;
+use ruma::{
+    events::room::message::{RoomMessageEventContent, TextMessageEventContent},
+    OwnedUserId, RoomId, UserId,
+};
+use std::collections::BTreeSet;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, trace};
+
+/// Usage shown when a group command can not be parsed
+const USAGE: &str = "Usage: !group join backend | !group leave backend | !group list | !group create backend | !group delete backend | !group add backend @user | !group remove backend @user";
+
+#[derive(Debug, PartialEq, Eq)]
+/// A parsed `!group` command
+pub enum GroupCommand {
+    /// Add the sender to a group
+    Join {
+        /// Lowercase group name
+        name: String,
+    },
+    /// Remove the sender from a group
+    Leave {
+        /// Lowercase group name
+        name: String,
+    },
+    /// List all groups
+    List,
+    /// Create an empty group
+    Create {
+        /// Lowercase group name
+        name: String,
+    },
+    /// Delete a group created from chat
+    Delete {
+        /// Lowercase group name
+        name: String,
+    },
+    /// Add a user to a group
+    Add {
+        /// Lowercase group name
+        name: String,
+        /// User to add
+        user: OwnedUserId,
+    },
+    /// Remove a user from a group
+    Remove {
+        /// Lowercase group name
+        name: String,
+        /// User to remove
+        user: OwnedUserId,
+    },
+}
+
+/// Runs `!group` commands and replies with the outcome
+///
+/// Joining, leaving, and listing are available to everyone, the rest only to users allowed to manage groups
+pub async fn group_handler(
+    text: &TextMessageEventContent,
+    sender: &UserId,
+    room_id: &RoomId,
+    reply_to: &MatrixReply,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    trace!("Body text is: {:?}", text.body);
+    let command = parse_group_command(&text.body, text.formatted.as_ref().map(|f| f.body.as_str()));
+    let result = match command {
+        Ok(GroupCommand::Create { .. })
+        | Ok(GroupCommand::Delete { .. })
+        | Ok(GroupCommand::Add { .. })
+        | Ok(GroupCommand::Remove { .. })
+            if !is_permitted(sender, room_id, Capability::ManageGroups, config, storage) =>
+        {
+            debug!("Unauthorized user for managing groups. Skipping...");
+            return Ok(());
+        }
+        Ok(command) => {
+            debug!("Running group command {:?}", command);
+            run_group_command(command, sender, config, storage)
+        }
+        Err(e) => Err(vec![e, USAGE.to_string()]),
+    };
+
+    let message = match result {
+        Ok(v) => RoomMessageEventContent::notice_plain(v),
+        Err(errors) => {
+            let mut response = MatrixFormattedNoticeResponse::default();
+            response.add_errrors(errors);
+            let formatted_text = response.format_text().unwrap();
+            RoomMessageEventContent::notice_html(response.to_string(), formatted_text)
+        }
+    };
+    if send
+        .send(MatrixMessage {
+            room_id: Some(room_id.to_owned()),
+            reply_to: Some(reply_to.clone()),
+            message: MatrixMessageType::Response(message),
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed. Unable to send message.");
+    }
+    Ok(())
+}
+
+/// Runs a parsed command, returning a confirmation or the errors to show the user
+fn run_group_command(
+    command: GroupCommand,
+    sender: &UserId,
+    config: &MatrixListenerConfig,
+    storage: &Database<'_>,
+) -> Result<String, Vec<String>> {
+    let (name, user, add) = match command {
+        GroupCommand::List => return Ok(list_group_pings(config, storage)),
+        GroupCommand::Create { name } => {
+            if config.group_pings.contains_key(name.as_str())
+                || stored_group_ping(storage, &name).is_some()
+            {
+                return Err(vec![format!("Group %{} already exists", name)]);
+            }
+            let group = GroupPing {
+                name: name.clone(),
+                members: Vec::new(),
+            };
+            return save(storage, group).map(|_| format!("Created group %{}", name));
+        }
+        GroupCommand::Delete { name } => {
+            if config.group_pings.contains_key(name.as_str()) {
+                return Err(vec![format!(
+                    "Group %{} is defined in the config file and can not be deleted from chat",
+                    name
+                )]);
+            }
+            return match remove_group_ping(storage, &name) {
+                Ok(true) => Ok(format!("Deleted group %{}", name)),
+                Ok(false) => Err(vec![format!("Group %{} does not exist", name)]),
+                Err(e) => {
+                    error!("{:#}", e);
+                    Err(vec!["Unable to delete group".to_string()])
+                }
+            };
+        }
+        GroupCommand::Join { name } => (name, sender.to_owned(), true),
+        GroupCommand::Leave { name } => (name, sender.to_owned(), false),
+        GroupCommand::Add { name, user } => (name, user, true),
+        GroupCommand::Remove { name, user } => (name, user, false),
+    };
+
+    let in_config = config.group_pings.get(name.as_str());
+    let mut group = match (stored_group_ping(storage, &name), in_config) {
+        (Some(v), _) => v,
+        (None, Some(_)) => GroupPing {
+            name: name.clone(),
+            members: Vec::new(),
+        },
+        (None, None) => return Err(vec![format!("Group %{} does not exist", name)]),
+    };
+    let stored = group.members.iter().any(|m| *m == user.as_str());
+    let configured = in_config.is_some_and(|g| g.contains(&user));
+    if add {
+        if stored || configured {
+            return Err(vec![format!("{} is already in group %{}", user, name)]);
+        }
+        group.members.push(user.to_string());
+        save(storage, group).map(|_| format!("Added {} to group %{}", user, name))
+    } else {
+        if configured {
+            return Err(vec![format!(
+                "{} is in group %{} in the config file and can not be removed from chat",
+                user, name
+            )]);
+        }
+        if !stored {
+            return Err(vec![format!("{} is not in group %{}", user, name)]);
+        }
+        group.members.retain(|m| *m != user.as_str());
+        save(storage, group).map(|_| format!("Removed {} from group %{}", user, name))
+    }
+}
+
+fn save(storage: &Database<'_>, group: GroupPing) -> Result<(), Vec<String>> {
+    save_group_ping(storage, group).map_err(|e| {
+        error!("{:#}", e);
+        vec!["Unable to save group".to_string()]
+    })
+}
+
+/// Lists every group with the number of users it pings, including members of aliased groups
+fn list_group_pings(config: &MatrixListenerConfig, storage: &Database<'_>) -> String {
+    let stored = stored_group_pings(storage);
+    let mut names = config
+        .group_pings
+        .keys()
+        .map(|k| k.to_string())
+        .collect::<BTreeSet<_>>();
+    names.extend(stored.iter().map(|g| g.name.clone()));
+    if names.is_empty() {
+        return "No groups exist".to_string();
+    }
+    let mut lines = Vec::new();
+    for name in names {
+        let members = group_members(&name, config, &stored);
+        lines.push(format!("%{} - {} members", name, members.len()));
+    }
+    format!("Groups:\n{}", lines.join("\n"))
+}
+
+/// Parses the body of a `!group` message, using the formatted body to find mentioned users
+///
+/// Returns a message explaining the problem if it is not a valid command
+pub fn parse_group_command(body: &str, formatted: Option<&str>) -> Result<GroupCommand, String> {
+    let mut args = strip_command(body, "!group")
+        .ok_or_else(|| "Not a group command".to_string())?
+        .split_whitespace();
+    let action = args.next().ok_or_else(|| "Missing action".to_string())?;
+    let action = action.to_lowercase();
+    if action == "list" {
+        return Ok(GroupCommand::List);
+    }
+    let name = parse_name(args.next().ok_or_else(|| "Missing group".to_string())?)?;
+    match action.as_str() {
+        "join" => Ok(GroupCommand::Join { name }),
+        "leave" => Ok(GroupCommand::Leave { name }),
+        "create" => Ok(GroupCommand::Create { name }),
+        "delete" => Ok(GroupCommand::Delete { name }),
+        "add" | "remove" => {
+            let user = args.next().ok_or_else(|| "Missing user".to_string())?;
+            let user = match UserId::parse(user) {
+                Ok(v) => v,
+                Err(_) => {
+                    debug!("Group command doesnt appear to include user, attempting formatted body parsing");
+                    formatted
+                        .and_then(|f| FORMATTED_USERNAME.captures_iter(f).next())
+                        .and_then(|c| UserId::parse(&c[0]).ok())
+                        .ok_or_else(|| format!("Invalid user {}", user))?
+                }
+            };
+            if action == "add" {
+                Ok(GroupCommand::Add { name, user })
+            } else {
+                Ok(GroupCommand::Remove { name, user })
+            }
+        }
+        v => Err(format!("Unknown action {}", v)),
+    }
+}
+
+/// Lowercases a group name, allowing an optional leading %
+///
+/// Names can only contain letters and numbers to match how they are found in messages
+fn parse_name(name: &str) -> Result<String, String> {
+    let name = name.strip_prefix('%').unwrap_or(name).to_lowercase();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric()) {
+        return Err(format!(
+            "Invalid group {}. Groups can only contain letters and numbers",
+            name
+        ));
+    }
+    if name == "all" {
+        return Err("%all is a reserved group name".to_string());
+    }
+    Ok(name)
+}
@@ -1,11 +1,14 @@
-use crate::config::MatrixListenerConfig;
+use crate::config::{Forge, MatrixListenerConfig};
 use crate::helpers::bot_response::MatrixFormattedNoticeResponse;
-use crate::messages::{MatrixMessage, MatrixMessageType};
+use crate::helpers::{group_members, stored_group_pings, stored_text_expansions};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply};
 use anyhow::bail;
+use native_db::Database;
 use ruma::{
     events::room::message::{RoomMessageEventContent, TextMessageEventContent},
     RoomId,
 };
+use std::collections::BTreeSet;
 use std::convert::From;
 use tokio::sync::mpsc::Sender;
 use tracing::{debug, trace};
@@ -16,6 +19,7 @@ enum HelpType {
     Commandless,
     GroupPing,
     GithubSearch,
+    Issue,
     Link,
     TextExpansion,
     UnitConversion,
@@ -29,6 +33,7 @@ impl From<&str> for HelpType {
             "commandless" => HelpType::Commandless,
             "ping" => HelpType::GroupPing,
             "github-search" => HelpType::GithubSearch,
+            "issue" => HelpType::Issue,
             "link" => HelpType::Link,
             "text-expansion" => HelpType::TextExpansion,
             "unit-conversion" => HelpType::UnitConversion,
@@ -40,6 +45,8 @@ impl From<&str> for HelpType {
 pub async fn help_handler(
     text: &TextMessageEventContent,
     room_id: &RoomId,
+    reply_to: &MatrixReply,
+    storage: &Database<'_>,
     config: &MatrixListenerConfig,
     send: &mut Sender<MatrixMessage>,
 ) -> anyhow::Result<()> {
@@ -50,10 +57,13 @@ pub async fn help_handler(
             Some(v) => match v {
                 HelpType::Command => message = action_command_help_message().await,
                 HelpType::Commandless => message = action_commandless_help_message().await,
-                HelpType::GroupPing => message = group_ping_help_message(config).await,
+                HelpType::GroupPing => message = group_ping_help_message(config, storage).await,
                 HelpType::GithubSearch => message = github_search_help_message(config).await,
+                HelpType::Issue => message = issue_help_message(config).await,
                 HelpType::Link => message = link_help_message(config).await,
-                HelpType::TextExpansion => message = text_expansion_help_message(config).await,
+                HelpType::TextExpansion => {
+                    message = text_expansion_help_message(config, storage).await
+                }
                 HelpType::UnitConversion => message = unit_conversion_help_message(config).await,
                 HelpType::UnknownCommand => (),
             },
@@ -66,6 +76,7 @@ pub async fn help_handler(
             if send
                 .send(MatrixMessage {
                     room_id: Some(room_id.to_owned()),
+                    reply_to: Some(reply_to.clone()),
                     message: MatrixMessageType::Response(RoomMessageEventContent::notice_plain(
                         message,
                     )),
@@ -88,6 +99,7 @@ pub async fn help_handler(
             if send
                 .send(MatrixMessage {
                     room_id: Some(room_id.to_owned()),
+                    reply_to: Some(reply_to.clone()),
                     message: MatrixMessageType::Response(RoomMessageEventContent::notice_html(
                         response.to_string(),
                         formatted_text,
@@ -126,6 +138,7 @@ ACTION TYPES:
 ACTIONS:
 \tping\t\t\tPing a group of people
 \tgithub-search\tSearch github by project and issue/PR number
+\tissue\t\t\tCreate and manage github issues
 \tlink\t\t\t\tShortcuts for linking helpful URLs
 \tunit-conversion\tConvert common conversational units",
         env!("CARGO_PKG_VERSION"),
@@ -156,28 +169,46 @@ EXAMPLES:
 ".to_string()
 }
 
-async fn group_ping_help_message(config: &MatrixListenerConfig) -> String {
-    let mut groups = Vec::new();
-    for group in config.group_pings.keys() {
-        groups.push(group);
-    }
-    groups.sort();
+async fn group_ping_help_message(config: &MatrixListenerConfig, storage: &Database<'_>) -> String {
+    // Members are listed as they would be pinged, including members of aliased groups and members added from chat
+    let stored = stored_group_pings(storage);
+    let mut names = config
+        .group_pings
+        .keys()
+        .map(|k| k.to_string())
+        .collect::<BTreeSet<_>>();
+    names.extend(stored.iter().map(|g| g.name.clone()));
     let mut available_groups = String::new();
-    for group in groups {
-        available_groups.push_str(group);
-        available_groups.push('|');
+    for group in names {
+        let users = group_members(&group, config, &stored)
+            .iter()
+            .map(|u| u.to_string())
+            .collect::<BTreeSet<_>>();
+        available_groups.push_str(&format!(
+            "\t{}: {}\n",
+            group,
+            users.into_iter().collect::<Vec<_>>().join(", ")
+        ));
     }
     available_groups.pop();
-    let available_groups = available_groups.replace('|', " | ");
     format!("Group Ping
 
 This action is only available as commandless. It will trigger on anything that matches \"%group\" where \"group\" is the group you want to ping.
 
-If the group exists and you are authorized to make a group ping, a message pinging everyone in the group will be made in a bot message.
+If the group exists and you are a member of a group in the config file or have a role allowing group pings, a message pinging everyone in the group will be made in a bot message. Joining a group from chat does not allow pinging groups.
+
+Anyone can join, leave, and list groups with the !group command. Users with a role allowing them to manage groups can also create and delete groups, and add or remove other users.
 
 USAGE:
 \tHey there %server can you look at this for me?
 \t%server
+\t!group join server
+\t!group leave server
+\t!group list
+\t!group create server
+\t!group delete server
+\t!group add server @user:homeserver.com
+\t!group remove server @user:homeserver.com
 
 AVAILABLE GROUPS:
 {}", available_groups
@@ -199,16 +230,43 @@ async fn github_search_help_message(config: &MatrixListenerConfig) -> String {
     let available_repos = available_repos.replace('|', " | ");
     format!("Github Search
 
-This action is only available as commandless. It will trigger on anything that matches \"jf#1234\" where \"jf\" is the repo you want to search and \"1234\" is the issue or PR you want to link.
+This action is only available as commandless. It will trigger on anything that matches \"jf#1234\" where \"jf\" is the repo you want to search and \"1234\" is the issue or PR you want to link. Repos can be hosted on Github, Gitea, Forgejo, or GitLab.
+
+If the repo and the number exist, it will reply with the title, state, author, labels, and a link to the issue or pull in a bot message.
 
-If the repo and the number exist, it will provide a link to the issue or pull in a bot message.
+Commits in Github repos can be searched with \"jf{}abc1234\" where \"abc1234\" is the full or abbreviated commit sha. It will reply with the commit message, author, date, and a link to the commit.
 
 USAGE:
 \tI could use a review on jf#1234
 \tjf#1234
+\tThis broke in jf{}abc1234
 
 AVAILABLE REPOS:
-{}", available_repos)
+{}", config.commit_search_sigil, config.commit_search_sigil, available_repos)
+}
+
+async fn issue_help_message(config: &MatrixListenerConfig) -> String {
+    let mut repos = config
+        .repos
+        .iter()
+        .filter(|(_, r)| r.forge == Forge::Github)
+        .map(|(k, _)| k.as_ref())
+        .collect::<Vec<_>>();
+    repos.sort_unstable();
+    format!("Issue
+
+This action is only available as a command and can only be used by users with a role allowing them to manage issues. It creates, closes, labels, and comments on issues and pulls in github repos, then replies with a link to the result.
+
+Titles and labels containing spaces must be wrapped in double quotes. Labels must already exist in the repo.
+
+USAGE:
+\t!issue new jf \"Title of the issue\" Body of the issue
+\t!issue close jf#1234
+\t!issue label jf#1234 bug \"good first issue\"
+\t!issue comment jf#1234 Text of the comment
+
+AVAILABLE REPOS:
+{}", repos.join(" | "))
 }
 
 async fn link_help_message(config: &MatrixListenerConfig) -> String {
@@ -254,15 +312,23 @@ AVAILABLE LINKS:
     ", available_keywords, available_links)
 }
 
-async fn text_expansion_help_message(config: &MatrixListenerConfig) -> String {
+async fn text_expansion_help_message(
+    config: &MatrixListenerConfig,
+    storage: &Database<'_>,
+) -> String {
     let mut keywords = Vec::new();
     for keyword in config.text_expansions.keys() {
-        keywords.push(keyword);
+        keywords.push(keyword.to_string());
+    }
+    for expansion in stored_text_expansions(storage) {
+        if !keywords.contains(&expansion.keyword) {
+            keywords.push(expansion.keyword);
+        }
     }
     keywords.sort();
     let mut available_keywords = String::new();
     for keyword in keywords {
-        available_keywords.push_str(keyword);
+        available_keywords.push_str(&keyword);
         available_keywords.push('|');
     }
     available_keywords.pop();
@@ -273,9 +339,15 @@ This action is only available as commandless. It will trigger on anything that m
 
 if the keyword exists, there will be a message containing designated expanded text provided in a bot message.
 
+Users with a role allowing them to edit expansions can add, edit, and remove keywords with the !expand command. Anyone can list keywords and who last edited them.
+
 USAGE:
 \tIf you have questions about the addon, i hope $kodi answers it for you
 \t$kodi
+\t!expand add kodi Text to expand to
+\t!expand edit kodi New text to expand to
+\t!expand rm kodi
+\t!expand list
 
 AVAILABLE KEYWORDS:
 {}
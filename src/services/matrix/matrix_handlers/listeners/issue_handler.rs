@@ -0,0 +1,236 @@
+//! Creates and manages github issues and pulls from chat
+
+use super::strip_command;
+use crate::config::{Capability, Forge, MatrixListenerConfig};
+use crate::forges::github_backend;
+use crate::helpers::{is_permitted, MatrixNoticeResponse};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply};
+use anyhow::bail;
+use native_db::Database;
+use ruma::{
+    events::room::message::{RoomMessageEventContent, TextMessageEventContent},
+    RoomId, UserId,
+};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, trace};
+
+/// Usage shown when an issue command can not be parsed
+const USAGE: &str = "Usage: !issue new jf \"title\" [body] | !issue close jf#123 | !issue label jf#123 label... | !issue comment jf#123 text";
+
+#[derive(Debug, PartialEq, Eq)]
+/// A parsed `!issue` command
+pub enum IssueCommand<'a> {
+    /// Create an issue with a title and optional body
+    New {
+        /// Short name of the repo
+        repo: String,
+        /// Title of the issue
+        title: String,
+        /// Body of the issue
+        body: Option<&'a str>,
+    },
+    /// Close an issue or pull
+    Close {
+        /// Short name of the repo
+        repo: String,
+        /// Issue or pull number
+        number: i64,
+    },
+    /// Add existing labels to an issue or pull
+    Label {
+        /// Short name of the repo
+        repo: String,
+        /// Issue or pull number
+        number: i64,
+        /// Names of the labels to add
+        labels: Vec<String>,
+    },
+    /// Comment on an issue or pull
+    Comment {
+        /// Short name of the repo
+        repo: String,
+        /// Issue or pull number
+        number: i64,
+        /// Text of the comment
+        body: &'a str,
+    },
+}
+
+/// Runs `!issue` commands for users allowed to manage issues and replies with a link to the result
+#[allow(clippy::too_many_arguments)]
+pub async fn issue_handler(
+    text: &TextMessageEventContent,
+    sender: &UserId,
+    room_id: &RoomId,
+    reply_to: &MatrixReply,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    api_client: &reqwest::Client,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    if config.repos.is_empty() {
+        trace!("No searchable repos specified, issue feature is disabled. Skipping...");
+        return Ok(());
+    }
+
+    if !is_permitted(sender, room_id, Capability::ManageIssues, config, storage) {
+        debug!("Unauthorized user for managing issues. Skipping...");
+        return Ok(());
+    }
+
+    trace!("Body text is: {:?}", text.body);
+    let mut notice_response = MatrixNoticeResponse::default();
+    match parse_issue_command(&text.body) {
+        Ok(command) => {
+            debug!("Running issue command {:?}", command);
+            match run_issue_command(command, config, api_client, storage).await {
+                Ok((links, errors)) => {
+                    notice_response.set_links(links);
+                    if !errors.is_empty() {
+                        notice_response.add_errors(errors);
+                    }
+                }
+                Err(e) => notice_response.add_errors(vec![e]),
+            }
+        }
+        Err(e) => notice_response.add_errors(vec![e, USAGE.to_string()]),
+    }
+
+    let formatted_text = notice_response.format_text().unwrap();
+    if send
+        .send(MatrixMessage {
+            room_id: Some(room_id.to_owned()),
+            reply_to: Some(reply_to.clone()),
+            message: MatrixMessageType::Response(RoomMessageEventContent::notice_html(
+                notice_response.to_string(),
+                formatted_text,
+            )),
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed. Unable to send message.");
+    }
+    Ok(())
+}
+
+/// Runs a parsed command against github, returning links to the results and errors for any parts that were skipped
+async fn run_issue_command(
+    command: IssueCommand<'_>,
+    config: &MatrixListenerConfig,
+    api_client: &reqwest::Client,
+    storage: &Database<'_>,
+) -> Result<(Vec<reqwest::Url>, Vec<String>), String> {
+    let short_name = match &command {
+        IssueCommand::New { repo, .. }
+        | IssueCommand::Close { repo, .. }
+        | IssueCommand::Label { repo, .. }
+        | IssueCommand::Comment { repo, .. } => repo,
+    };
+    let repo = match config.repos.get(short_name.as_str()) {
+        Some(v) if v.forge == Forge::Github => &v.repo,
+        Some(_) => {
+            return Err(format!(
+                "Repo {} is not on github. Only github issues can be managed",
+                short_name
+            ))
+        }
+        None => return Err(format!("Repo {} not found", short_name)),
+    };
+    let github = github_backend(config, api_client, storage);
+    let url = match command {
+        IssueCommand::New { title, body, .. } => github.create_issue(repo, &title, body).await?,
+        IssueCommand::Close { number, .. } => github.close_issue(repo, number).await?,
+        IssueCommand::Label { number, labels, .. } => {
+            let (url, missing) = github.add_labels(repo, number, &labels).await?;
+            let errors = missing
+                .into_iter()
+                .map(|l| format!("Label {} not found", l))
+                .collect();
+            return Ok((vec![url], errors));
+        }
+        IssueCommand::Comment { number, body, .. } => {
+            github.add_comment(repo, number, body).await?
+        }
+    };
+    Ok((vec![url], Vec::new()))
+}
+
+/// Parses the body of an `!issue` message
+///
+/// Returns a message explaining the problem if it is not a valid command
+pub fn parse_issue_command(body: &str) -> Result<IssueCommand<'_>, String> {
+    let rest = strip_command(body, "!issue").ok_or_else(|| "Not an issue command".to_string())?;
+    let (action, rest) = next_arg(rest).ok_or_else(|| "Missing action".to_string())?;
+    match action.to_lowercase().as_str() {
+        "new" => {
+            let (repo, rest) = next_arg(rest).ok_or_else(|| "Missing repo".to_string())?;
+            let (title, rest) = next_arg(rest).ok_or_else(|| "Missing title".to_string())?;
+            let body = rest.trim();
+            Ok(IssueCommand::New {
+                repo: repo.to_lowercase(),
+                title,
+                body: if body.is_empty() { None } else { Some(body) },
+            })
+        }
+        "close" => {
+            let (repo, number, _) = issue_arg(rest)?;
+            Ok(IssueCommand::Close { repo, number })
+        }
+        "label" => {
+            let (repo, number, mut rest) = issue_arg(rest)?;
+            let mut labels = Vec::new();
+            while let Some((label, remaining)) = next_arg(rest) {
+                labels.push(label);
+                rest = remaining;
+            }
+            if labels.is_empty() {
+                return Err("Missing label".to_string());
+            }
+            Ok(IssueCommand::Label {
+                repo,
+                number,
+                labels,
+            })
+        }
+        "comment" => {
+            let (repo, number, rest) = issue_arg(rest)?;
+            let body = rest.trim();
+            if body.is_empty() {
+                return Err("Missing comment".to_string());
+            }
+            Ok(IssueCommand::Comment { repo, number, body })
+        }
+        v => Err(format!("Unknown action {}", v)),
+    }
+}
+
+/// Parses the next argument as an issue or pull in the form of "jf#123"
+fn issue_arg(text: &str) -> Result<(String, i64, &str), String> {
+    let (arg, rest) = next_arg(text).ok_or_else(|| "Missing issue".to_string())?;
+    match arg.split_once('#') {
+        Some((repo, number)) if !repo.is_empty() => match number.parse() {
+            Ok(number) => Ok((repo.to_lowercase(), number, rest)),
+            Err(_) => Err(format!("Invalid issue number {}", number)),
+        },
+        _ => Err(format!("Invalid issue {}, expected the form jf#123", arg)),
+    }
+}
+
+/// Splits off the next whitespace separated or double quoted argument, returning it and the remaining text
+fn next_arg(text: &str) -> Option<(String, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    match text.strip_prefix('"') {
+        Some(quoted) => match quoted.split_once('"') {
+            Some((arg, rest)) => Some((arg.to_string(), rest)),
+            None => Some((quoted.to_string(), "")),
+        },
+        None => match text.split_once(char::is_whitespace) {
+            Some((arg, rest)) => Some((arg.to_string(), rest)),
+            None => Some((text.to_string(), "")),
+        },
+    }
+}
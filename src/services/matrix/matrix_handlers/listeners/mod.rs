@@ -2,23 +2,51 @@
 //!
 //! Sub modules exist for performing various processes such as unit conversion
 //! and searching github
+//!
+//! Relevant tests are in a test submodule
+
+#[cfg(test)]
+mod tests;
 
-mod ban_handler;
+mod anti_spam_handler;
 mod commandless_handler;
+mod expand_handler;
+mod group_handler;
 mod help_handler;
+mod issue_handler;
+mod moderation_handler;
+mod modlog_handler;
+mod policy_handler;
 mod unit_conversion_handler;
+mod welcome_handler;
 
-use self::ban_handler::ban_handler;
+pub use self::anti_spam_handler::{anti_spam_handler, SpamTracker};
 use self::commandless_handler::commandless_handler;
+use self::expand_handler::expand_handler;
+use self::group_handler::group_handler;
 use self::help_handler::help_handler;
+use self::issue_handler::issue_handler;
+use self::moderation_handler::{is_moderation_command, moderation_handler};
+use self::modlog_handler::modlog_handler;
+pub use self::policy_handler::{policy_handler, policy_join_handler};
 use self::unit_conversion_handler::unit_conversion_handler;
-use crate::config::MatrixListenerConfig;
-use crate::messages::{MatrixInviteMessage, MatrixInviteType, MatrixMessage, MatrixMessageType};
-use anyhow::bail;
+pub use self::welcome_handler::{welcome_handler, welcome_join_handler, WelcomeQueue};
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::helpers::is_permitted;
+use crate::messages::{
+    MatrixInviteMessage, MatrixInviteType, MatrixMessage, MatrixMessageType, MatrixReply,
+    MatrixResponsesMessage,
+};
+use crate::services::matrix::MatrixClient;
+use anyhow::{bail, Context};
 use native_db::Database;
 use ruma::{
-    events::room::message::{Relation, TextMessageEventContent},
-    RoomId, UserId,
+    api::client::room::get_room_event,
+    events::{
+        room::message::{Relation, TextMessageEventContent},
+        AnyMessageLikeEvent, AnyTimelineEvent, MessageLikeEvent,
+    },
+    EventId, OwnedUserId, RoomId, UserId,
 };
 use tokio::sync::mpsc::Sender;
 use tracing::{debug, trace};
@@ -27,6 +55,7 @@ use tracing::{debug, trace};
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_text_event(
     text: &TextMessageEventContent,
+    event_id: &EventId,
     relates_to: Option<&Relation>,
     sender: &UserId,
     room_id: &RoomId,
@@ -35,36 +64,174 @@ pub async fn handle_text_event(
     api_client: &reqwest::Client,
     send: &mut Sender<MatrixMessage>,
 ) -> anyhow::Result<()> {
+    let reply_to = &reply_target(event_id, relates_to, room_id, config);
     if !&text.body.starts_with('!') {
         debug!("Entering no command path...");
         commandless_handler(
-            text, relates_to, sender, room_id, storage, config, api_client, send,
+            text, relates_to, sender, room_id, reply_to, false, storage, config, api_client, send,
         )
         .await?
     } else if text.body.to_lowercase().starts_with("!convert ") {
         debug!("Entering unit conversion path...");
-        unit_conversion_handler(text, relates_to, room_id, send).await?
+        unit_conversion_handler(text, relates_to, room_id, reply_to, send).await?
     } else if text.body.to_lowercase().starts_with("!help") {
         debug!("Entering help path...");
-        help_handler(text, room_id, config, send).await?
-    } else if text.body.to_lowercase().starts_with("!ban") {
-        debug!("Entering help path...");
-        ban_handler(text, config, sender, send).await?;
+        help_handler(text, room_id, reply_to, storage, config, send).await?
+    } else if text.body.to_lowercase().starts_with("!modlog") {
+        debug!("Entering modlog path...");
+        modlog_handler(text, sender, room_id, reply_to, storage, config, send).await?
+    } else if is_moderation_command(&text.body) {
+        debug!("Entering moderation path...");
+        moderation_handler(text, sender, room_id, reply_to, storage, config, send).await?
+    } else if strip_command(&text.body, "!expand").is_some() {
+        debug!("Entering expand path...");
+        expand_handler(text, sender, room_id, reply_to, storage, config, send).await?
+    } else if strip_command(&text.body, "!group").is_some() {
+        debug!("Entering group path...");
+        group_handler(text, sender, room_id, reply_to, storage, config, send).await?
+    } else if strip_command(&text.body, "!issue").is_some() {
+        debug!("Entering issue path...");
+        issue_handler(
+            text, sender, room_id, reply_to, storage, config, api_client, send,
+        )
+        .await?
     } else {
         debug!("Doing nothing...");
     }
     Ok(())
 }
 
+/// Handles the new content of an edited message, updating or removing the responses to the original message
+///
+/// Only the commandless handlers are run again. Edits by anyone but the sender of the original message are ignored
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_edit_event(
+    text: &TextMessageEventContent,
+    event_id: &EventId,
+    sender: &UserId,
+    room_id: &RoomId,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    api_client: &reqwest::Client,
+    client: &MatrixClient,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    let (original_sender, relates_to) = original_message(event_id, room_id, client).await?;
+    if original_sender != sender {
+        debug!("Edit of {} is not from its sender, ignoring", event_id);
+        return Ok(());
+    }
+    let reply_to = &reply_target(event_id, relates_to.as_ref(), room_id, config);
+    if !text.body.starts_with('!') {
+        debug!("Entering edited no command path...");
+        commandless_handler(
+            text,
+            relates_to.as_ref(),
+            sender,
+            room_id,
+            reply_to,
+            true,
+            storage,
+            config,
+            api_client,
+            send,
+        )
+        .await
+    } else {
+        debug!("Edited message is a command, removing earlier responses...");
+        let message = MatrixMessageType::Responses(MatrixResponsesMessage {
+            sender: sender.to_owned(),
+            responses: Vec::new(),
+            edited: true,
+        });
+        if send
+            .send(MatrixMessage {
+                room_id: Some(room_id.to_owned()),
+                reply_to: Some(reply_to.clone()),
+                message,
+            })
+            .await
+            .is_err()
+        {
+            bail!("Channel closed, unable to send mesage.");
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the sender and relation of an edited message, as the edit only carries its new content
+async fn original_message(
+    event_id: &EventId,
+    room_id: &RoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<(OwnedUserId, Option<Relation>)> {
+    let response = client
+        .send_request(get_room_event::v3::Request::new(room_id, event_id))
+        .await
+        .with_context(|| format!("Unable to get event {} in room {}", event_id, room_id))?;
+    match response
+        .event
+        .deserialize()
+        .with_context(|| format!("Invalid event {}", event_id))?
+    {
+        AnyTimelineEvent::MessageLike(AnyMessageLikeEvent::RoomMessage(
+            MessageLikeEvent::Original(e),
+        )) => Ok((e.sender, e.content.relates_to)),
+        _ => bail!("Edited event {} is not a message", event_id),
+    }
+}
+
+/// Returns the event responses answer, starting a thread from it in rooms where every response is sent in a thread
+fn reply_target(
+    event_id: &EventId,
+    relates_to: Option<&Relation>,
+    room_id: &RoomId,
+    config: &MatrixListenerConfig,
+) -> MatrixReply {
+    if config.thread_rooms.contains(room_id) {
+        MatrixReply::threaded(event_id, relates_to)
+    } else {
+        MatrixReply::new(event_id, relates_to)
+    }
+}
+
+/// Returns the arguments of a command if the message is that command, ignoring case
+///
+/// The command must be followed by whitespace or end the message, so `!issue` does not match `!issues`
+pub(super) fn strip_command<'a>(body: &'a str, command: &str) -> Option<&'a str> {
+    let body = body.trim_start();
+    let rest = body.get(command.len()..)?;
+    if body[..command.len()].eq_ignore_ascii_case(command)
+        && (rest.is_empty() || rest.starts_with(char::is_whitespace))
+    {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// Returns true if the message is a reply, whose body quotes the message it replies to
+///
+/// Messages sent in a thread are only replies if they answer a specific message in it.
+/// Messages without a relation are deserialized with a custom one, so only known relations count
+fn is_reply(relates_to: Option<&Relation>) -> bool {
+    match relates_to {
+        Some(Relation::Reply { .. }) | Some(Relation::Replacement(_)) => true,
+        Some(Relation::Thread(t)) => !t.is_falling_back,
+        _ => false,
+    }
+}
+
 /// Accepts or rejects invites to rooms from matrix users
 pub async fn handle_invite_event(
     sender: &UserId,
     room_id: &RoomId,
+    storage: &Database<'_>,
     config: &MatrixListenerConfig,
     send: &mut Sender<MatrixMessage>,
 ) -> anyhow::Result<()> {
     trace!("Invited by {} to room {} ", &sender, &room_id);
-    if config.admins.contains(sender) {
+    if is_permitted(sender, room_id, Capability::Invite, config, storage) {
         let message = MatrixInviteMessage {
             kind: MatrixInviteType::Accept,
             sender: sender.to_owned(),
@@ -72,6 +239,7 @@ pub async fn handle_invite_event(
         if send
             .send(MatrixMessage {
                 room_id: Some(room_id.to_owned()),
+                reply_to: None,
                 message: MatrixMessageType::Invite(message),
             })
             .await
@@ -87,6 +255,7 @@ pub async fn handle_invite_event(
         if send
             .send(MatrixMessage {
                 room_id: Some(room_id.to_owned()),
+                reply_to: None,
                 message: MatrixMessageType::Invite(message),
             })
             .await
@@ -0,0 +1,399 @@
+//! Bans, kicks, mutes, and redacts from chat
+//!
+//! Actions against users apply to all ban rooms, or the current room if no ban rooms are configured
+
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::helpers::bot_response::MatrixFormattedNoticeResponse;
+use crate::helpers::is_permitted;
+use crate::messages::{
+    MatrixBanMessage, MatrixKickMessage, MatrixMessage, MatrixMessageType, MatrixMuteMessage,
+    MatrixPurgeMessage, MatrixRedactMessage, MatrixReply, MatrixUnbanMessage, MatrixUnmuteMessage,
+};
+use crate::regex::{FORMATTED_PILL, FORMATTED_USERNAME};
+use anyhow::bail;
+use native_db::Database;
+use ruma::{
+    events::room::message::{RoomMessageEventContent, TextMessageEventContent},
+    EventId, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, UserId,
+};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, trace};
+
+/// Most messages that can be redacted with a single purge
+const MAX_PURGE_COUNT: u32 = 100;
+
+/// Longest duration of a timed ban or mute, longer bans should be permanent
+const MAX_DURATION: Duration = Duration::from_secs(10 * 365 * 24 * 60 * 60);
+
+/// Commands handled here paired with the capability required to use them
+const COMMANDS: [(&str, Capability); 7] = [
+    ("!ban", Capability::Ban),
+    ("!unban", Capability::Ban),
+    ("!kick", Capability::Kick),
+    ("!mute", Capability::Mute),
+    ("!unmute", Capability::Mute),
+    ("!redact", Capability::Redact),
+    ("!purge", Capability::Redact),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+/// A parsed moderation command
+pub enum ModerationCommand {
+    /// Ban a user
+    Ban {
+        /// User to ban
+        user: OwnedUserId,
+        /// How long until the user is unbanned, forever if not supplied
+        duration: Option<Duration>,
+        /// Reason shown in the room
+        reason: Option<String>,
+    },
+    /// Unban a user
+    Unban {
+        /// User to unban
+        user: OwnedUserId,
+        /// Reason shown in the room
+        reason: Option<String>,
+    },
+    /// Kick a user
+    Kick {
+        /// User to kick
+        user: OwnedUserId,
+        /// Reason shown in the room
+        reason: Option<String>,
+    },
+    /// Lower the power level of a user so they can not send messages
+    Mute {
+        /// User to mute
+        user: OwnedUserId,
+        /// How long until the user is unmuted, forever if not supplied
+        duration: Option<Duration>,
+    },
+    /// Reset the power level of a user to the room default
+    Unmute {
+        /// User to unmute
+        user: OwnedUserId,
+    },
+    /// Redact a single event in the current room
+    Redact {
+        /// Event to redact
+        event_id: OwnedEventId,
+        /// Reason shown in the room
+        reason: Option<String>,
+    },
+    /// Redact the latest messages of a user
+    Purge {
+        /// User whose messages will be redacted
+        user: OwnedUserId,
+        /// Number of messages to redact in each room
+        count: u32,
+    },
+}
+
+/// Returns true if the message is a moderation command
+pub fn is_moderation_command(body: &str) -> bool {
+    command_capability(body).is_some()
+}
+
+/// Runs moderation commands for users allowed to use them
+///
+/// Replies with the problem if the command can not be parsed
+pub async fn moderation_handler(
+    text: &TextMessageEventContent,
+    sender: &UserId,
+    room_id: &RoomId,
+    reply_to: &MatrixReply,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    let capability = match command_capability(&text.body) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    if !is_permitted(sender, room_id, capability, config, storage) {
+        debug!("Unauthorized user for moderation. Skipping...");
+        return Ok(());
+    }
+
+    trace!("Body text is: {:?}", text.body);
+    let command =
+        parse_moderation_command(&text.body, text.formatted.as_ref().map(|f| f.body.as_str()));
+    let (room_id, reply_to, message) = match command {
+        Ok(command) => {
+            debug!("Running moderation command {:?}", command);
+            let rooms = target_rooms(config, room_id);
+            (
+                None,
+                None,
+                moderation_message(command, sender, room_id, rooms),
+            )
+        }
+        Err(e) => {
+            let mut response = MatrixFormattedNoticeResponse::default();
+            response.add_errrors(vec![e]);
+            let formatted_text = response.format_text().unwrap();
+            (
+                Some(room_id.to_owned()),
+                Some(reply_to.clone()),
+                MatrixMessageType::Response(RoomMessageEventContent::notice_html(
+                    response.to_string(),
+                    formatted_text,
+                )),
+            )
+        }
+    };
+    if send
+        .send(MatrixMessage {
+            room_id,
+            reply_to,
+            message,
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed, unable to send mesage.");
+    }
+    Ok(())
+}
+
+/// Builds the message that performs the command in the supplied rooms
+fn moderation_message(
+    command: ModerationCommand,
+    sender: &UserId,
+    room_id: &RoomId,
+    rooms: HashSet<OwnedRoomId>,
+) -> MatrixMessageType {
+    let sender = sender.to_owned();
+    match command {
+        ModerationCommand::Ban {
+            user,
+            duration,
+            reason,
+        } => MatrixMessageType::Ban(MatrixBanMessage {
+            sender,
+            user,
+            duration,
+            reason,
+            rooms,
+            // Timed bans are lifted here, so only permanent bans are shared
+            publish: duration.is_none(),
+        }),
+        ModerationCommand::Unban { user, reason } => MatrixMessageType::Unban(MatrixUnbanMessage {
+            sender,
+            user,
+            reason,
+            rooms,
+            publish: true,
+        }),
+        ModerationCommand::Kick { user, reason } => MatrixMessageType::Kick(MatrixKickMessage {
+            sender,
+            user,
+            reason,
+            rooms,
+        }),
+        ModerationCommand::Mute { user, duration } => MatrixMessageType::Mute(MatrixMuteMessage {
+            sender,
+            user,
+            duration,
+            reason: None,
+            rooms,
+        }),
+        ModerationCommand::Unmute { user } => MatrixMessageType::Unmute(MatrixUnmuteMessage {
+            sender,
+            user,
+            reason: None,
+            rooms,
+        }),
+        ModerationCommand::Redact { event_id, reason } => {
+            MatrixMessageType::Redact(MatrixRedactMessage {
+                sender,
+                room_id: room_id.to_owned(),
+                event_id,
+                reason,
+            })
+        }
+        ModerationCommand::Purge { user, count } => MatrixMessageType::Purge(MatrixPurgeMessage {
+            sender,
+            user,
+            count,
+            rooms,
+        }),
+    }
+}
+
+/// Returns the ban rooms, or the current room if there are none
+pub(super) fn target_rooms(
+    config: &MatrixListenerConfig,
+    room_id: &RoomId,
+) -> HashSet<OwnedRoomId> {
+    if config.ban_rooms.is_empty() {
+        HashSet::from([room_id.to_owned()])
+    } else {
+        config.ban_rooms.clone()
+    }
+}
+
+/// Returns the capability needed for the command the message starts with
+fn command_capability(body: &str) -> Option<Capability> {
+    let command = body.split_whitespace().next()?.to_lowercase();
+    COMMANDS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, capability)| *capability)
+}
+
+/// Parses the body of a moderation message, using the formatted body to find mentioned users
+///
+/// Returns a message explaining the problem if it is not a valid command
+pub fn parse_moderation_command(
+    body: &str,
+    formatted: Option<&str>,
+) -> Result<ModerationCommand, String> {
+    let args = body.split_whitespace().collect::<Vec<_>>();
+    let action = args.first().map(|v| v.to_lowercase()).unwrap_or_default();
+    let reason = |skip: usize| {
+        let reason = args
+            .iter()
+            .skip(skip)
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if reason.trim().is_empty() {
+            None
+        } else {
+            Some(reason.trim().to_string())
+        }
+    };
+    match action.as_str() {
+        "!ban" => {
+            let user = parse_user(&args, formatted, "!ban @user [duration] [reason]")?;
+            let next = 1 + user_word_count(&args, formatted, &user);
+            // Reasons can start with a number, so only a word that is a valid duration makes the ban timed
+            let duration = args
+                .get(next)
+                .and_then(|v| humantime::parse_duration(v).ok());
+            check_duration(duration)?;
+            Ok(ModerationCommand::Ban {
+                user,
+                duration,
+                reason: reason(if duration.is_some() { next + 1 } else { next }),
+            })
+        }
+        "!unban" => {
+            let user = parse_user(&args, formatted, "!unban @user [reason]")?;
+            let next = 1 + user_word_count(&args, formatted, &user);
+            Ok(ModerationCommand::Unban {
+                user,
+                reason: reason(next),
+            })
+        }
+        "!kick" => {
+            let user = parse_user(&args, formatted, "!kick @user [reason]")?;
+            let next = 1 + user_word_count(&args, formatted, &user);
+            Ok(ModerationCommand::Kick {
+                user,
+                reason: reason(next),
+            })
+        }
+        "!mute" => {
+            let user = parse_user(&args, formatted, "!mute @user [duration]")?;
+            let next = 1 + user_word_count(&args, formatted, &user);
+            let duration = match args.get(next..).unwrap_or_default() {
+                [] => None,
+                [v] => {
+                    Some(humantime::parse_duration(v).map_err(|_| {
+                        format!("Invalid duration {}, expected the form 10m or 1h", v)
+                    })?)
+                }
+                _ => return Err("Too many arguments. Usage: !mute @user [duration]".to_string()),
+            };
+            check_duration(duration)?;
+            Ok(ModerationCommand::Mute { user, duration })
+        }
+        "!unmute" => Ok(ModerationCommand::Unmute {
+            user: parse_user(&args, formatted, "!unmute @user")?,
+        }),
+        "!redact" => {
+            let event_id = args
+                .get(1)
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| "Missing event. Usage: !redact $event [reason]".to_string())?;
+            let event_id =
+                EventId::parse(*event_id).map_err(|_| format!("Invalid event {}", event_id))?;
+            Ok(ModerationCommand::Redact {
+                event_id,
+                reason: reason(2),
+            })
+        }
+        "!purge" => {
+            let user = parse_user(&args, formatted, "!purge @user count")?;
+            let count = match args.len() {
+                0..=2 => return Err("Missing count. Usage: !purge @user count".to_string()),
+                _ => args[args.len() - 1],
+            };
+            match count.parse() {
+                Ok(v) if (1..=MAX_PURGE_COUNT).contains(&v) => {
+                    Ok(ModerationCommand::Purge { user, count: v })
+                }
+                _ => Err(format!(
+                    "Invalid count {}, expected a number from 1 to {}",
+                    count, MAX_PURGE_COUNT
+                )),
+            }
+        }
+        v => Err(format!("Unknown action {}", v)),
+    }
+}
+
+/// Parses the user from the second word of the message, falling back to the first user mentioned in the formatted body
+pub(super) fn parse_user(
+    args: &[&str],
+    formatted: Option<&str>,
+    usage: &str,
+) -> Result<OwnedUserId, String> {
+    let user = match args.get(1) {
+        Some(v) if !v.is_empty() => v,
+        _ => return Err(format!("Missing user. Usage: {}", usage)),
+    };
+    trace!("Attempting parse of plain body text");
+    match UserId::parse(*user) {
+        Ok(u) => Ok(u),
+        Err(_) => {
+            debug!("Command doesnt appear to include user, attempting formatted body parsing");
+            trace!("Formatted body text is: {:?}", formatted);
+            formatted
+                .and_then(|f| FORMATTED_USERNAME.captures_iter(f).next())
+                .and_then(|c| UserId::parse(&c[0]).ok())
+                .ok_or_else(|| format!("Invalid user {}", user))
+        }
+    }
+}
+
+/// Rejects durations too long to be scheduled
+fn check_duration(duration: Option<Duration>) -> Result<(), String> {
+    match duration {
+        Some(d) if d > MAX_DURATION => Err(format!(
+            "Duration {} is too long, expected at most {}",
+            humantime::format_duration(d),
+            humantime::format_duration(MAX_DURATION)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Returns how many words of the body the user takes up, which is more than one for pills with a display name containing spaces
+fn user_word_count(args: &[&str], formatted: Option<&str>, user: &UserId) -> usize {
+    if args.get(1) == Some(&user.as_str()) {
+        return 1;
+    }
+    formatted
+        .and_then(|f| {
+            FORMATTED_PILL
+                .captures_iter(f)
+                .find(|c| &c[1] == user.as_str())
+        })
+        .map_or(1, |c| c[2].split_whitespace().count().max(1))
+}
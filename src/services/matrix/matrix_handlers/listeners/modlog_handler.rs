@@ -0,0 +1,89 @@
+//! Shows the moderation history of a user
+
+use super::moderation_handler::parse_user;
+use crate::config::{Capability, MatrixListenerConfig};
+use crate::helpers::bot_response::MatrixFormattedNoticeResponse;
+use crate::helpers::{is_permitted, stored_moderation_records};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply};
+use anyhow::bail;
+use native_db::Database;
+use ruma::{
+    events::room::message::{RoomMessageEventContent, TextMessageEventContent},
+    RoomId, UserId,
+};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, trace};
+
+/// Most records shown for a single user
+const MODLOG_LIMIT: usize = 20;
+
+/// Replies with the latest moderation actions against a user for users allowed to view them
+pub async fn modlog_handler(
+    text: &TextMessageEventContent,
+    sender: &UserId,
+    room_id: &RoomId,
+    reply_to: &MatrixReply,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    if !is_permitted(sender, room_id, Capability::ViewModlog, config, storage) {
+        debug!("Unauthorized user for viewing the moderation log. Skipping...");
+        return Ok(());
+    }
+
+    trace!("Body text is: {:?}", text.body);
+    let args = text.body.split_whitespace().collect::<Vec<_>>();
+    let message = match parse_user(
+        &args,
+        text.formatted.as_ref().map(|f| f.body.as_str()),
+        "!modlog @user",
+    ) {
+        Ok(user) => {
+            let records = stored_moderation_records(storage, user.as_str(), MODLOG_LIMIT);
+            if records.is_empty() {
+                RoomMessageEventContent::notice_plain(format!(
+                    "No moderation actions recorded against {}",
+                    user
+                ))
+            } else {
+                let lines = records
+                    .iter()
+                    .map(|r| {
+                        format!(
+                            "{} {}",
+                            humantime::format_rfc3339_seconds(
+                                SystemTime::UNIX_EPOCH + Duration::from_secs(r.timestamp)
+                            ),
+                            r
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                RoomMessageEventContent::notice_plain(format!(
+                    "Moderation log for {}:\n{}",
+                    user,
+                    lines.join("\n")
+                ))
+            }
+        }
+        Err(e) => {
+            let mut response = MatrixFormattedNoticeResponse::default();
+            response.add_errrors(vec![e]);
+            let formatted_text = response.format_text().unwrap();
+            RoomMessageEventContent::notice_html(response.to_string(), formatted_text)
+        }
+    };
+    if send
+        .send(MatrixMessage {
+            room_id: Some(room_id.to_owned()),
+            reply_to: Some(reply_to.clone()),
+            message: MatrixMessageType::Response(message),
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed. Unable to send message.");
+    }
+    Ok(())
+}
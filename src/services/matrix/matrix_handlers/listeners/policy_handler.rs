@@ -0,0 +1,181 @@
+//! Applies ban rules read from policy rooms to the ban rooms
+//!
+//! Rules are stored so users matching them are also banned when they join a ban room later
+
+use crate::config::MatrixListenerConfig;
+use crate::database::models::{PolicyRule, PolicyRuleKind};
+use crate::helpers::{
+    is_moderator, listed_moderators, matching_policy_rule, policy_rule_matches, remove_policy_rule,
+    save_policy_rule,
+};
+use crate::messages::{MatrixBanMessage, MatrixMessage, MatrixMessageType, MatrixPolicyBanMessage};
+use anyhow::bail;
+use native_db::Database;
+use ruma::{serde::Raw, OwnedUserId, RoomId, UserId};
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info, warn};
+
+/// Recommendation of a rule that bans the entity
+const BAN_RECOMMENDATION: &str = "m.ban";
+
+#[derive(Debug, Deserialize)]
+/// A policy rule state event
+///
+/// Deserialized by hand because removed rules are sent with empty content
+struct PolicyEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    state_key: String,
+    sender: OwnedUserId,
+    content: PolicyEventContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolicyEventContent {
+    entity: Option<String>,
+    recommendation: Option<String>,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Stores ban rules set in policy rooms and bans the users they match in the ban rooms
+///
+/// Rules that are removed or no longer recommend a ban are forgotten, but users they banned stay banned.
+/// Rules sent by the bot are bans it published and already applied, so they are ignored
+pub async fn policy_handler<T>(
+    raw_event: &Raw<T>,
+    room_id: &RoomId,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    if !config.policy_rooms.contains(room_id) {
+        return Ok(());
+    }
+    let event = match raw_event.deserialize_as::<PolicyEvent>() {
+        Ok(v) => v,
+        Err(_) => return Ok(()),
+    };
+    let kind = match event.event_type.as_str() {
+        "m.policy.rule.user" => PolicyRuleKind::User,
+        "m.policy.rule.server" => PolicyRuleKind::Server,
+        _ => return Ok(()),
+    };
+    let entity = match (event.content.entity, event.content.recommendation) {
+        (Some(entity), Some(recommendation)) if recommendation == BAN_RECOMMENDATION => entity,
+        _ => {
+            debug!(
+                "Policy rule {} in {} removed or not a ban",
+                event.state_key, room_id
+            );
+            return remove_policy_rule(
+                storage,
+                &PolicyRule::id(room_id.as_str(), kind, &event.state_key),
+            );
+        }
+    };
+    let rule = PolicyRule::new(
+        room_id.as_str(),
+        kind,
+        &event.state_key,
+        entity,
+        event.content.reason,
+    );
+    if event.sender == config.mx_uname {
+        debug!(
+            "Ignoring policy rule {} in {} as it was published by the bot",
+            rule.entity, room_id
+        );
+        return Ok(());
+    }
+    if policy_rule_matches(&rule, &config.mx_uname) {
+        warn!(
+            "Ignoring policy rule {} in {} as it would ban the bot",
+            rule.entity, room_id
+        );
+        return Ok(());
+    }
+    if !save_policy_rule(storage, rule.clone())? || config.ban_rooms.is_empty() {
+        return Ok(());
+    }
+
+    info!("Applying policy rule {} from {}", rule.entity, room_id);
+    let message = MatrixMessageType::PolicyBan(MatrixPolicyBanMessage {
+        sender: event.sender,
+        policy_room: room_id.to_owned(),
+        rule,
+        rooms: config.ban_rooms.clone(),
+        exempt: listed_moderators(config),
+    });
+    if send
+        .send(MatrixMessage {
+            room_id: None,
+            reply_to: None,
+            message,
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed, unable to send mesage.");
+    }
+    Ok(())
+}
+
+/// Bans a user joining a ban room if they match a rule stored from one of the policy rooms, unless they are a moderator
+pub async fn policy_join_handler(
+    user: &UserId,
+    room_id: &RoomId,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    if config.policy_rooms.is_empty() || !config.ban_rooms.contains(room_id) {
+        return Ok(());
+    }
+    let rule = match matching_policy_rule(storage, &config.policy_rooms, user) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    if is_moderator(user, room_id, config, storage) {
+        info!(
+            "Moderator {} joined {} and matches policy rule {}, not banning",
+            user, room_id, rule.entity
+        );
+        return Ok(());
+    }
+
+    info!(
+        "User {} joined {} and matches policy rule {}",
+        user, room_id, rule.entity
+    );
+    let message = MatrixMessageType::Ban(MatrixBanMessage {
+        sender: config.mx_uname.clone(),
+        user: user.to_owned(),
+        duration: None,
+        reason: Some(policy_reason(&rule)),
+        rooms: config.ban_rooms.clone(),
+        publish: false,
+    });
+    if send
+        .send(MatrixMessage {
+            room_id: None,
+            reply_to: None,
+            message,
+        })
+        .await
+        .is_err()
+    {
+        bail!("Channel closed, unable to send mesage.");
+    }
+    Ok(())
+}
+
+/// Describes the rule a user was banned by
+fn policy_reason(rule: &PolicyRule) -> String {
+    if rule.reason.is_empty() {
+        format!("matched policy rule {}", rule.entity)
+    } else {
+        format!("matched policy rule {}: {}", rule.entity, rule.reason)
+    }
+}
@@ -0,0 +1,156 @@
+use super::super::anti_spam_handler::SpamTracker;
+use crate::config::{AntiSpam, BlockedDomains, SpamAction, SpamLimit};
+use ruma::{RoomId, UserId};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+fn rules() -> AntiSpam {
+    AntiSpam {
+        rooms: HashSet::new(),
+        mute_duration: None,
+        flood: Some(SpamLimit {
+            limit: 3,
+            period: Duration::from_secs(10),
+            action: SpamAction::Mute,
+        }),
+        mentions: Some(SpamLimit {
+            limit: 3,
+            period: Duration::ZERO,
+            action: SpamAction::Redact,
+        }),
+        repeats: Some(SpamLimit {
+            limit: 2,
+            period: Duration::from_secs(60),
+            action: SpamAction::Ban,
+        }),
+        blocked_domains: Some(BlockedDomains {
+            domains: HashSet::from(["spam.example".into()]),
+            action: SpamAction::Warn,
+        }),
+    }
+}
+
+#[test]
+fn flood() {
+    let rules = rules();
+    let mut tracker = SpamTracker::default();
+    let user = UserId::parse("@user1:matrix.org").unwrap();
+    let room = RoomId::parse("!room:matrix.org").unwrap();
+    let other_room = RoomId::parse("!other:matrix.org").unwrap();
+    let start = Instant::now();
+    let check = |tracker: &mut SpamTracker, room: &RoomId, body: &str, secs: u64| {
+        tracker
+            .check(
+                &rules,
+                &user,
+                room,
+                body,
+                None,
+                start + Duration::from_secs(secs),
+            )
+            .map(|(action, _)| action)
+    };
+
+    assert_eq!(None, check(&mut tracker, &room, "one", 0));
+    assert_eq!(None, check(&mut tracker, &other_room, "two", 1));
+    assert_eq!(None, check(&mut tracker, &room, "three", 2));
+    // The first message is outside the period by now
+    assert_eq!(None, check(&mut tracker, &room, "four", 11));
+    assert_eq!(
+        Some(SpamAction::Mute),
+        check(&mut tracker, &room, "five", 12)
+    );
+    // Flagged messages are forgotten
+    assert_eq!(None, check(&mut tracker, &room, "six", 13));
+}
+
+#[test]
+fn repeats() {
+    let rules = rules();
+    let mut tracker = SpamTracker::default();
+    let user = UserId::parse("@user1:matrix.org").unwrap();
+    let room = RoomId::parse("!room:matrix.org").unwrap();
+    let other_room = RoomId::parse("!other:matrix.org").unwrap();
+    let start = Instant::now();
+
+    assert!(tracker
+        .check(&rules, &user, &room, "buy now", None, start)
+        .is_none());
+    assert_eq!(
+        Some(SpamAction::Ban),
+        tracker
+            .check(
+                &rules,
+                &user,
+                &other_room,
+                "buy now ",
+                None,
+                start + Duration::from_secs(30)
+            )
+            .map(|(action, _)| action)
+    );
+}
+
+#[test]
+fn mentions() {
+    let rules = rules();
+    let mut tracker = SpamTracker::default();
+    let user = UserId::parse("@user1:matrix.org").unwrap();
+    let room = RoomId::parse("!room:matrix.org").unwrap();
+
+    assert!(tracker
+        .check(
+            &rules,
+            &user,
+            &room,
+            "@a:matrix.org @a:matrix.org @b:matrix.org",
+            None,
+            Instant::now()
+        )
+        .is_none());
+    assert_eq!(
+        Some(SpamAction::Redact),
+        tracker
+            .check(
+                &rules,
+                &user,
+                &room,
+                "a, b, c",
+                Some("<a href=\"https://matrix.to/#/@a:matrix.org\">a</a>, <a href=\"https://matrix.to/#/@b:matrix.org\">b</a>, <a href=\"https://matrix.to/#/@c:matrix.org\">c</a>"),
+                Instant::now()
+            )
+            .map(|(action, _)| action)
+    );
+}
+
+#[test]
+fn blocked_domains() {
+    let rules = rules();
+    let mut tracker = SpamTracker::default();
+    let user = UserId::parse("@user1:matrix.org").unwrap();
+    let room = RoomId::parse("!room:matrix.org").unwrap();
+
+    assert_eq!(
+        Some(SpamAction::Warn),
+        tracker
+            .check(
+                &rules,
+                &user,
+                &room,
+                "free https://WWW.Spam.example/win",
+                None,
+                Instant::now()
+            )
+            .map(|(action, _)| action)
+    );
+    assert!(tracker
+        .check(
+            &rules,
+            &user,
+            &room,
+            "see https://notspam.example",
+            None,
+            Instant::now()
+        )
+        .is_none());
+}
@@ -0,0 +1,98 @@
+use super::super::handle_edit_event;
+use crate::messages::{MatrixMessage, MatrixMessageType};
+use crate::services::matrix::MatrixClient;
+use crate::test_helpers::{database, listener_config};
+use ruma::api::MatrixVersion;
+use ruma::events::room::message::TextMessageEventContent;
+use ruma::{event_id, room_id, user_id, EventId, RoomId, UserId};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use wiremock::matchers::{method, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn room() -> &'static RoomId {
+    room_id!("!room:matrix.org")
+}
+
+fn original() -> &'static EventId {
+    event_id!("$original:matrix.org")
+}
+
+fn author() -> &'static UserId {
+    user_id!("@author:matrix.org")
+}
+
+/// Starts a homeserver returning the original message with the supplied content
+async fn homeserver(content: Value) -> (MockServer, MatrixClient) {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/_matrix/client/v3/rooms/.+/event/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "m.room.message",
+            "event_id": original(),
+            "room_id": room(),
+            "sender": author(),
+            "origin_server_ts": 0,
+            "content": content,
+        })))
+        .mount(&server)
+        .await;
+    let client = ruma::client::Client::builder()
+        .homeserver_url(server.uri())
+        .access_token(Some("token".to_string()))
+        .supported_matrix_versions(vec![MatrixVersion::V1_1])
+        .build()
+        .await
+        .unwrap();
+    (server, client)
+}
+
+async fn edit(sender: &UserId, client: &MatrixClient) -> Option<MatrixMessage> {
+    let config = listener_config("thread_rooms = ['!room:matrix.org']", "");
+    let storage = database();
+    let (mut send, mut recv) = mpsc::channel::<MatrixMessage>(1);
+    handle_edit_event(
+        &TextMessageEventContent::plain("!help"),
+        original(),
+        sender,
+        room(),
+        &storage,
+        &config,
+        &reqwest::Client::new(),
+        client,
+        &mut send,
+    )
+    .await
+    .unwrap();
+    recv.try_recv().ok()
+}
+
+#[tokio::test]
+async fn edit_from_sender() {
+    let (_server, client) = homeserver(json!({"msgtype": "m.text", "body": "hi"})).await;
+    let message = edit(author(), &client).await.unwrap();
+    assert!(matches!(message.message, MatrixMessageType::Responses(_)));
+    let reply_to = message.reply_to.unwrap();
+    assert_eq!(original(), reply_to.event_id);
+    assert_eq!(Some(original().to_owned()), reply_to.thread_root);
+}
+
+#[tokio::test]
+async fn edit_from_other_user_ignored() {
+    let (_server, client) = homeserver(json!({"msgtype": "m.text", "body": "hi"})).await;
+    assert!(edit(user_id!("@impostor:matrix.org"), &client)
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn edited_reply_not_threaded() {
+    let (_server, client) = homeserver(json!({
+        "msgtype": "m.text",
+        "body": "hi",
+        "m.relates_to": {"m.in_reply_to": {"event_id": "$earlier:matrix.org"}}
+    }))
+    .await;
+    let reply_to = edit(author(), &client).await.unwrap().reply_to.unwrap();
+    assert_eq!(None, reply_to.thread_root);
+}
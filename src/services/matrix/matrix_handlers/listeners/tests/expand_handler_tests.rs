@@ -0,0 +1,51 @@
+use super::super::expand_handler::{parse_expand_command, ExpandCommand};
+
+#[test]
+fn add_and_edit() {
+    assert_eq!(
+        Ok(ExpandCommand::Add {
+            keyword: "kodi".to_string(),
+            text: "This addon syncs metadata\nfrom selected libraries",
+        }),
+        parse_expand_command("!expand add Kodi This addon syncs metadata\nfrom selected libraries")
+    );
+    assert_eq!(
+        Ok(ExpandCommand::Edit {
+            keyword: "kodi".to_string(),
+            text: "New text",
+        }),
+        parse_expand_command("!expand edit $kodi New text")
+    );
+}
+
+#[test]
+fn remove_and_list() {
+    assert_eq!(
+        Ok(ExpandCommand::Remove {
+            keyword: "kodi".to_string(),
+        }),
+        parse_expand_command("!expand rm kodi")
+    );
+    assert_eq!(
+        Ok(ExpandCommand::List),
+        parse_expand_command("!expand list")
+    );
+}
+
+#[test]
+fn invalid_commands() {
+    assert!(parse_expand_command("!expand").is_err());
+    assert!(parse_expand_command("!expand rename kodi emby").is_err());
+    assert!(parse_expand_command("!expand add kodi").is_err());
+    assert!(parse_expand_command("!expand add ko-di text").is_err());
+    assert!(parse_expand_command("!expand rm").is_err());
+}
+
+#[test]
+fn command_case() {
+    assert_eq!(
+        Ok(ExpandCommand::List),
+        parse_expand_command("!Expand list")
+    );
+    assert!(parse_expand_command("!expanded list").is_err());
+}
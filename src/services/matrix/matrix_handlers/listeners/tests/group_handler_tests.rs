@@ -0,0 +1,78 @@
+use super::super::group_handler::{parse_group_command, GroupCommand};
+use ruma::UserId;
+
+#[test]
+fn member_commands() {
+    assert_eq!(
+        Ok(GroupCommand::Join {
+            name: "backend".to_string(),
+        }),
+        parse_group_command("!group join Backend", None)
+    );
+    assert_eq!(
+        Ok(GroupCommand::Leave {
+            name: "backend".to_string(),
+        }),
+        parse_group_command("!group leave %backend", None)
+    );
+    assert_eq!(
+        Ok(GroupCommand::List),
+        parse_group_command("!group list", None)
+    );
+}
+
+#[test]
+fn admin_commands() {
+    assert_eq!(
+        Ok(GroupCommand::Create {
+            name: "web".to_string(),
+        }),
+        parse_group_command("!group create web", None)
+    );
+    assert_eq!(
+        Ok(GroupCommand::Delete {
+            name: "web".to_string(),
+        }),
+        parse_group_command("!group delete web", None)
+    );
+    assert_eq!(
+        Ok(GroupCommand::Add {
+            name: "web".to_string(),
+            user: UserId::parse("@sparky:matrix.possumlodge.me").unwrap(),
+        }),
+        parse_group_command("!group add web @sparky:matrix.possumlodge.me", None)
+    );
+}
+
+#[test]
+fn mentioned_user() {
+    assert_eq!(
+        Ok(GroupCommand::Remove {
+            name: "web".to_string(),
+            user: UserId::parse("@danoneil:matrix.org").unwrap(),
+        }),
+        parse_group_command(
+            "!group remove web danoneil",
+            Some("!group remove web <a href=\"https://matrix.to/#/@danoneil:matrix.org\">danoneil</a>")
+        )
+    );
+}
+
+#[test]
+fn invalid_commands() {
+    assert!(parse_group_command("!group", None).is_err());
+    assert!(parse_group_command("!group join", None).is_err());
+    assert!(parse_group_command("!group join all", None).is_err());
+    assert!(parse_group_command("!group join tui-client", None).is_err());
+    assert!(parse_group_command("!group rename web", None).is_err());
+    assert!(parse_group_command("!group add web danoneil", None).is_err());
+}
+
+#[test]
+fn command_case() {
+    assert_eq!(
+        Ok(GroupCommand::List),
+        parse_group_command("!GROUP list", None)
+    );
+    assert!(parse_group_command("!groups list", None).is_err());
+}
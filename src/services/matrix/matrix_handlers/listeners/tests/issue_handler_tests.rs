@@ -0,0 +1,85 @@
+use super::super::issue_handler::{parse_issue_command, IssueCommand};
+
+#[test]
+fn new_issue() {
+    assert_eq!(
+        Ok(IssueCommand::New {
+            repo: "jf".to_string(),
+            title: "Playback fails on Tizen".to_string(),
+            body: Some("Reported in the support room\nwith logs"),
+        }),
+        parse_issue_command(
+            "!issue new JF \"Playback fails on Tizen\" Reported in the support room\nwith logs"
+        )
+    );
+    assert_eq!(
+        Ok(IssueCommand::New {
+            repo: "jf".to_string(),
+            title: "Crash".to_string(),
+            body: None,
+        }),
+        parse_issue_command("!issue new jf Crash")
+    );
+}
+
+#[test]
+fn close_issue() {
+    assert_eq!(
+        Ok(IssueCommand::Close {
+            repo: "jf-web".to_string(),
+            number: 123,
+        }),
+        parse_issue_command("!issue close jf-web#123")
+    );
+}
+
+#[test]
+fn label_issue() {
+    assert_eq!(
+        Ok(IssueCommand::Label {
+            repo: "jf".to_string(),
+            number: 123,
+            labels: vec!["bug".to_string(), "good first issue".to_string()],
+        }),
+        parse_issue_command("!issue label jf#123 bug \"good first issue\"")
+    );
+}
+
+#[test]
+fn comment_issue() {
+    assert_eq!(
+        Ok(IssueCommand::Comment {
+            repo: "jf".to_string(),
+            number: 123,
+            body: "Fixed in 10.8.1",
+        }),
+        parse_issue_command("!issue comment jf#123 Fixed in 10.8.1")
+    );
+}
+
+#[test]
+fn invalid_commands() {
+    assert!(parse_issue_command("!issue").is_err());
+    assert!(parse_issue_command("!issue reopen jf#123").is_err());
+    assert!(parse_issue_command("!issue new jf").is_err());
+    assert!(parse_issue_command("!issue close jf").is_err());
+    assert!(parse_issue_command("!issue close jf#abc").is_err());
+    assert!(parse_issue_command("!issue label jf#123").is_err());
+    assert!(parse_issue_command("!issue comment jf#123 ").is_err());
+}
+
+#[test]
+fn command_case() {
+    assert_eq!(
+        Ok(IssueCommand::Close {
+            repo: "jf".to_string(),
+            number: 1,
+        }),
+        parse_issue_command("!Issue close jf#1")
+    );
+    assert_eq!(
+        Err("Missing action".to_string()),
+        parse_issue_command("!ISSUE")
+    );
+    assert!(parse_issue_command("!issues close jf#1").is_err());
+}
@@ -0,0 +1,9 @@
+mod anti_spam_handler_tests;
+mod edit_tests;
+mod expand_handler_tests;
+mod group_handler_tests;
+mod issue_handler_tests;
+mod moderation_handler_tests;
+mod policy_handler_tests;
+mod thread_tests;
+mod welcome_handler_tests;
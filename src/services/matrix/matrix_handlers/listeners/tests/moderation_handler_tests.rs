@@ -0,0 +1,179 @@
+use super::super::moderation_handler::{
+    is_moderation_command, parse_moderation_command, ModerationCommand,
+};
+use ruma::{EventId, UserId};
+use std::time::Duration;
+
+#[test]
+fn moderation_commands() {
+    assert!(is_moderation_command("!kick @user1:matrix.org"));
+    assert!(is_moderation_command("!UNBAN @user1:matrix.org"));
+    assert!(!is_moderation_command("!banana"));
+    assert!(!is_moderation_command("!help"));
+}
+
+#[test]
+fn user_commands() {
+    assert_eq!(
+        Ok(ModerationCommand::Ban {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+            duration: None,
+            reason: Some("spam links".to_string()),
+        }),
+        parse_moderation_command("!ban @user1:matrix.org spam links", None)
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Kick {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+            reason: None,
+        }),
+        parse_moderation_command("!kick @user1:matrix.org", None)
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Unban {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+            reason: Some("appealed".to_string()),
+        }),
+        parse_moderation_command("!unban @user1:matrix.org appealed", None)
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Unmute {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+        }),
+        parse_moderation_command("!unmute @user1:matrix.org", None)
+    );
+}
+
+#[test]
+fn formatted_user() {
+    assert_eq!(
+        Ok(ModerationCommand::Kick {
+            user: UserId::parse("@danoneil:matrix.org").unwrap(),
+            reason: None,
+        }),
+        parse_moderation_command(
+            "!kick danoneil",
+            Some("!kick <a href=\"https://matrix.to/#/@danoneil:matrix.org\">danoneil</a>")
+        )
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Kick {
+            user: UserId::parse("@danoneil:matrix.org").unwrap(),
+            reason: Some("spam".to_string()),
+        }),
+        parse_moderation_command(
+            "!kick Dan ONeil spam",
+            Some("!kick <a href=\"https://matrix.to/#/@danoneil:matrix.org\">Dan ONeil</a> spam")
+        )
+    );
+    assert!(parse_moderation_command("!kick danoneil", None).is_err());
+}
+
+#[test]
+fn mute() {
+    assert_eq!(
+        Ok(ModerationCommand::Mute {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+            duration: Some(Duration::from_secs(600)),
+        }),
+        parse_moderation_command("!mute @user1:matrix.org 10m", None)
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Mute {
+            user: UserId::parse("@danoneil:matrix.org").unwrap(),
+            duration: None,
+        }),
+        parse_moderation_command(
+            "!mute Dan ONeil",
+            Some("!mute <a href=\"https://matrix.to/#/@danoneil:matrix.org\">Dan ONeil</a>")
+        )
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Mute {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+            duration: Some(Duration::from_secs(600)),
+        }),
+        parse_moderation_command("!mute  @user1:matrix.org  10m", None)
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Mute {
+            user: UserId::parse("@agent:matrix.org").unwrap(),
+            duration: Some(Duration::from_secs(3600)),
+        }),
+        parse_moderation_command(
+            "!mute Agent 007 1h",
+            Some("!mute <a href=\"https://matrix.to/#/@agent:matrix.org\">Agent 007</a> 1h")
+        )
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Mute {
+            user: UserId::parse("@agent:matrix.org").unwrap(),
+            duration: None,
+        }),
+        parse_moderation_command(
+            "!mute Agent 007",
+            Some("!mute <a href=\"https://matrix.to/#/@agent:matrix.org\">Agent 007</a>")
+        )
+    );
+    assert!(parse_moderation_command("!mute @user1:matrix.org 10lightyears", None).is_err());
+}
+
+#[test]
+fn redact() {
+    assert_eq!(
+        Ok(ModerationCommand::Redact {
+            event_id: EventId::parse("$abc123:matrix.org").unwrap(),
+            reason: Some("off topic".to_string()),
+        }),
+        parse_moderation_command("!redact $abc123:matrix.org off topic", None)
+    );
+    assert!(parse_moderation_command("!redact", None).is_err());
+    assert!(parse_moderation_command("!redact abc123", None).is_err());
+}
+
+#[test]
+fn purge() {
+    assert_eq!(
+        Ok(ModerationCommand::Purge {
+            user: UserId::parse("@user1:matrix.org").unwrap(),
+            count: 20,
+        }),
+        parse_moderation_command("!purge @user1:matrix.org 20", None)
+    );
+    assert!(parse_moderation_command("!purge @user1:matrix.org", None).is_err());
+    assert!(parse_moderation_command("!purge @user1:matrix.org 0", None).is_err());
+    assert!(parse_moderation_command("!purge @user1:matrix.org 1000", None).is_err());
+}
+
+#[test]
+fn timed_ban() {
+    assert_eq!(
+        Ok(ModerationCommand::Ban {
+            user: UserId::parse("@spammer:matrix.org").unwrap(),
+            duration: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            reason: Some("spam links".to_string()),
+        }),
+        parse_moderation_command("!ban @spammer:matrix.org 7d spam links", None)
+    );
+    assert_eq!(
+        Ok(ModerationCommand::Ban {
+            user: UserId::parse("@spammer:matrix.org").unwrap(),
+            duration: None,
+            reason: Some("2nd offense".to_string()),
+        }),
+        parse_moderation_command("!ban @spammer:matrix.org 2nd offense", None)
+    );
+    assert!(parse_moderation_command("!ban @spammer:matrix.org 500000000000y", None).is_err());
+    assert!(parse_moderation_command("!mute @spammer:matrix.org 500000000000y", None).is_err());
+    assert_eq!(
+        Ok(ModerationCommand::Ban {
+            user: UserId::parse("@danoneil:matrix.org").unwrap(),
+            duration: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            reason: Some("spam".to_string()),
+        }),
+        parse_moderation_command(
+            "!ban Dan ONeil 7d spam",
+            Some("!ban <a href=\"https://matrix.to/#/@danoneil:matrix.org\">Dan ONeil</a> 7d spam")
+        )
+    );
+}
@@ -0,0 +1,160 @@
+use super::super::{policy_handler, policy_join_handler};
+use crate::database::models::{PolicyRule, PolicyRuleKind, RoomPowerLevels};
+use crate::helpers::save_policy_rule;
+use crate::messages::{MatrixMessage, MatrixMessageType};
+use crate::test_helpers::{database, listener_config};
+use native_db::Database;
+use ruma::events::AnySyncStateEvent;
+use ruma::serde::Raw;
+use ruma::{room_id, user_id};
+use serde_json::json;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+const POLICY_ROOMS: &str = "ban_rooms = ['!ban:matrix.org']
+policy_rooms = ['!policy:matrix.org']
+policy_publish_room = '!policy:matrix.org'";
+
+/// Stores a ban rule for the entity read from the policy room
+fn save_rule(storage: &Database, policy_room: &str, entity: &str) {
+    save_policy_rule(
+        storage,
+        PolicyRule::new(
+            policy_room,
+            PolicyRuleKind::User,
+            entity,
+            entity.to_string(),
+            "spam".to_string(),
+        ),
+    )
+    .unwrap();
+}
+
+fn ban_rule(sender: &str, user: &str) -> Raw<AnySyncStateEvent> {
+    Raw::from_json(
+        serde_json::value::to_raw_value(&json!({
+            "type": "m.policy.rule.user",
+            "state_key": format!("rule:{}", user),
+            "sender": sender,
+            "event_id": "$rule:matrix.org",
+            "origin_server_ts": 0,
+            "content": {
+                "entity": user,
+                "recommendation": "m.ban",
+                "reason": "spam"
+            }
+        }))
+        .unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn rule_applied() {
+    let config = listener_config(POLICY_ROOMS, "");
+    let storage = database();
+    let (mut send, mut recv) = mpsc::channel::<MatrixMessage>(1);
+    policy_handler(
+        &ban_rule("@admin:matrix.org", "@spammer:matrix.org"),
+        room_id!("!policy:matrix.org"),
+        &storage,
+        &config,
+        &mut send,
+    )
+    .await
+    .unwrap();
+    match recv.try_recv().unwrap().message {
+        MatrixMessageType::PolicyBan(m) => {
+            assert_eq!("@spammer:matrix.org", m.rule.entity);
+            assert!(m.exempt.contains(user_id!("@admin:matrix.org")));
+        }
+        v => panic!("Unexpected message {:?}", v),
+    }
+}
+
+#[tokio::test]
+async fn published_rule_ignored() {
+    let config = listener_config(POLICY_ROOMS, "");
+    let storage = database();
+    let (mut send, mut recv) = mpsc::channel::<MatrixMessage>(1);
+    policy_handler(
+        &ban_rule("@bot:matrix.org", "@spammer:matrix.org"),
+        room_id!("!policy:matrix.org"),
+        &storage,
+        &config,
+        &mut send,
+    )
+    .await
+    .unwrap();
+    assert!(recv.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn joining_user_banned() {
+    let config = listener_config(POLICY_ROOMS, "");
+    let storage = database();
+    save_rule(&storage, "!policy:matrix.org", "@spam*:matrix.org");
+    let (mut send, mut recv) = mpsc::channel::<MatrixMessage>(1);
+    policy_join_handler(
+        user_id!("@spammer:matrix.org"),
+        room_id!("!ban:matrix.org"),
+        &storage,
+        &config,
+        &mut send,
+    )
+    .await
+    .unwrap();
+    match recv.try_recv().unwrap().message {
+        MatrixMessageType::Ban(m) => assert_eq!("@spammer:matrix.org", m.user),
+        v => panic!("Unexpected message {:?}", v),
+    }
+}
+
+#[tokio::test]
+async fn rules_from_removed_policy_rooms_ignored() {
+    let config = listener_config(POLICY_ROOMS, "");
+    let storage = database();
+    save_rule(&storage, "!removed:matrix.org", "@spammer:matrix.org");
+    let (mut send, mut recv) = mpsc::channel::<MatrixMessage>(1);
+    policy_join_handler(
+        user_id!("@spammer:matrix.org"),
+        room_id!("!ban:matrix.org"),
+        &storage,
+        &config,
+        &mut send,
+    )
+    .await
+    .unwrap();
+    assert!(recv.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn joining_moderators_not_banned() {
+    let config = listener_config(POLICY_ROOMS, "");
+    let storage = database();
+    save_rule(&storage, "!policy:matrix.org", "@*:*");
+    let rw = storage.rw_transaction().unwrap();
+    rw.insert(RoomPowerLevels {
+        room_id: "!ban:matrix.org".to_string(),
+        users: HashMap::from([("@moderator:matrix.org".to_string(), 50)]),
+        users_default: 0,
+    })
+    .unwrap();
+    rw.commit().unwrap();
+    let (mut send, mut recv) = mpsc::channel::<MatrixMessage>(1);
+
+    for user in [
+        user_id!("@admin:matrix.org"),
+        user_id!("@moderator:matrix.org"),
+    ] {
+        policy_join_handler(
+            user,
+            room_id!("!ban:matrix.org"),
+            &storage,
+            &config,
+            &mut send,
+        )
+        .await
+        .unwrap();
+        assert!(recv.try_recv().is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use super::super::is_reply;
+use crate::messages::MatrixReply;
+use ruma::events::room::message::{InReplyTo, Relation, RoomMessageEventContent, Thread};
+use ruma::{event_id, EventId};
+use serde_json::json;
+
+fn thread_event() -> &'static EventId {
+    event_id!("$thread:matrix.org")
+}
+
+#[test]
+fn thread_messages_are_not_replies() {
+    let root = event_id!("$root:matrix.org").to_owned();
+    let latest = event_id!("$latest:matrix.org").to_owned();
+    let reply = Relation::Reply {
+        in_reply_to: InReplyTo::new(latest.clone()),
+    };
+    assert!(!is_reply(None));
+    assert!(is_reply(Some(&reply)));
+    assert!(!is_reply(Some(&Relation::Thread(Thread::plain(
+        root.clone(),
+        latest.clone()
+    )))));
+    assert!(is_reply(Some(&Relation::Thread(Thread::reply(
+        root, latest
+    )))));
+}
+
+#[test]
+fn plain_messages_are_not_replies() {
+    let content: RoomMessageEventContent =
+        serde_json::from_value(json!({"msgtype": "m.text", "body": "hi"})).unwrap();
+    assert!(!is_reply(content.relates_to.as_ref()));
+}
+
+#[test]
+fn responses_stay_in_thread() {
+    let root = event_id!("$root:matrix.org").to_owned();
+    let relation = Relation::Thread(Thread::plain(root.clone(), root.clone()));
+
+    let reply_to = MatrixReply::new(thread_event(), Some(&relation));
+    assert_eq!(Some(root.clone()), reply_to.thread_root);
+    let reply_to = MatrixReply::threaded(thread_event(), Some(&relation));
+    assert_eq!(Some(root), reply_to.thread_root);
+}
+
+#[test]
+fn responses_start_thread() {
+    let reply_to = MatrixReply::new(thread_event(), None);
+    assert_eq!(None, reply_to.thread_root);
+    let reply_to = MatrixReply::threaded(thread_event(), None);
+    assert_eq!(Some(thread_event().to_owned()), reply_to.thread_root);
+}
+
+#[test]
+fn replies_do_not_start_thread() {
+    let reply = Relation::Reply {
+        in_reply_to: InReplyTo::new(event_id!("$original:matrix.org").to_owned()),
+    };
+    let reply_to = MatrixReply::threaded(thread_event(), Some(&reply));
+    assert_eq!(None, reply_to.thread_root);
+    assert_eq!(thread_event(), reply_to.event_id);
+}
+
+#[test]
+fn messages_without_relation_start_thread() {
+    let content: RoomMessageEventContent =
+        serde_json::from_value(json!({"msgtype": "m.text", "body": "hi"})).unwrap();
+    let reply_to = MatrixReply::threaded(thread_event(), content.relates_to.as_ref());
+    assert_eq!(Some(thread_event().to_owned()), reply_to.thread_root);
+}
@@ -0,0 +1,48 @@
+use super::super::welcome_handler::WelcomeQueue;
+use ruma::{RoomId, UserId};
+use std::time::{Duration, Instant};
+
+#[test]
+fn joins_are_batched() {
+    let mut queue = WelcomeQueue::default();
+    let room = RoomId::parse("!room:matrix.org").unwrap();
+    let user1 = UserId::parse("@user1:matrix.org").unwrap();
+    let user2 = UserId::parse("@user2:matrix.org").unwrap();
+    let now = Instant::now();
+
+    queue.join(&room, &user1);
+    queue.join(&room, &user2);
+    queue.join(&room, &user1);
+
+    assert_eq!(
+        vec![(room.clone(), vec![user1, user2])],
+        queue.due(Duration::from_secs(60), now)
+    );
+    assert!(queue.due(Duration::from_secs(60), now).is_empty());
+}
+
+#[test]
+fn cooldown() {
+    let mut queue = WelcomeQueue::default();
+    let room = RoomId::parse("!room:matrix.org").unwrap();
+    let user1 = UserId::parse("@user1:matrix.org").unwrap();
+    let user2 = UserId::parse("@user2:matrix.org").unwrap();
+    let user3 = UserId::parse("@user3:matrix.org").unwrap();
+    let cooldown = Duration::from_secs(60);
+    let now = Instant::now();
+
+    queue.join(&room, &user1);
+    assert_eq!(1, queue.due(cooldown, now).len());
+
+    queue.join(&room, &user2);
+    queue.join(&room, &user3);
+    assert!(queue
+        .due(cooldown, now + Duration::from_secs(30))
+        .is_empty());
+
+    queue.leave(&room, &user3);
+    assert_eq!(
+        vec![(room.clone(), vec![user2])],
+        queue.due(cooldown, now + cooldown)
+    );
+}
@@ -1,8 +1,9 @@
 //! Handler for the unit conversion command
 
+use super::is_reply;
 use crate::helpers::bot_response::MatrixNoticeResponse;
 use crate::helpers::convert_unit;
-use crate::messages::{MatrixMessage, MatrixMessageType};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixReply};
 use crate::regex::UNIT_CONVERSION;
 use anyhow::bail;
 use ruma::events::room::message::RoomMessageEventContent;
@@ -18,9 +19,10 @@ pub async fn unit_conversion_handler(
     text: &TextMessageEventContent,
     relates_to: Option<&Relation>,
     room_id: &RoomId,
+    reply_to: &MatrixReply,
     send: &mut Sender<MatrixMessage>,
 ) -> anyhow::Result<()> {
-    if relates_to.is_none() && text.formatted.is_none() {
+    if !is_reply(relates_to) && text.formatted.is_none() {
         let mut conversions = Vec::new();
         for cap in UNIT_CONVERSION.captures_iter(&text.body.to_lowercase()) {
             conversions.push((cap[1].to_string(), cap[2].to_string()));
@@ -37,6 +39,7 @@ pub async fn unit_conversion_handler(
         if send
             .send(MatrixMessage {
                 room_id: Some(room_id.to_owned()),
+                reply_to: Some(reply_to.clone()),
                 message: MatrixMessageType::Response(RoomMessageEventContent::notice_plain(
                     response.to_string(),
                 )),
@@ -0,0 +1,140 @@
+//! Welcomes users joining rooms with a configured welcome message
+//!
+//! Users joining during the cooldown after a welcome are welcomed together once it passes
+
+use crate::config::MatrixListenerConfig;
+use crate::helpers::stored_text_expansion;
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixWelcomeMessage};
+use crate::regex::TEXT_EXPANSION;
+use anyhow::bail;
+use native_db::Database;
+use regex::Captures;
+use ruma::{OwnedRoomId, OwnedUserId, RoomId, UserId};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tracing::debug;
+
+#[derive(Default)]
+/// Users waiting to be welcomed and when each room last had a welcome message
+pub struct WelcomeQueue {
+    pending: HashMap<OwnedRoomId, Vec<OwnedUserId>>,
+    last_sent: HashMap<OwnedRoomId, Instant>,
+}
+
+impl WelcomeQueue {
+    /// Queues a welcome for a user who joined the room
+    pub fn join(&mut self, room_id: &RoomId, user: &UserId) {
+        let users = self.pending.entry(room_id.to_owned()).or_default();
+        if !users.iter().any(|u| u == user) {
+            users.push(user.to_owned());
+        }
+    }
+
+    /// Drops the welcome of a user who left the room before being welcomed
+    pub fn leave(&mut self, room_id: &RoomId, user: &UserId) {
+        if let Some(users) = self.pending.get_mut(room_id) {
+            users.retain(|u| u != user);
+        }
+    }
+
+    /// Takes the users waiting to be welcomed in every room whose cooldown has passed
+    pub fn due(
+        &mut self,
+        cooldown: Duration,
+        now: Instant,
+    ) -> Vec<(OwnedRoomId, Vec<OwnedUserId>)> {
+        let last_sent = &self.last_sent;
+        let rooms = self
+            .pending
+            .iter()
+            .filter(|(room_id, users)| {
+                !users.is_empty()
+                    && last_sent
+                        .get(*room_id)
+                        .is_none_or(|sent| now.duration_since(*sent) >= cooldown)
+            })
+            .map(|(room_id, _)| room_id.clone())
+            .collect::<Vec<_>>();
+        rooms
+            .into_iter()
+            .filter_map(|room_id| {
+                let users = self.pending.remove(&room_id)?;
+                self.last_sent.insert(room_id.clone(), now);
+                Some((room_id, users))
+            })
+            .collect()
+    }
+}
+
+/// Queues a welcome for a user joining a room with a welcome message
+pub fn welcome_join_handler(
+    user: &UserId,
+    room_id: &RoomId,
+    queue: &mut WelcomeQueue,
+    config: &MatrixListenerConfig,
+) {
+    if user != config.mx_uname && config.welcome_messages.contains_key(room_id) {
+        debug!("Queueing welcome for {} in {}", user, room_id);
+        queue.join(room_id, user);
+    }
+}
+
+/// Sends a welcome message to every room with users waiting to be welcomed whose cooldown has passed
+pub async fn welcome_handler(
+    queue: &mut WelcomeQueue,
+    storage: &Database<'_>,
+    config: &MatrixListenerConfig,
+    send: &mut Sender<MatrixMessage>,
+) -> anyhow::Result<()> {
+    for (room_id, users) in queue.due(config.welcome_cooldown, Instant::now()) {
+        let template = match config.welcome_messages.get(&room_id) {
+            Some(v) => v,
+            None => continue,
+        };
+        debug!("Welcoming {} users to {}", users.len(), room_id);
+        let message = MatrixMessageType::Welcome(MatrixWelcomeMessage {
+            users,
+            template: expand_template(template, config, storage),
+        });
+        if send
+            .send(MatrixMessage {
+                room_id: Some(room_id),
+                reply_to: None,
+                message,
+            })
+            .await
+            .is_err()
+        {
+            bail!("Channel closed, unable to send mesage.");
+        }
+    }
+    Ok(())
+}
+
+/// Replaces text expansion references like $rules with their text, preferring expansions edited from chat
+fn expand_template(
+    template: &str,
+    config: &MatrixListenerConfig,
+    storage: &Database<'_>,
+) -> String {
+    TEXT_EXPANSION
+        .replace_all(template, |caps: &Captures| {
+            let keyword = caps[1].to_lowercase();
+            let expansion = match stored_text_expansion(storage, &keyword) {
+                Some(v) => v.text,
+                None => match config.text_expansions.get(keyword.as_str()) {
+                    Some(v) => v.to_string(),
+                    None => return caps[0].to_string(),
+                },
+            };
+            // Keep the whitespace matched before the reference
+            let reference = &caps[0];
+            format!(
+                "{}{}",
+                &reference[..reference.find('$').unwrap_or(0)],
+                expansion
+            )
+        })
+        .into_owned()
+}
@@ -0,0 +1,35 @@
+//! Records moderation actions in the database and the audit room
+
+use super::send_message;
+use crate::database::models::ModerationRecord;
+use crate::helpers::save_moderation_record;
+use crate::services::matrix::MatrixClient;
+use native_db::Database;
+use ruma::{events::room::message::RoomMessageEventContent, OwnedRoomId};
+use tracing::{error, info};
+
+/// Saves the record and posts it to the audit room if one is configured
+pub async fn audit(
+    record: ModerationRecord,
+    audit_room: Option<OwnedRoomId>,
+    storage: &Database<'_>,
+    client: &MatrixClient,
+) {
+    let text = record.to_string();
+    info!("{}", text);
+    if let Err(e) = save_moderation_record(storage, record) {
+        error!("{:#}", e);
+    }
+    if let Some(room_id) = audit_room {
+        if let Err(e) = send_message(
+            client,
+            room_id,
+            RoomMessageEventContent::notice_plain(text),
+            None,
+        )
+        .await
+        {
+            error!("Unable to post to audit room. Error is {:#}", e);
+        }
+    }
+}
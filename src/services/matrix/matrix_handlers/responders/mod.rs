@@ -1,48 +1,589 @@
+//! Functions that perform actions requested by the listeners
+//!
+//! Moderation actions return their outcome in each room so they can be audited
+
+mod audit;
+
+pub use audit::audit;
+
+use crate::database::models::{MutedUser, PolicyRule, PolicyRuleKind, RoomOutcome};
+use crate::helpers::{escape_html, is_glob, policy_rule_matches, save_muted_user, take_muted_user};
+use crate::messages::MatrixReply;
 use crate::services::matrix::MatrixClient;
-use anyhow::Context;
+use anyhow::{bail, Context};
+use native_db::Database;
 use ruma::{
     api::client::{
-        membership::{ban_user, join_room_by_id, leave_room},
-        message::send_message_event,
+        membership::{
+            ban_user, join_room_by_id, joined_members, kick_user, leave_room, unban_user,
+        },
+        message::{get_message_events, send_message_event},
+        redact::redact_event,
+        state::{get_state_events_for_key, send_state_event},
     },
-    events::room::message::RoomMessageEventContent,
-    OwnedRoomId, TransactionId, UserId,
+    events::{
+        policy::rule::{user::PolicyRuleUserEventContent, PolicyRuleEventContent, Recommendation},
+        room::{
+            canonical_alias::RoomCanonicalAliasEventContent,
+            message::{InReplyTo, Relation, Replacement, RoomMessageEventContent, Thread},
+            name::RoomNameEventContent,
+            power_levels::RoomPowerLevelsEventContent,
+        },
+        AnyStateEventContent, AnyTimelineEvent, EmptyStateKey, RoomEventType, StateEventType,
+    },
+    int,
+    serde::Raw,
+    uint, EventId, Int, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, TransactionId, UserId,
 };
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
 use tracing::{debug, error, info};
 
+/// Sends a message to the room, as a reply to the event it answers if there is one
+///
+/// Replies to events in a thread are sent in the same thread, and replies starting a thread are sent as its first message
 pub async fn send_message(
     client: &MatrixClient,
     room_id: OwnedRoomId,
+    mut content: RoomMessageEventContent,
+    reply_to: Option<MatrixReply>,
+) -> anyhow::Result<OwnedEventId> {
+    if let Some(reply_to) = reply_to {
+        content.relates_to = Some(match reply_to.thread_root {
+            Some(root) if root == reply_to.event_id => {
+                Relation::Thread(Thread::plain(root, reply_to.event_id))
+            }
+            Some(root) => Relation::Thread(Thread::reply(root, reply_to.event_id)),
+            None => Relation::Reply {
+                in_reply_to: InReplyTo::new(reply_to.event_id),
+            },
+        });
+    }
+    let txn_id = TransactionId::new();
+    let req = send_message_event::v3::Request::new(&room_id, &txn_id, &content)
+        .context("m.room.message serialization must work")?;
+    let resp = client
+        .send_request(req)
+        .await
+        .context("Matrix response was unable to be sent")?;
+    Ok(resp.event_id)
+}
+
+/// Replaces the content of a message sent by the bot, keeping the new content as the fallback for clients without edits
+pub async fn edit_message(
+    client: &MatrixClient,
+    room_id: &RoomId,
+    event_id: OwnedEventId,
     content: RoomMessageEventContent,
 ) -> anyhow::Result<()> {
+    debug!("Editing event {} in room {}...", event_id, room_id);
+    let mut replacement = content.clone();
+    replacement.relates_to = Some(Relation::Replacement(Replacement::new(
+        event_id,
+        Box::new(content),
+    )));
     let txn_id = TransactionId::new();
-    let req = send_message_event::v3::Request::new(&room_id, &txn_id, &content)
+    let req = send_message_event::v3::Request::new(room_id, &txn_id, &replacement)
         .context("m.room.message serialization must work")?;
     client
         .send_request(req)
         .await
-        .context("Matrix response was unable to be sent")?;
+        .context("Matrix response was unable to be edited")?;
+    Ok(())
+}
+
+/// Posts a warning that mentions the user
+pub async fn send_warn_message(
+    user: &UserId,
+    reason: &str,
+    room_id: OwnedRoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    debug!("Warning user {} in room {}...", user, room_id);
+    let content = RoomMessageEventContent::notice_html(
+        format!("{}: {}", user, reason),
+        format!(
+            "<a href=\"https://matrix.to/#/{}\">{}</a>: {}",
+            user,
+            user.localpart(),
+            escape_html(reason)
+        ),
+    );
+    send_message(client, room_id, content, None).await?;
+    Ok(())
+}
+
+/// Posts a welcome message, replacing {user} with the welcomed users and {room} with the name of the room
+pub async fn send_welcome_message(
+    users: &[OwnedUserId],
+    template: &str,
+    room_id: OwnedRoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    let room = room_name(&room_id, client).await;
+    let plain_users = join_names(users.iter().map(|u| u.to_string()).collect());
+    let formatted_users = join_names(
+        users
+            .iter()
+            .map(|u| {
+                format!(
+                    "<a href=\"https://matrix.to/#/{}\">{}</a>",
+                    u,
+                    u.localpart()
+                )
+            })
+            .collect(),
+    );
+    let content = RoomMessageEventContent::notice_html(
+        template
+            .replace("{user}", &plain_users)
+            .replace("{room}", &room),
+        escape_html(template)
+            .replace("{user}", &formatted_users)
+            .replace("{room}", &escape_html(&room)),
+    );
+    send_message(client, room_id, content, None).await?;
     Ok(())
 }
 
+/// Returns the name of the room, falling back to its canonical alias and then its ID
+async fn room_name(room_id: &RoomId, client: &MatrixClient) -> String {
+    let name = client
+        .send_request(get_state_events_for_key::v3::Request::new(
+            room_id,
+            StateEventType::RoomName,
+            "",
+        ))
+        .await
+        .ok()
+        .and_then(|r| r.content.deserialize_as::<RoomNameEventContent>().ok())
+        .and_then(|c| c.name);
+    if let Some(name) = name.filter(|n| !n.is_empty()) {
+        return name;
+    }
+    let alias = client
+        .send_request(get_state_events_for_key::v3::Request::new(
+            room_id,
+            StateEventType::RoomCanonicalAlias,
+            "",
+        ))
+        .await
+        .ok()
+        .and_then(|r| {
+            r.content
+                .deserialize_as::<RoomCanonicalAliasEventContent>()
+                .ok()
+        })
+        .and_then(|c| c.alias);
+    match alias {
+        Some(v) => v.to_string(),
+        None => room_id.to_string(),
+    }
+}
+
+/// Joins names into a list like "a, b and c"
+fn join_names(mut names: Vec<String>) -> String {
+    match names.pop() {
+        Some(last) if names.is_empty() => last,
+        Some(last) => format!("{} and {}", names.join(", "), last),
+        None => String::new(),
+    }
+}
+
 pub async fn send_ban_message(
     user: &UserId,
     reason: Option<String>,
     rooms: HashSet<OwnedRoomId>,
     client: &MatrixClient,
-) -> anyhow::Result<()> {
+) -> Vec<RoomOutcome> {
+    let mut outcomes = Vec::new();
     for room_id in rooms {
         debug!("Banning user {} in room {}...", user, room_id);
         let mut req = ban_user::v3::Request::new(&room_id, user);
         req.reason = reason.as_deref();
-        if let Err(e) = client.send_request(req).await {
-            error!("{:?}", e);
+        outcomes.push(outcome(&room_id, client.send_request(req).await));
+    }
+    outcomes
+}
+
+pub async fn send_unban_message(
+    user: &UserId,
+    reason: Option<String>,
+    rooms: HashSet<OwnedRoomId>,
+    client: &MatrixClient,
+) -> Vec<RoomOutcome> {
+    let mut outcomes = Vec::new();
+    for room_id in rooms {
+        debug!("Unbanning user {} in room {}...", user, room_id);
+        let mut req = unban_user::v3::Request::new(&room_id, user);
+        req.reason = reason.as_deref();
+        outcomes.push(outcome(&room_id, client.send_request(req).await));
+    }
+    outcomes
+}
+
+/// Bans the users matching the rule in the rooms, returning the outcomes of each banned user
+///
+/// Moderators are never banned, whether they are exempt or have a power level above the room default.
+/// Rooms whose members or power levels can not be read are returned as outcomes of the rule entity
+pub async fn send_policy_ban_message(
+    rule: &PolicyRule,
+    reason: Option<String>,
+    rooms: HashSet<OwnedRoomId>,
+    exempt: &HashSet<OwnedUserId>,
+    client: &MatrixClient,
+) -> BTreeMap<String, Vec<RoomOutcome>> {
+    let mut outcomes = BTreeMap::<String, Vec<RoomOutcome>>::new();
+    for room_id in rooms {
+        let users = match policy_ban_targets(rule, exempt, &room_id, client).await {
+            Ok(v) => v,
+            Err(e) => {
+                outcomes
+                    .entry(rule.entity.clone())
+                    .or_default()
+                    .push(outcome(&room_id, Err::<(), _>(e)));
+                continue;
+            }
         };
+        for user in users {
+            debug!("Banning user {} in room {}...", user, room_id);
+            let mut req = ban_user::v3::Request::new(&room_id, &user);
+            req.reason = reason.as_deref();
+            outcomes
+                .entry(user.to_string())
+                .or_default()
+                .push(outcome(&room_id, client.send_request(req).await));
+        }
+    }
+    outcomes
+}
+
+/// Returns the users in the room a policy rule bans, looking through the members of the room unless the rule names
+/// a single user
+async fn policy_ban_targets(
+    rule: &PolicyRule,
+    exempt: &HashSet<OwnedUserId>,
+    room_id: &RoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<Vec<OwnedUserId>> {
+    let power_levels = room_power_levels(room_id, client).await?;
+    let single_user = match rule.kind {
+        PolicyRuleKind::User if !is_glob(&rule.entity) => UserId::parse(rule.entity.as_str()).ok(),
+        _ => None,
+    };
+    let users = match single_user {
+        Some(v) => vec![v],
+        None => client
+            .send_request(joined_members::v3::Request::new(room_id))
+            .await
+            .with_context(|| format!("Unable to get members of room {}", room_id))?
+            .joined
+            .into_keys()
+            .filter(|u| policy_rule_matches(rule, u))
+            .collect(),
+    };
+    Ok(users
+        .into_iter()
+        .filter(|u| {
+            let moderator = exempt.contains(u)
+                || power_levels
+                    .users
+                    .get(u)
+                    .is_some_and(|l| *l > power_levels.users_default);
+            if moderator {
+                info!(
+                    "Not banning moderator {} in room {} for policy rule {}",
+                    u, room_id, rule.entity
+                );
+            }
+            !moderator
+        })
+        .collect())
+}
+
+/// Publishes a ban of the user as a policy rule, keyed by the user so unbanning can remove it
+pub async fn publish_policy_ban(
+    user: &UserId,
+    reason: Option<String>,
+    room_id: &RoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    debug!("Publishing ban of user {} to room {}...", user, room_id);
+    let content = PolicyRuleUserEventContent(PolicyRuleEventContent::new(
+        user.to_string(),
+        Recommendation::Ban,
+        reason.unwrap_or_default(),
+    ));
+    let state_key = policy_state_key(user);
+    let req = send_state_event::v3::Request::new(room_id, &state_key, &content)
+        .context("m.policy.rule.user serialization must work")?;
+    client
+        .send_request(req)
+        .await
+        .context("Unable to publish policy rule")?;
+    Ok(())
+}
+
+/// Removes a published ban of the user by replacing its policy rule with empty content
+pub async fn unpublish_policy_ban(
+    user: &UserId,
+    room_id: &RoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    debug!(
+        "Removing published ban of user {} from room {}...",
+        user, room_id
+    );
+    let state_key = policy_state_key(user);
+    let body = serde_json::value::to_raw_value(&serde_json::json!({}))
+        .context("Empty content serialization must work")?;
+    let req = send_state_event::v3::Request::new_raw(
+        room_id,
+        StateEventType::PolicyRuleUser,
+        &state_key,
+        Raw::<AnyStateEventContent>::from_json(body),
+    );
+    client
+        .send_request(req)
+        .await
+        .context("Unable to remove policy rule")?;
+    Ok(())
+}
+
+fn policy_state_key(user: &UserId) -> String {
+    format!("rule:{}", user)
+}
+
+pub async fn send_kick_message(
+    user: &UserId,
+    reason: Option<String>,
+    rooms: HashSet<OwnedRoomId>,
+    client: &MatrixClient,
+) -> Vec<RoomOutcome> {
+    let mut outcomes = Vec::new();
+    for room_id in rooms {
+        debug!("Kicking user {} in room {}...", user, room_id);
+        let mut req = kick_user::v3::Request::new(&room_id, user);
+        req.reason = reason.as_deref();
+        outcomes.push(outcome(&room_id, client.send_request(req).await));
+    }
+    outcomes
+}
+
+pub async fn send_mute_message(
+    sender: &UserId,
+    user: &UserId,
+    rooms: &HashSet<OwnedRoomId>,
+    storage: &Database<'_>,
+    client: &MatrixClient,
+) -> Vec<RoomOutcome> {
+    let mut outcomes = Vec::new();
+    for room_id in rooms {
+        debug!("Muting user {} in room {}...", user, room_id);
+        outcomes.push(outcome(
+            room_id,
+            mute_user(sender, user, room_id, storage, client).await,
+        ));
+    }
+    outcomes
+}
+
+pub async fn send_unmute_message(
+    user: &UserId,
+    rooms: &HashSet<OwnedRoomId>,
+    storage: &Database<'_>,
+    client: &MatrixClient,
+) -> Vec<RoomOutcome> {
+    let mut outcomes = Vec::new();
+    for room_id in rooms {
+        debug!("Unmuting user {} in room {}...", user, room_id);
+        outcomes.push(outcome(
+            room_id,
+            unmute_user(user, room_id, storage, client).await,
+        ));
+    }
+    outcomes
+}
+
+/// Lowers the power level of a user below what is needed to send messages, remembering the level
+/// they had so it can be restored
+///
+/// Senders with a power level above the room default can not mute users at or above it. Senders given their role
+/// in the config usually have the default power level, so they are only limited by the homeserver, which refuses
+/// changes to users at or above the bot
+async fn mute_user(
+    sender: &UserId,
+    user: &UserId,
+    room_id: &RoomId,
+    storage: &Database<'_>,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    let mut content = room_power_levels(room_id, client).await?;
+    let level_of = |u: &UserId| {
+        content
+            .users
+            .get(u)
+            .copied()
+            .unwrap_or(content.users_default)
+    };
+    let sender_level = level_of(sender);
+    if sender_level > content.users_default && level_of(user) >= sender_level {
+        bail!(
+            "{} has a power level at or above the one of {} in room {}",
+            user,
+            sender,
+            room_id
+        );
     }
+    let previous_level = content.users.get(user).map(|l| i64::from(*l));
+    let message_level = content
+        .events
+        .get(&RoomEventType::RoomMessage)
+        .copied()
+        .unwrap_or(content.events_default)
+        .min(content.events_default);
+    content
+        .users
+        .insert(user.to_owned(), message_level - int!(1));
+    set_power_levels(room_id, &content, client).await?;
+    save_muted_user(
+        storage,
+        MutedUser::new(room_id.as_str(), user.as_str(), previous_level),
+    )
+}
+
+/// Restores the power level a user had before they were muted, or resets it to the room default
+/// if the mute is unknown
+async fn unmute_user(
+    user: &UserId,
+    room_id: &RoomId,
+    storage: &Database<'_>,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    let mut content = room_power_levels(room_id, client).await?;
+    let previous_level = take_muted_user(storage, room_id, user)?
+        .and_then(|m| m.previous_level)
+        .and_then(Int::new);
+    match previous_level {
+        Some(level) => content.users.insert(user.to_owned(), level),
+        None => content.users.remove(user),
+    };
+    set_power_levels(room_id, &content, client).await
+}
+
+async fn room_power_levels(
+    room_id: &RoomId,
+    client: &MatrixClient,
+) -> anyhow::Result<RoomPowerLevelsEventContent> {
+    let response = client
+        .send_request(get_state_events_for_key::v3::Request::new(
+            room_id,
+            StateEventType::RoomPowerLevels,
+            "",
+        ))
+        .await
+        .with_context(|| format!("Unable to get power levels of room {}", room_id))?;
+    response
+        .content
+        .deserialize_as::<RoomPowerLevelsEventContent>()
+        .with_context(|| format!("Invalid power levels in room {}", room_id))
+}
+
+async fn set_power_levels(
+    room_id: &RoomId,
+    content: &RoomPowerLevelsEventContent,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    let req = send_state_event::v3::Request::new(room_id, &EmptyStateKey, content)
+        .context("m.room.power_levels serialization must work")?;
+    client
+        .send_request(req)
+        .await
+        .with_context(|| format!("Unable to update power levels of room {}", room_id))?;
+    Ok(())
+}
+
+pub async fn send_redact_message(
+    room_id: &RoomId,
+    event_id: &EventId,
+    reason: Option<String>,
+    client: &MatrixClient,
+) -> anyhow::Result<()> {
+    debug!("Redacting event {} in room {}...", event_id, room_id);
+    let txn_id = TransactionId::new();
+    let mut req = redact_event::v3::Request::new(room_id, event_id, &txn_id);
+    req.reason = reason.as_deref();
+    client
+        .send_request(req)
+        .await
+        .context("Unable to redact event")?;
     Ok(())
 }
 
+/// Redacts the latest messages of a user in every room, skipping messages that are already redacted
+pub async fn send_purge_message(
+    user: &UserId,
+    count: u32,
+    rooms: HashSet<OwnedRoomId>,
+    client: &MatrixClient,
+) -> Vec<RoomOutcome> {
+    let senders = [user.to_owned()];
+    let mut outcomes = Vec::new();
+    for room_id in rooms {
+        debug!(
+            "Purging {} messages of user {} in room {}...",
+            count, user, room_id
+        );
+        let mut remaining = count;
+        let mut failed = 0;
+        let mut from = None;
+        let mut error = None;
+        while remaining > 0 {
+            let mut req = get_message_events::v3::Request::backward(&room_id);
+            req.from = from.as_deref();
+            req.limit = uint!(50);
+            req.filter.senders = Some(&senders);
+            let response = match client.send_request(req).await {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("{:?}", e);
+                    error = Some(format!("Unable to fetch messages: {}", e));
+                    break;
+                }
+            };
+            for raw_event in &response.chunk {
+                match raw_event.deserialize() {
+                    Ok(AnyTimelineEvent::MessageLike(e))
+                        if remaining > 0 && e.original_content().is_some() =>
+                    {
+                        if let Err(e) =
+                            send_redact_message(&room_id, e.event_id(), None, client).await
+                        {
+                            error!("{:#}", e);
+                            failed += 1;
+                        }
+                        remaining -= 1;
+                    }
+                    Ok(_) => (),
+                    Err(e) => debug!("{:?}", e),
+                }
+            }
+            match response.end {
+                Some(v) if !response.chunk.is_empty() => from = Some(v),
+                _ => break,
+            }
+        }
+        if error.is_none() && failed > 0 {
+            error = Some(format!("Unable to redact {} messages", failed));
+        }
+        outcomes.push(RoomOutcome {
+            room_id: room_id.to_string(),
+            error,
+        });
+    }
+    outcomes
+}
+
 pub async fn accept_invite(
     sender: &UserId,
     room_id: Option<OwnedRoomId>,
@@ -74,3 +615,14 @@ pub async fn reject_invite(
     info!("Rejected invite from unathorized user {}", sender);
     Ok(())
 }
+
+/// Turns the result of an action in a room into its outcome, logging any error
+pub fn outcome<T, E: Display>(room_id: &RoomId, result: Result<T, E>) -> RoomOutcome {
+    RoomOutcome {
+        room_id: room_id.to_string(),
+        error: result.err().map(|e| {
+            error!("{:#}", e);
+            format!("{:#}", e)
+        }),
+    }
+}
@@ -2,22 +2,52 @@
 //! plus main loop initialization.
 
 use super::MatrixClient;
-use crate::messages::{MatrixInviteType, MatrixMessage, MatrixMessageType};
+use crate::config::MatrixResponderConfig;
+use crate::database::models::{
+    ModerationAction, ModerationRecord, ResponseRecord, ScheduledExpiry, SentResponse,
+};
+use crate::helpers::{
+    remove_response_record, remove_scheduled_expiries, save_response_record, save_scheduled_expiry,
+    stored_response_record,
+};
+use crate::messages::{
+    MatrixInviteType, MatrixMessage, MatrixMessageType, MatrixReply, MatrixResponsesMessage,
+};
 use crate::services::matrix::matrix_handlers::responders::{
-    accept_invite, reject_invite, send_ban_message, send_message,
+    accept_invite, audit, edit_message, outcome, publish_policy_ban, reject_invite,
+    send_ban_message, send_kick_message, send_message, send_mute_message, send_policy_ban_message,
+    send_purge_message, send_redact_message, send_unban_message, send_unmute_message,
+    send_warn_message, send_welcome_message, unpublish_policy_ban,
 };
+use native_db::Database;
+use ruma::{EventId, OwnedRoomId, UserId};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, watch};
 use tracing::{error, info, trace};
 
 /// Struct representing all required data for a functioning bot instance.
 pub struct MatrixResponder {
     recv: mpsc::Receiver<MatrixMessage>,
+    /// Configuration data, replaced when the config is reloaded.
+    config: watch::Receiver<Arc<MatrixResponderConfig>>,
+    /// Storage data.
+    storage: &'static Database<'static>,
 }
 
 impl MatrixResponder {
     /// Loads storage data, config data, and then creates a reqwest client and then returns a Bot instance.
-    pub fn new(recv: mpsc::Receiver<MatrixMessage>) -> anyhow::Result<Self> {
-        Ok(Self { recv })
+    pub fn new(
+        recv: mpsc::Receiver<MatrixMessage>,
+        config: watch::Receiver<Arc<MatrixResponderConfig>>,
+        storage: &'static Database<'static>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            recv,
+            config,
+            storage,
+        })
     }
 
     /// Used to start main program loop for the bot.
@@ -44,35 +74,335 @@ impl MatrixResponder {
         message: Option<MatrixMessage>,
         client: &MatrixClient,
     ) -> anyhow::Result<()> {
-        match message {
+        let (audit_room, policy_publish_room) = {
+            let config = self.config.borrow();
+            (
+                config.audit_room.clone(),
+                config.policy_publish_room.clone(),
+            )
+        };
+        let record = match message {
             Some(v) => match v.message {
                 MatrixMessageType::Response(m) => {
-                    if let Err(e) = send_message(&client, v.room_id.unwrap(), m).await {
+                    if let Err(e) = send_message(client, v.room_id.unwrap(), m, v.reply_to).await {
                         error!("{}", e);
                     }
+                    return Ok(());
                 }
-                MatrixMessageType::Invite(m) => match m.kind {
-                    MatrixInviteType::Accept => {
-                        if let Err(e) = accept_invite(&m.sender, v.room_id, &client).await {
-                            error!("{}", e);
+                MatrixMessageType::Responses(m) => {
+                    match (v.room_id, v.reply_to) {
+                        (Some(room_id), Some(reply_to)) => {
+                            self.send_responses(m, room_id, reply_to, client).await
                         }
+                        _ => error!("Responses were not provided with room_id and reply_to"),
                     }
-                    MatrixInviteType::Reject => {
-                        if let Err(e) = reject_invite(&m.sender, v.room_id, &client).await {
-                            error!("{}", e);
+                    return Ok(());
+                }
+                MatrixMessageType::Welcome(m) => {
+                    let room_id = match v.room_id {
+                        Some(v) => v,
+                        None => {
+                            error!("Welcome message was not provided with room_id");
+                            return Ok(());
                         }
+                    };
+                    if let Err(e) =
+                        send_welcome_message(&m.users, &m.template, room_id, client).await
+                    {
+                        error!("{:#}", e);
                     }
-                },
+                    return Ok(());
+                }
+                MatrixMessageType::Invite(m) => {
+                    let room_id = match v.room_id {
+                        Some(v) => v,
+                        None => {
+                            error!("Invite message was not provided with room_id");
+                            return Ok(());
+                        }
+                    };
+                    let (action, result) = match m.kind {
+                        MatrixInviteType::Accept => (
+                            ModerationAction::AcceptInvite,
+                            accept_invite(&m.sender, Some(room_id.clone()), client).await,
+                        ),
+                        MatrixInviteType::Reject => (
+                            ModerationAction::RejectInvite,
+                            reject_invite(&m.sender, Some(room_id.clone()), client).await,
+                        ),
+                    };
+                    ModerationRecord::new(
+                        action,
+                        m.sender.to_string(),
+                        room_id.to_string(),
+                        None,
+                        vec![outcome(&room_id, result)],
+                    )
+                }
                 MatrixMessageType::Ban(m) => {
-                    if let Err(e) = send_ban_message(&m.user, m.reason, m.rooms, &client).await {
-                        error!("{}", e);
+                    let mut outcomes =
+                        send_ban_message(&m.user, m.reason.clone(), m.rooms.clone(), client).await;
+                    if let Some(room_id) = policy_publish_room.filter(|_| m.publish) {
+                        outcomes.push(outcome(
+                            &room_id,
+                            publish_policy_ban(&m.user, m.reason.clone(), &room_id, client).await,
+                        ));
                     }
+                    self.schedule(
+                        ModerationAction::Unban,
+                        &m.sender,
+                        &m.user,
+                        &m.rooms,
+                        m.duration,
+                    );
+                    ModerationRecord::new(
+                        ModerationAction::Ban,
+                        m.sender.to_string(),
+                        m.user.to_string(),
+                        timed_reason(m.reason, m.duration),
+                        outcomes,
+                    )
+                }
+                MatrixMessageType::Unban(m) => {
+                    self.schedule(ModerationAction::Unban, &m.sender, &m.user, &m.rooms, None);
+                    let publish_room = policy_publish_room.filter(|_| m.publish);
+                    let mut outcomes =
+                        send_unban_message(&m.user, m.reason.clone(), m.rooms, client).await;
+                    if let Some(room_id) = publish_room {
+                        outcomes.push(outcome(
+                            &room_id,
+                            unpublish_policy_ban(&m.user, &room_id, client).await,
+                        ));
+                    }
+                    ModerationRecord::new(
+                        ModerationAction::Unban,
+                        m.sender.to_string(),
+                        m.user.to_string(),
+                        m.reason,
+                        outcomes,
+                    )
+                }
+                MatrixMessageType::Kick(m) => ModerationRecord::new(
+                    ModerationAction::Kick,
+                    m.sender.to_string(),
+                    m.user.to_string(),
+                    m.reason.clone(),
+                    send_kick_message(&m.user, m.reason, m.rooms, client).await,
+                ),
+                MatrixMessageType::Mute(m) => {
+                    let outcomes =
+                        send_mute_message(&m.sender, &m.user, &m.rooms, self.storage, client).await;
+                    self.schedule(
+                        ModerationAction::Unmute,
+                        &m.sender,
+                        &m.user,
+                        &m.rooms,
+                        m.duration,
+                    );
+                    ModerationRecord::new(
+                        ModerationAction::Mute,
+                        m.sender.to_string(),
+                        m.user.to_string(),
+                        timed_reason(m.reason, m.duration),
+                        outcomes,
+                    )
+                }
+                MatrixMessageType::Unmute(m) => {
+                    self.schedule(ModerationAction::Unmute, &m.sender, &m.user, &m.rooms, None);
+                    ModerationRecord::new(
+                        ModerationAction::Unmute,
+                        m.sender.to_string(),
+                        m.user.to_string(),
+                        m.reason,
+                        send_unmute_message(&m.user, &m.rooms, self.storage, client).await,
+                    )
+                }
+                MatrixMessageType::Redact(m) => ModerationRecord::new(
+                    ModerationAction::Redact,
+                    m.sender.to_string(),
+                    m.event_id.to_string(),
+                    m.reason.clone(),
+                    vec![outcome(
+                        &m.room_id,
+                        send_redact_message(&m.room_id, &m.event_id, m.reason, client).await,
+                    )],
+                ),
+                MatrixMessageType::Purge(m) => ModerationRecord::new(
+                    ModerationAction::Purge,
+                    m.sender.to_string(),
+                    m.user.to_string(),
+                    Some(format!("latest {} messages", m.count)),
+                    send_purge_message(&m.user, m.count, m.rooms, client).await,
+                ),
+                MatrixMessageType::Warn(m) => ModerationRecord::new(
+                    ModerationAction::Warn,
+                    m.sender.to_string(),
+                    m.user.to_string(),
+                    Some(m.reason.clone()),
+                    vec![outcome(
+                        &m.room_id,
+                        send_warn_message(&m.user, &m.reason, m.room_id.clone(), client).await,
+                    )],
+                ),
+                MatrixMessageType::PolicyBan(m) => {
+                    let reason = if m.rule.reason.is_empty() {
+                        format!("policy rule in {}", m.policy_room)
+                    } else {
+                        format!("policy rule in {}: {}", m.policy_room, m.rule.reason)
+                    };
+                    let outcomes = send_policy_ban_message(
+                        &m.rule,
+                        Some(reason.clone()),
+                        m.rooms,
+                        &m.exempt,
+                        client,
+                    )
+                    .await;
+                    // Record each banned user separately so they show up in their moderation log
+                    for (target, outcomes) in outcomes {
+                        let record = ModerationRecord::new(
+                            ModerationAction::Ban,
+                            m.sender.to_string(),
+                            target,
+                            Some(reason.clone()),
+                            outcomes,
+                        );
+                        audit(record, audit_room.clone(), self.storage, client).await;
+                    }
+                    return Ok(());
                 }
             },
             None => {
                 info!("Matrix channel closed and empty. Exiting thread.");
+                return Ok(());
             }
-        }
+        };
+        audit(record, audit_room, self.storage, client).await;
         Ok(())
     }
+
+    /// Sends the responses to a message and records them, so they can be updated if the message is edited
+    ///
+    /// Responses to an edited message replace the recorded responses in order. Unchanged responses are left alone,
+    /// leftover recorded responses are redacted, and extra responses are sent as new replies
+    async fn send_responses(
+        &self,
+        m: MatrixResponsesMessage,
+        room_id: OwnedRoomId,
+        mut reply_to: MatrixReply,
+        client: &MatrixClient,
+    ) {
+        let mut previous = Vec::new();
+        if m.edited {
+            if let Some(record) = stored_response_record(self.storage, &reply_to.event_id) {
+                // Keep answering in the thread the original message was answered in
+                reply_to.thread_root = record
+                    .thread_root
+                    .and_then(|r| EventId::parse(r).ok())
+                    .or(reply_to.thread_root);
+                previous = record.responses;
+            }
+        }
+        let mut previous = previous.into_iter();
+        let mut sent = Vec::new();
+        for content in m.responses {
+            let serialized = match serde_json::to_string(&content) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("Unable to serialize response. Error is {}", e);
+                    continue;
+                }
+            };
+            let result = match previous.next() {
+                Some(old) if old.content == serialized => Ok(old.event_id),
+                Some(old) => match EventId::parse(&old.event_id) {
+                    Ok(event_id) => edit_message(client, &room_id, event_id, content)
+                        .await
+                        .map(|_| old.event_id),
+                    Err(e) => Err(e.into()),
+                },
+                None => send_message(client, room_id.clone(), content, Some(reply_to.clone()))
+                    .await
+                    .map(|e| e.to_string()),
+            };
+            match result {
+                Ok(event_id) => sent.push(SentResponse {
+                    event_id,
+                    content: serialized,
+                }),
+                Err(e) => error!("{:#}", e),
+            }
+        }
+        for old in previous {
+            let result = match EventId::parse(&old.event_id) {
+                Ok(event_id) => {
+                    send_redact_message(
+                        &room_id,
+                        &event_id,
+                        Some("Message responded to was edited".to_string()),
+                        client,
+                    )
+                    .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                error!("{:#}", e);
+            }
+        }
+
+        let result = if sent.is_empty() {
+            remove_response_record(self.storage, &reply_to.event_id)
+        } else {
+            save_response_record(
+                self.storage,
+                ResponseRecord::new(
+                    reply_to.event_id.to_string(),
+                    m.sender.to_string(),
+                    reply_to.thread_root.map(|r| r.to_string()),
+                    sent,
+                ),
+            )
+        };
+        if let Err(e) = result {
+            error!("{:#}", e);
+        }
+    }
+
+    /// Replaces any pending expiry of the action for the user with one after the duration if supplied
+    ///
+    /// Clearing pending expiries keeps a permanent ban or mute from being lifted by an earlier timed one
+    fn schedule(
+        &self,
+        action: ModerationAction,
+        sender: &UserId,
+        user: &UserId,
+        rooms: &HashSet<OwnedRoomId>,
+        duration: Option<Duration>,
+    ) {
+        if let Err(e) = remove_scheduled_expiries(self.storage, action, user.as_str()) {
+            error!("{:#}", e);
+        }
+        if let Some(duration) = duration {
+            let expiry = ScheduledExpiry::new(
+                action,
+                sender.to_string(),
+                user.to_string(),
+                rooms.iter().map(|r| r.to_string()).collect(),
+                duration,
+            );
+            if let Err(e) = save_scheduled_expiry(self.storage, expiry) {
+                error!("{:#}", e);
+            }
+        }
+    }
+}
+
+/// Adds the duration of a timed ban or mute to its reason
+fn timed_reason(reason: Option<String>, duration: Option<Duration>) -> Option<String> {
+    match (reason, duration) {
+        (Some(r), Some(d)) => Some(format!("{} (for {})", r, humantime::format_duration(d))),
+        (None, Some(d)) => Some(format!("for {}", humantime::format_duration(d))),
+        (r, None) => r,
+    }
 }
@@ -0,0 +1,120 @@
+//! Lifts timed bans and mutes once they expire, and removes expired records of responses
+//!
+//! Expiries are stored in the database so they survive restarts, and are lifted through the
+//! matrix responder so they are audited like any other moderation action
+
+use crate::database::models::{ModerationAction, ScheduledExpiry};
+use crate::helpers::{
+    due_scheduled_expiries, remove_expired_response_records, remove_scheduled_expiry,
+};
+use crate::messages::{MatrixMessage, MatrixMessageType, MatrixUnbanMessage, MatrixUnmuteMessage};
+use anyhow::{bail, Context};
+use native_db::Database;
+use ruma::{RoomId, UserId};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::watch::Receiver;
+use tracing::{debug, error, trace};
+
+/// How often the database is checked for expiries that are due
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How often expired records of responses are removed from the database
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Struct representing all required data for the scheduler.
+pub struct Scheduler {
+    send: Sender<MatrixMessage>,
+    storage: &'static Database<'static>,
+}
+
+impl Scheduler {
+    pub fn new(send: Sender<MatrixMessage>, storage: &'static Database<'static>) -> Self {
+        Self { send, storage }
+    }
+
+    /// Checks for due expiries on startup and then every `CHECK_INTERVAL` until shutdown
+    ///
+    /// Expired response records are removed on startup and then every `PRUNE_INTERVAL`
+    pub async fn start(&self, mut shutdown_rx: Receiver<bool>) {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut prune_interval = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => {
+                    trace!("Received shutdown on scheduler thread");
+                    break;
+                },
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_due_expiries().await {
+                        error!("{:#}", e);
+                    }
+                }
+                _ = prune_interval.tick() => {
+                    match remove_expired_response_records(self.storage) {
+                        Ok(v) => debug!("Removed {} expired response records", v),
+                        Err(e) => error!("{:#}", e),
+                    }
+                }
+            }
+        }
+        trace!("Scheduler shutdown complete")
+    }
+
+    /// Sends the unban or unmute of every due expiry to the responder, removing them once sent
+    async fn run_due_expiries(&self) -> anyhow::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        for expiry in due_scheduled_expiries(self.storage, now) {
+            debug!("Running scheduled expiry {:?}", expiry);
+            match expiry_message(&expiry) {
+                Ok(message) => {
+                    if self
+                        .send
+                        .send(MatrixMessage {
+                            room_id: None,
+                            reply_to: None,
+                            message,
+                        })
+                        .await
+                        .is_err()
+                    {
+                        bail!("Channel closed. Unable to send message.");
+                    }
+                }
+                Err(e) => error!("Dropping invalid scheduled expiry. Error is {:#}", e),
+            }
+            remove_scheduled_expiry(self.storage, &expiry.id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the message that lifts the ban or mute of an expiry
+fn expiry_message(expiry: &ScheduledExpiry) -> anyhow::Result<MatrixMessageType> {
+    let sender = UserId::parse(expiry.actor.as_str()).context("Invalid actor")?;
+    let user = UserId::parse(expiry.user.as_str()).context("Invalid user")?;
+    let rooms = expiry
+        .rooms
+        .iter()
+        .map(|r| RoomId::parse(r.as_str()))
+        .collect::<Result<_, _>>()
+        .context("Invalid room")?;
+    match expiry.action {
+        ModerationAction::Unban => Ok(MatrixMessageType::Unban(MatrixUnbanMessage {
+            sender,
+            user,
+            reason: Some("Ban expired".to_string()),
+            rooms,
+            publish: false,
+        })),
+        ModerationAction::Unmute => Ok(MatrixMessageType::Unmute(MatrixUnmuteMessage {
+            sender,
+            user,
+            reason: Some("Mute expired".to_string()),
+            rooms,
+        })),
+        v => bail!("Unable to schedule action {:?}", v),
+    }
+}
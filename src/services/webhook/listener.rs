@@ -1,7 +1,8 @@
-use crate::config::{Config, WebhookListenerConfig};
+use crate::config::WebhookListenerConfig;
 use crate::messages::MatrixMessage;
-use crate::services::webhook::webhook_handlers::message_fn;
+use crate::services::webhook::webhook_handlers::{github_fn, message_fn};
 use axum::{extract::Extension, routing::post, Router};
+use native_db::Database;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::watch::Receiver;
@@ -9,21 +10,29 @@ use tracing::{error, trace};
 
 pub struct WebhookListener {
     pub send: Sender<MatrixMessage>,
-    pub config: WebhookListenerConfig,
+    /// Current config, replaced when the config is reloaded
+    pub config: Receiver<Arc<WebhookListenerConfig>>,
+    pub storage: &'static Database<'static>,
 }
 
 impl WebhookListener {
-    pub fn new(config: &Config, send: Sender<MatrixMessage>) -> Self {
-        let config = WebhookListenerConfig {
-            token: config.webhook_token.clone(),
-        };
-        WebhookListener { send, config }
+    pub fn new(
+        config: Receiver<Arc<WebhookListenerConfig>>,
+        send: Sender<MatrixMessage>,
+        storage: &'static Database<'static>,
+    ) -> Self {
+        WebhookListener {
+            send,
+            config,
+            storage,
+        }
     }
 
     pub async fn start(self, mut shutdown_rx: Receiver<bool>) {
         let state = Arc::new(self);
         let app = Router::new()
             .route("/message", post(message_fn))
+            .route("/github", post(github_fn))
             .layer(Extension(state));
 
         // TODO: enable customized binding of ip and port
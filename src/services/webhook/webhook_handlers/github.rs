@@ -0,0 +1,396 @@
+//! Handler for signed GitHub webhooks that posts notifications for repo activity
+
+use crate::events::{Event, NOTIFIABLE_EVENTS};
+use crate::helpers::{
+    cache_search_result, escape_html, remove_cached_search_result, SearchResult, SearchResultKind,
+    SearchResultState,
+};
+use crate::messages::{MatrixMessage, MatrixMessageType};
+use crate::services::webhook::listener::WebhookListener;
+use axum::{body::Bytes, extract::Extension, http::HeaderMap, http::StatusCode};
+use hmac::{Hmac, Mac};
+use native_db::Database;
+use reqwest::Url;
+use ruma::events::room::message::RoomMessageEventContent;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use tracing::{debug, error, info, trace};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Maximum number of commits listed in a push notification
+const MAX_LISTED_COMMITS: usize = 5;
+
+pub async fn github(
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<WebhookListener>>,
+    body: Bytes,
+) -> StatusCode {
+    let event_name = match headers.get("X-GitHub-Event").map(|v| v.to_str()) {
+        Some(Ok(v)) => v.to_owned(),
+        _ => {
+            debug!("Github webhook is missing X-GitHub-Event header");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    let signature = match headers.get("X-Hub-Signature-256").map(|v| v.to_str()) {
+        Some(Ok(v)) => v.to_owned(),
+        _ => {
+            debug!("Github webhook is missing X-Hub-Signature-256 header");
+            return StatusCode::UNAUTHORIZED;
+        }
+    };
+    let repo = match serde_json::from_slice::<WebhookRepository>(&body) {
+        Ok(v) => v.repository.full_name.to_lowercase(),
+        Err(e) => {
+            debug!(
+                "Unable to find repository in github webhook. Error is {}",
+                e
+            );
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    let config = state.config.borrow().clone();
+    let webhook = match config.github_webhooks.get(repo.as_str()) {
+        Some(v) => v,
+        None => {
+            debug!("Github webhook received for unconfigured repo {}", repo);
+            return StatusCode::UNAUTHORIZED;
+        }
+    };
+    if !verify_signature(webhook.secret.as_bytes(), &signature, &body) {
+        debug!("Github webhook for repo {} has an invalid signature", repo);
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let event = match parse_event(&event_name, &body) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            debug!(
+                "Github webhook event {} for {} is not handled, ignoring",
+                event_name, repo
+            );
+            return StatusCode::OK;
+        }
+        Err(e) => {
+            error!("{}", e);
+            return StatusCode::UNPROCESSABLE_ENTITY;
+        }
+    };
+    trace!("Github webhook event is {:?}", event);
+    refresh_search_cache(state.storage, &event);
+
+    let (plain, html) = match format_event(&event) {
+        Some(v) => v,
+        None => {
+            info!("Received github {} event for {}", event_name, repo);
+            return StatusCode::OK;
+        }
+    };
+    let rooms = webhook.rooms_for(&event);
+    if rooms.is_empty() {
+        debug!(
+            "No rooms configured for github {} event for {}, doing nothing",
+            event_name, repo
+        );
+        return StatusCode::OK;
+    }
+    for room_id in rooms {
+        let matrix_message = MatrixMessage {
+            room_id: Some(room_id),
+            reply_to: None,
+            message: MatrixMessageType::Response(RoomMessageEventContent::notice_html(
+                plain.clone(),
+                html.clone(),
+            )),
+        };
+        if state.send.clone().send(matrix_message).await.is_err() {
+            error!("Channel closed. Unable to send github notification.");
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        };
+    }
+    StatusCode::OK
+}
+
+/// Parses the payload of the event named by the `X-GitHub-Event` header
+///
+/// Events that can not be parsed, or parse as a different event, are only an error if the bot posts
+/// notifications for them. Others are not handled and return `None`, so GitHub does not mark them as failed
+pub(super) fn parse_event(event_name: &str, body: &[u8]) -> Result<Option<Event>, String> {
+    let error = match serde_json::from_slice::<Event>(body) {
+        Ok(v) if v.name() == event_name => return Ok(Some(v)),
+        Ok(v) => format!(
+            "Github webhook event {} was parsed as {}",
+            event_name,
+            v.name()
+        ),
+        Err(e) => format!(
+            "Unable to parse github webhook event {}. Error is {}",
+            event_name, e
+        ),
+    };
+    if NOTIFIABLE_EVENTS.contains(&event_name) {
+        Err(error)
+    } else {
+        debug!("{}", error);
+        Ok(None)
+    }
+}
+
+/// Verifies the `X-Hub-Signature-256` header value against an HMAC of the body using the repo secret
+pub(super) fn verify_signature(secret: &[u8], signature: &str, body: &[u8]) -> bool {
+    let signature = match signature.strip_prefix("sha256=").map(hex::decode) {
+        Some(Ok(v)) => v,
+        _ => return false,
+    };
+    let mut mac = match HmacSha256::new_from_slice(secret) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Updates cached search results with the issue or pull contained in the event
+pub(super) fn refresh_search_cache(storage: &Database, event: &Event) {
+    let result = match event {
+        Event::Issues {
+            action,
+            issue,
+            repository,
+            ..
+        } => {
+            if action == "deleted" || action == "transferred" {
+                remove_cached_search_result(storage, &repository.full_name, issue.number as i64);
+                return;
+            }
+            SearchResult {
+                repo: repository.full_name.clone(),
+                number: issue.number as i64,
+                kind: SearchResultKind::Issue,
+                title: issue.title.clone(),
+                state: match issue.state.as_str() {
+                    "open" => SearchResultState::Open,
+                    _ => SearchResultState::Closed,
+                },
+                author: Some(issue.user.login.clone()),
+                labels: issue.labels.iter().map(|l| l.name.clone()).collect(),
+                url: match Url::parse(&issue.html_url) {
+                    Ok(v) => v,
+                    Err(_) => return,
+                },
+            }
+        }
+        Event::PullRequest {
+            pull_request,
+            repository,
+            ..
+        } => SearchResult {
+            repo: repository.full_name.clone(),
+            number: pull_request.number as i64,
+            kind: SearchResultKind::PullRequest,
+            title: pull_request.title.clone(),
+            state: match pull_request.state.as_str() {
+                "open" => SearchResultState::Open,
+                _ if pull_request.merged => SearchResultState::Merged,
+                _ => SearchResultState::Closed,
+            },
+            author: Some(pull_request.user.login.clone()),
+            labels: pull_request.labels.iter().map(|l| l.name.clone()).collect(),
+            url: match Url::parse(&pull_request.html_url) {
+                Ok(v) => v,
+                Err(_) => return,
+            },
+        },
+        _ => return,
+    };
+    trace!(
+        "Refreshing cached search result for {}#{}",
+        result.repo,
+        result.number
+    );
+    cache_search_result(storage, &result);
+}
+
+/// Builds plain and HTML notification text for supported events
+///
+/// Returns `None` if the event is not one that should be posted
+fn format_event(event: &Event) -> Option<(String, String)> {
+    match event {
+        Event::Issues {
+            action,
+            issue,
+            repository,
+            sender,
+        } => Some(notice(
+            &repository.full_name,
+            &sender.login,
+            &format!("{} issue", action),
+            &format!("#{}", issue.number),
+            &issue.html_url,
+            Some(&issue.title),
+        )),
+        Event::PullRequest {
+            action,
+            pull_request,
+            repository,
+            sender,
+            ..
+        } => {
+            let action = if action == "closed" && pull_request.merged {
+                "merged"
+            } else {
+                action
+            };
+            Some(notice(
+                &repository.full_name,
+                &sender.login,
+                &format!("{} pull request", action),
+                &format!("#{}", pull_request.number),
+                &pull_request.html_url,
+                Some(&pull_request.title),
+            ))
+        }
+        Event::IssueComment {
+            action,
+            comment,
+            issue,
+            repository,
+            sender,
+        } if action == "created" => Some(notice(
+            &repository.full_name,
+            &sender.login,
+            "commented on",
+            &format!("#{}", issue.number),
+            &comment.html_url,
+            Some(&issue.title),
+        )),
+        Event::Release {
+            action,
+            release,
+            repository,
+            sender,
+        } => Some(notice(
+            &repository.full_name,
+            &sender.login,
+            &format!("{} release", action),
+            &release.tag_name,
+            &release.html_url,
+            release.name.as_deref(),
+        )),
+        Event::Create {
+            _ref,
+            ref_type,
+            repository,
+            sender,
+            ..
+        } => Some(notice(
+            &repository.full_name,
+            &sender.login,
+            &format!("created {}", ref_type),
+            _ref,
+            &format!("{}/tree/{}", repository.html_url, _ref),
+            None,
+        )),
+        Event::Delete {
+            _ref,
+            ref_type,
+            repository,
+            sender,
+            ..
+        } => {
+            let plain = format!(
+                "[{}] {} deleted {} {}",
+                repository.full_name, sender.login, ref_type, _ref
+            );
+            let html = format!(
+                "[<b>{}</b>] {} deleted {} {}",
+                escape_html(&repository.full_name),
+                escape_html(&sender.login),
+                escape_html(ref_type),
+                escape_html(_ref)
+            );
+            Some((plain, html))
+        }
+        Event::Push {
+            _ref,
+            commits,
+            compare,
+            deleted,
+            repository,
+            sender,
+            ..
+        } if !deleted && !commits.is_empty() => {
+            let branch = _ref.trim_start_matches("refs/heads/");
+            let count = if commits.len() == 1 {
+                "1 commit".to_string()
+            } else {
+                format!("{} commits", commits.len())
+            };
+            let (mut plain, mut html) = notice(
+                &repository.full_name,
+                &sender.login,
+                &format!("pushed {} to", count),
+                branch,
+                compare,
+                None,
+            );
+            for commit in commits.iter().take(MAX_LISTED_COMMITS) {
+                let id: String = commit.id.chars().take(7).collect();
+                let message = commit.message.lines().next().unwrap_or_default();
+                plain.push_str(&format!("\n{} {}", id, message));
+                html.push_str(&format!(
+                    "<br><a href=\"{}\"><code>{}</code></a> {}",
+                    escape_html(&commit.url),
+                    id,
+                    escape_html(message)
+                ));
+            }
+            if commits.len() > MAX_LISTED_COMMITS {
+                let remaining = commits.len() - MAX_LISTED_COMMITS;
+                plain.push_str(&format!("\n...and {} more", remaining));
+                html.push_str(&format!("<br>...and {} more", remaining));
+            }
+            Some((plain, html))
+        }
+        _ => None,
+    }
+}
+
+/// Builds a single line notice in the form of `[owner/repo] user action target: title`
+fn notice(
+    repo: &str,
+    user: &str,
+    action: &str,
+    target: &str,
+    url: &str,
+    title: Option<&str>,
+) -> (String, String) {
+    let mut plain = format!("[{}] {} {} {}", repo, user, action, target);
+    let mut html = format!(
+        "[<b>{}</b>] {} {} <a href=\"{}\">{}</a>",
+        escape_html(repo),
+        escape_html(user),
+        escape_html(action),
+        escape_html(url),
+        escape_html(target)
+    );
+    if let Some(title) = title {
+        plain.push_str(&format!(": {}", title));
+        html.push_str(&format!(": {}", escape_html(title)));
+    }
+    plain.push_str(&format!(" {}", url));
+    (plain, html)
+}
+
+/// Minimal representation of a webhook payload used to look up repo settings before verification
+#[derive(Debug, Deserialize)]
+struct WebhookRepository {
+    repository: WebhookRepositoryName,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookRepositoryName {
+    full_name: String,
+}
@@ -16,9 +16,11 @@ pub async fn message(
     Json(message): Json<Message>,
     Extension(state): Extension<Arc<WebhookListener>>,
 ) -> StatusCode {
-    if *req_token.0 == *state.config.token {
+    let token = state.config.borrow().token.clone();
+    if *req_token.0 == *token {
         let matrix_message = MatrixMessage {
             room_id: Some(message.room_id.clone()),
+            reply_to: None,
             message: MatrixMessageType::Response(RoomMessageEventContent::notice_plain(
                 message.message.clone(),
             )),
@@ -33,6 +35,7 @@ pub async fn message(
             let formatted_text = response.format_text().unwrap();
             let matrix_message = MatrixMessage {
                 room_id: Some(message.room_id.clone()),
+                reply_to: None,
                 message: MatrixMessageType::Response(RoomMessageEventContent::text_html(
                     response.to_string(),
                     formatted_text,
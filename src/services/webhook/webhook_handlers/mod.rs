@@ -1,4 +1,9 @@
+#[cfg(test)]
+mod tests;
+
+mod github;
 mod message;
 
+pub use github::github as github_fn;
 pub use message::message as message_fn;
 //pub use message::Message;
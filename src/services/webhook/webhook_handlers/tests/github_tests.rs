@@ -0,0 +1,407 @@
+mod signature {
+    use crate::services::webhook::webhook_handlers::github::verify_signature;
+
+    const SECRET: &[u8] = b"It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+
+    #[test]
+    fn valid() {
+        assert!(verify_signature(
+            SECRET,
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            BODY
+        ))
+    }
+    #[test]
+    fn wrong_secret() {
+        assert!(!verify_signature(
+            b"Not the secret",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            BODY
+        ))
+    }
+    #[test]
+    fn tampered_body() {
+        assert!(!verify_signature(
+            SECRET,
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            b"Hello, World?"
+        ))
+    }
+    #[test]
+    fn missing_prefix() {
+        assert!(!verify_signature(
+            SECRET,
+            "757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            BODY
+        ))
+    }
+    #[test]
+    fn invalid_hex() {
+        assert!(!verify_signature(SECRET, "sha256=not-hex", BODY))
+    }
+}
+
+mod routing {
+    use crate::config::{GithubEventFilter, GithubWebhook, GithubWebhookRoute};
+    use crate::events::{Event, Issue, PullRequestDetails, Repository, User};
+    use ruma::{room_id, OwnedRoomId};
+    use std::collections::HashSet;
+
+    fn issues(action: &str) -> Event {
+        Event::Issues {
+            action: action.to_string(),
+            issue: Issue::default(),
+            repository: Repository::default(),
+            sender: User::default(),
+        }
+    }
+
+    fn pull_request(action: &str, merged: bool) -> Event {
+        Event::PullRequest {
+            action: action.to_string(),
+            number: 1,
+            pull_request: Box::new(PullRequestDetails {
+                merged,
+                ..Default::default()
+            }),
+            repository: Repository::default(),
+            sender: User::default(),
+        }
+    }
+
+    fn webhook() -> GithubWebhook {
+        GithubWebhook {
+            secret: "secret".into(),
+            rooms: HashSet::from([room_id!("!all:example.org").to_owned()]),
+            routes: vec![
+                GithubWebhookRoute {
+                    events: vec!["issues.opened".parse().unwrap()],
+                    rooms: HashSet::from([room_id!("!backend:example.org").to_owned()]),
+                },
+                GithubWebhookRoute {
+                    events: vec![
+                        "pull_request.merged".parse().unwrap(),
+                        "release".parse().unwrap(),
+                    ],
+                    rooms: HashSet::from([room_id!("!releases:example.org").to_owned()]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn parse_filter() {
+        let filter: GithubEventFilter = "push.master".parse().unwrap();
+        assert_eq!("push", &*filter.event);
+        assert_eq!(Some("master"), filter.qualifier.as_deref());
+
+        let filter: GithubEventFilter = "release".parse().unwrap();
+        assert_eq!("release", &*filter.event);
+        assert_eq!(None, filter.qualifier);
+    }
+    #[test]
+    fn parse_filter_invalid() {
+        assert!("not_an_event".parse::<GithubEventFilter>().is_err());
+        assert!("issues.".parse::<GithubEventFilter>().is_err());
+    }
+    #[test]
+    fn route_matching_action() {
+        let rooms = webhook().rooms_for(&issues("opened"));
+        let expected: HashSet<OwnedRoomId> = HashSet::from([
+            room_id!("!all:example.org").to_owned(),
+            room_id!("!backend:example.org").to_owned(),
+        ]);
+        assert_eq!(expected, rooms);
+    }
+    #[test]
+    fn route_not_matching_action() {
+        let rooms = webhook().rooms_for(&issues("closed"));
+        let expected: HashSet<OwnedRoomId> =
+            HashSet::from([room_id!("!all:example.org").to_owned()]);
+        assert_eq!(expected, rooms);
+    }
+    #[test]
+    fn route_merged_pull_request() {
+        let rooms = webhook().rooms_for(&pull_request("closed", true));
+        assert!(rooms.contains(room_id!("!releases:example.org")));
+
+        let rooms = webhook().rooms_for(&pull_request("closed", false));
+        assert!(!rooms.contains(room_id!("!releases:example.org")));
+    }
+}
+
+mod payloads {
+    use crate::events::Event;
+    use crate::services::webhook::webhook_handlers::github::parse_event;
+    use serde_json::{json, Value};
+
+    fn user() -> Value {
+        let mut user = json!({ "id": 1, "login": "octocat", "site_admin": false, "type": "User" });
+        for field in [
+            "avatar_url",
+            "events_url",
+            "followers_url",
+            "following_url",
+            "gists_url",
+            "gravatar_id",
+            "html_url",
+            "organizations_url",
+            "received_events_url",
+            "repos_url",
+            "starred_url",
+            "subscriptions_url",
+            "url",
+        ]
+        .iter()
+        {
+            user[*field] = json!("");
+        }
+        user
+    }
+
+    fn repository() -> Value {
+        let mut repo = json!({
+            "description": null,
+            "full_name": "jellyfin/jellyfin",
+            "has_downloads": true,
+            "has_issues": true,
+            "has_pages": false,
+            "has_wiki": false,
+            "homepage": null,
+            "html_url": "https://github.com/jellyfin/jellyfin",
+            "language": null,
+            "mirror_url": null,
+            "owner": user(),
+            "private": false,
+            "pushed_at": null,
+        });
+        for field in [
+            "forks",
+            "forks_count",
+            "id",
+            "open_issues",
+            "open_issues_count",
+            "size",
+            "stargazers_count",
+            "watchers",
+            "watchers_count",
+        ]
+        .iter()
+        {
+            repo[*field] = json!(0);
+        }
+        for field in [
+            "archive_url",
+            "assignees_url",
+            "blobs_url",
+            "branches_url",
+            "clone_url",
+            "collaborators_url",
+            "comments_url",
+            "commits_url",
+            "compare_url",
+            "contents_url",
+            "contributors_url",
+            "created_at",
+            "default_branch",
+            "downloads_url",
+            "events_url",
+            "forks_url",
+            "git_commits_url",
+            "git_refs_url",
+            "git_tags_url",
+            "git_url",
+            "hooks_url",
+            "issue_comment_url",
+            "issue_events_url",
+            "issues_url",
+            "keys_url",
+            "labels_url",
+            "languages_url",
+            "merges_url",
+            "milestones_url",
+            "name",
+            "notifications_url",
+            "pulls_url",
+            "releases_url",
+            "ssh_url",
+            "stargazers_url",
+            "statuses_url",
+            "subscribers_url",
+            "subscription_url",
+            "svn_url",
+            "tags_url",
+            "teams_url",
+            "trees_url",
+            "updated_at",
+            "url",
+        ]
+        .iter()
+        {
+            repo[*field] = json!("");
+        }
+        repo
+    }
+
+    fn create(description: Value) -> Value {
+        json!({
+            "description": description,
+            "master_branch": "master",
+            "pusher_type": "user",
+            "ref": "v10.9.0",
+            "ref_type": "tag",
+            "repository": repository(),
+            "sender": user(),
+        })
+    }
+
+    #[test]
+    fn create_with_description() {
+        let event: Event =
+            serde_json::from_value(create(json!("The Free Software Media System"))).unwrap();
+        assert_eq!("create", event.name());
+        assert_eq!(Some("tag"), event.qualifier());
+    }
+    #[test]
+    fn create_without_description() {
+        let event: Event = serde_json::from_value(create(Value::Null)).unwrap();
+        assert_eq!("create", event.name());
+        assert_eq!(Some("tag"), event.qualifier());
+    }
+    #[test]
+    fn delete() {
+        let event: Event = serde_json::from_value(json!({
+            "pusher_type": "user",
+            "ref": "feature",
+            "ref_type": "branch",
+            "repository": repository(),
+            "sender": user(),
+        }))
+        .unwrap();
+        assert_eq!("delete", event.name());
+        assert_eq!(Some("branch"), event.qualifier());
+    }
+    #[test]
+    fn parsed_by_header() {
+        let body = serde_json::to_vec(&create(Value::Null)).unwrap();
+        let event = parse_event("create", &body).unwrap().unwrap();
+        assert_eq!("create", event.name());
+    }
+    #[test]
+    fn unhandled_event_ignored() {
+        // Star payloads parse as a watch event
+        let body = serde_json::to_vec(&json!({
+            "action": "created",
+            "starred_at": "2024-01-01T00:00:00Z",
+            "repository": repository(),
+            "sender": user(),
+        }))
+        .unwrap();
+        assert!(parse_event("star", &body).unwrap().is_none());
+        assert!(parse_event("ping", b"{}").unwrap().is_none());
+    }
+    #[test]
+    fn invalid_notifiable_event() {
+        assert!(parse_event("issues", b"{}").is_err());
+        let body = serde_json::to_vec(&create(Value::Null)).unwrap();
+        assert!(parse_event("delete", &body).is_err());
+    }
+}
+
+mod search_cache {
+    use crate::events::{Event, Issue, Repository, User};
+    use crate::forges::GithubBackend;
+    use crate::services::webhook::webhook_handlers::github::refresh_search_cache;
+    use crate::test_helpers::database;
+    use native_db::Database;
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn issues(action: &str, title: &str) -> Event {
+        Event::Issues {
+            action: action.to_string(),
+            issue: Issue {
+                number: 12,
+                title: title.to_string(),
+                state: "closed".to_string(),
+                html_url: "https://github.com/jellyfin/jellyfin/issues/12".to_string(),
+                ..Default::default()
+            },
+            repository: Repository {
+                full_name: "jellyfin/jellyfin".to_string(),
+                ..Default::default()
+            },
+            sender: User::default(),
+        }
+    }
+
+    async fn mock_github(calls: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": {
+                    "rateLimit": { "cost": 1, "remaining": 4999, "resetAt": "2030-01-01T00:00:00Z" },
+                    "lookup0": {
+                        "issueOrPullRequest": {
+                            "__typename": "Issue",
+                            "number": 12,
+                            "title": "Playback fails",
+                            "state": "OPEN",
+                            "url": "https://github.com/jellyfin/jellyfin/issues/12",
+                            "resourcePath": "/jellyfin/jellyfin/issues/12",
+                            "author": { "__typename": "User", "login": "someone" },
+                            "labels": { "nodes": [] }
+                        }
+                    }
+                }
+            })))
+            .expect(calls)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn title(server: &MockServer, storage: &Database<'_>) -> String {
+        let client = reqwest::Client::new();
+        let url = format!("{}/graphql", server.uri());
+        let user_agent = HeaderValue::from_static("jellyfin-matrix-bot/tester");
+        let backend = GithubBackend {
+            api_client: &client,
+            graphql_url: &url,
+            token: "secret",
+            user_agent: &user_agent,
+            storage,
+            cache_ttl: Duration::from_secs(60),
+        };
+        let search = crate::forges::Search {
+            repo: "jellyfin/jellyfin".to_string(),
+            number: 12,
+        };
+        let (mut outcomes, _) = backend.lookup(&[search], &[]).await.unwrap();
+        outcomes.remove(0).unwrap().title
+    }
+
+    #[tokio::test]
+    async fn updated_by_webhook() {
+        let server = mock_github(1).await;
+        let storage = database();
+        assert_eq!("Playback fails", title(&server, &storage).await);
+
+        refresh_search_cache(&storage, &issues("edited", "Playback fails on Android"));
+        assert_eq!("Playback fails on Android", title(&server, &storage).await);
+    }
+    #[tokio::test]
+    async fn invalidated_by_webhook() {
+        let server = mock_github(2).await;
+        let storage = database();
+        assert_eq!("Playback fails", title(&server, &storage).await);
+
+        refresh_search_cache(&storage, &issues("deleted", "Playback fails"));
+        assert_eq!("Playback fails", title(&server, &storage).await);
+    }
+}
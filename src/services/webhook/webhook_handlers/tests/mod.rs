@@ -0,0 +1 @@
+mod github_tests;
@@ -0,0 +1,61 @@
+//! Config and database factories shared by the tests of every module
+
+use crate::config::{Config, MatrixListenerConfig, RawConfig};
+use crate::database::models::{
+    AccessToken, CorrectionTimeCooldown, GithubRateLimit, GithubSearchCache, GroupPing, LastSync,
+    ModerationRecord, MutedUser, PolicyRule, ResponseRecord, RoomPowerLevels, ScheduledExpiry,
+    TextExpansion,
+};
+use native_db::{Database, DatabaseBuilder};
+
+/// Parses a config file with only the required settings, adding the lines to the end of its general section and
+/// the sections after it
+pub fn raw_config(general: &str, sections: &str) -> RawConfig {
+    toml::from_str(&format!(
+        r#"
+[general]
+enable_unit_conversions = false
+enable_corrections = false
+webhook_token = "token"
+{}
+
+[matrix_authentication]
+url = "https://matrix.org"
+username = "@bot:matrix.org"
+password = "password"
+
+{}
+"#,
+        general, sections
+    ))
+    .unwrap()
+}
+
+/// Loads the listener config of a config file made by `raw_config`, with @admin:matrix.org as the authorized user
+pub fn listener_config(general: &str, sections: &str) -> MatrixListenerConfig {
+    let raw = raw_config(
+        &format!("authorized_users = ['@admin:matrix.org']\n{}", general),
+        sections,
+    );
+    MatrixListenerConfig::new(&Config::from_raw(raw).unwrap())
+}
+
+/// Creates an empty in-memory database with every model defined
+pub fn database() -> Database<'static> {
+    let mut builder = Box::new(DatabaseBuilder::new());
+    builder.define::<AccessToken>().unwrap();
+    builder.define::<LastSync>().unwrap();
+    builder.define::<CorrectionTimeCooldown>().unwrap();
+    builder.define::<GithubRateLimit>().unwrap();
+    builder.define::<GithubSearchCache>().unwrap();
+    builder.define::<TextExpansion>().unwrap();
+    builder.define::<GroupPing>().unwrap();
+    builder.define::<RoomPowerLevels>().unwrap();
+    builder.define::<ModerationRecord>().unwrap();
+    builder.define::<ScheduledExpiry>().unwrap();
+    builder.define::<PolicyRule>().unwrap();
+    builder.define::<ResponseRecord>().unwrap();
+    builder.define::<MutedUser>().unwrap();
+    let builder: &'static DatabaseBuilder = Box::leak(builder);
+    builder.create_in_memory().unwrap()
+}